@@ -0,0 +1,609 @@
+use paralelizace_3d_renderovani::framework::{self, FrameSample, FrameStats};
+use paralelizace_3d_renderovani::shapes::{self, Mesh, MeshId, Object, TextureId};
+use std::{borrow::Cow, f32::consts, mem, ops::Range, str::FromStr};
+use std::time::Instant;
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::{DeviceExt, RenderEncoder};
+
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+const DEFAULT_WIDTH: u32 = 800;
+const DEFAULT_HEIGHT: u32 = 600;
+const DEFAULT_OBJECTS: &str = "10,100,1000,10000,100000";
+const DEFAULT_FRAMES: u32 = 100;
+// Frames rendered before the measurement starts
+const WARMUP_FRAMES: u32 = 5;
+
+// How the scene is submitted to the GPU
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Strategy {
+    PerObject, // One draw_indexed per object
+    Instanced, // One instanced draw_indexed per batch
+    MultiDrawIndirect, // One multi_draw_indexed_indirect for all batches
+    RenderBundle, // Per-object draws recorded once into a render bundle
+}
+
+const STRATEGIES: [Strategy; 4] = [
+    Strategy::PerObject,
+    Strategy::Instanced,
+    Strategy::MultiDrawIndirect,
+    Strategy::RenderBundle,
+];
+
+impl Strategy {
+    fn name(self) -> &'static str {
+        match self {
+            Strategy::PerObject => "draw_indexed per object",
+            Strategy::Instanced => "instanced per batch",
+            Strategy::MultiDrawIndirect => "multi_draw_indexed_indirect",
+            Strategy::RenderBundle => "render bundle",
+        }
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "per-object" => Ok(Strategy::PerObject),
+            "instanced" => Ok(Strategy::Instanced),
+            "indirect" => Ok(Strategy::MultiDrawIndirect),
+            "bundle" => Ok(Strategy::RenderBundle),
+            _ => Err(format!("Unknown strategy '{}'", s)),
+        }
+    }
+}
+
+// Per-instance vertex data
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Instance {
+    model: [f32; 16],
+    color: [f32; 4],
+}
+
+// Indices of the batch's mesh and range of its instances
+struct BatchDraw {
+    indices: Range<u32>,
+    instances: Range<u32>,
+}
+
+// Resources shared by all scene sizes
+struct Renderer {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    vertex_buf: wgpu::Buffer,
+    index_buf: wgpu::Buffer,
+    index_format: wgpu::IndexFormat, // 16-bit unless the merged meshes need 32-bit indices
+    mesh_ranges: Vec<(MeshId, Range<u32>)>, // Indices of every mesh in index_buf
+}
+
+impl Renderer {
+    fn new(device: &wgpu::Device) -> Self {
+        // Create meshes
+        let mut meshes = [MeshId::CUBE, MeshId::CYLINDER, MeshId::SPHERE].map(|m_type| Mesh {
+            m_type,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            lods: Vec::new(),
+        });
+        for mesh in &mut meshes {
+            mesh.generate_vertices();
+        }
+
+        // Only the full level of detail is drawn
+        let lod_ranges = shapes::lod_ranges(&meshes.iter().collect::<Vec<_>>());
+        let mesh_ranges = meshes
+            .iter()
+            .zip(lod_ranges)
+            .map(|(mesh, lods)| {
+                let (count, first) = lods[0];
+                (mesh.m_type, first..first + count)
+            })
+            .collect();
+
+        let (vertex_data, index_data) = shapes::merge_index_vertex_data(&meshes.iter().collect())
+            .unwrap_or_else(|err| panic!("{}", err));
+
+        let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertex_data),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let index_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: index_data.as_bytes(),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                // Camera transform (projection * view matrix): mat4x4<f32>
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(64),
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl"))),
+        });
+
+        let vertex_buffers = [
+            wgpu::VertexBufferLayout {
+                array_stride: mem::size_of::<shapes::Vertex>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x2],
+            },
+            wgpu::VertexBufferLayout {
+                array_stride: mem::size_of::<Instance>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Instance,
+                attributes: &wgpu::vertex_attr_array![
+                    2 => Float32x4, 3 => Float32x4, 4 => Float32x4, 5 => Float32x4, // Model matrix
+                    6 => Float32x4 // Color
+                ],
+            },
+        ];
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &vertex_buffers,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(FORMAT.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Renderer {
+            pipeline,
+            bind_group_layout,
+            vertex_buf,
+            index_buf,
+            index_format: index_data.format(),
+            mesh_ranges,
+        }
+    }
+
+    fn mesh_range(&self, m_type: MeshId) -> Range<u32> {
+        self.mesh_ranges
+            .iter()
+            .find(|(t, _)| *t == m_type)
+            .map(|(_, range)| range.clone())
+            .unwrap()
+    }
+}
+
+// Grid of objects repeating the row of the gpu-driven-rendering example
+fn create_objects(count: usize) -> Vec<Object> {
+    let pattern = [
+        (MeshId::CUBE, TextureId::BLUE),
+        (MeshId::CYLINDER, TextureId::RED),
+        (MeshId::CUBE, TextureId::BLUE),
+        (MeshId::SPHERE, TextureId::YELLOW),
+    ];
+    let side = (count as f32).sqrt().ceil().max(1.0) as usize;
+
+    (0..count)
+        .map(|i| {
+            let (m_type, t_type) = pattern[i % pattern.len()];
+            Object {
+                transform_m: glam::Mat4::from_translation(glam::Vec3::new(
+                    3.0 * (i % side) as f32,
+                    3.0 * (i / side) as f32,
+                    0.0,
+                )),
+                m_type,
+                t_type,
+            }
+        })
+        .collect()
+}
+
+// Camera looking at the whole grid
+fn generate_matrix(count: usize, aspect_ratio: f32) -> glam::Mat4 {
+    let extent = 3.0 * (count as f32).sqrt().ceil().max(1.0);
+    let center = glam::Vec3::new(extent / 2.0, extent / 2.0, 0.0);
+    let eye = center + glam::Vec3::new(0.0, -extent, extent) + glam::Vec3::new(0.0, -5.0, 5.0);
+
+    let projection = glam::Mat4::perspective_rh(consts::FRAC_PI_4, aspect_ratio, 1.0, 4.0 * extent + 20.0);
+    let view = glam::Mat4::look_at_rh(eye, center, glam::Vec3::Z);
+    projection * view
+}
+
+fn color(t_type: TextureId) -> [f32; 4] {
+    match t_type {
+        TextureId::BLUE => [0.2, 0.4, 0.9, 1.0],
+        TextureId::RED => [0.9, 0.2, 0.2, 1.0],
+        TextureId::YELLOW => [0.95, 0.85, 0.2, 1.0],
+        _ => [1.0; 4],
+    }
+}
+
+// Scene of the given number of objects prepared for every strategy
+struct Scene<'a> {
+    renderer: &'a Renderer,
+    objects_count: usize,
+    instance_buf: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    draws: Vec<BatchDraw>,
+    indirect_buf: Option<wgpu::Buffer>, // None if MULTI_DRAW_INDIRECT isn't supported
+    bundle: Option<wgpu::RenderBundle>, // Recorded once the rest of the scene exists
+}
+
+impl<'a> Scene<'a> {
+    fn new(device: &wgpu::Device, renderer: &'a Renderer, objects_count: usize, aspect_ratio: f32) -> Self {
+        let objects = create_objects(objects_count);
+        let batches = shapes::get_batches_from_objects(&objects);
+
+        // Instances are ordered by batches, so every batch is a continuous range
+        let mut instances = Vec::with_capacity(objects_count);
+        let mut draws = Vec::with_capacity(batches.len());
+        for b in &batches {
+            let base_instance = instances.len() as u32;
+            instances.extend(b.transform_m.iter().map(|m| Instance {
+                model: m.to_cols_array(),
+                color: color(b.t_type),
+            }));
+            draws.push(BatchDraw {
+                indices: renderer.mesh_range(b.m_type),
+                instances: base_instance..instances.len() as u32,
+            });
+        }
+
+        let instance_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Instance Buffer"),
+            contents: bytemuck::cast_slice(&instances),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let mx_total = generate_matrix(objects_count, aspect_ratio);
+        let mx_ref: &[f32; 16] = mx_total.as_ref();
+        let uniform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Uniform Buffer"),
+            contents: bytemuck::cast_slice(mx_ref),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &renderer.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buf.as_entire_binding(),
+                },
+            ],
+            label: None,
+        });
+
+        // Without INDIRECT_FIRST_INSTANCE the base_instance of every batch would be ignored
+        let indirect_features = wgpu::Features::MULTI_DRAW_INDIRECT | wgpu::Features::INDIRECT_FIRST_INSTANCE;
+        let indirect_buf = if device.features().contains(indirect_features) {
+            let mut indirect_data: Vec<u8> = Vec::new();
+            for draw in &draws {
+                indirect_data.extend(
+                    wgpu::util::DrawIndexedIndirect {
+                        vertex_count: draw.indices.len() as u32,
+                        instance_count: draw.instances.len() as u32,
+                        base_index: draw.indices.start,
+                        vertex_offset: 0,
+                        base_instance: draw.instances.start,
+                    }.as_bytes()
+                );
+            }
+            Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Indirect buffer"),
+                contents: &indirect_data,
+                usage: wgpu::BufferUsages::INDIRECT,
+            }))
+        } else {
+            None
+        };
+
+        let mut scene = Scene {
+            renderer,
+            objects_count,
+            instance_buf,
+            bind_group,
+            draws,
+            indirect_buf,
+            bundle: None,
+        };
+
+        let mut bundle_encoder = device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
+            label: Some("Benchmark bundle"),
+            color_formats: &[Some(FORMAT)],
+            depth_stencil: None,
+            sample_count: 1,
+            multiview: None,
+        });
+        scene.record_per_object(&mut bundle_encoder);
+        let bundle = bundle_encoder.finish(&wgpu::RenderBundleDescriptor {
+            label: Some("Benchmark bundle"),
+        });
+        scene.bundle = Some(bundle);
+
+        scene
+    }
+
+    fn supports(&self, strategy: Strategy) -> bool {
+        strategy != Strategy::MultiDrawIndirect || self.indirect_buf.is_some()
+    }
+
+    // Number of draw commands recorded into the render pass
+    fn draw_calls(&self, strategy: Strategy) -> usize {
+        match strategy {
+            Strategy::PerObject => self.objects_count,
+            Strategy::Instanced => self.draws.len(),
+            Strategy::MultiDrawIndirect => 1,
+            Strategy::RenderBundle => 1,
+        }
+    }
+
+    fn bind<'b, E: RenderEncoder<'b>>(&'b self, encoder: &mut E) {
+        encoder.set_pipeline(&self.renderer.pipeline);
+        encoder.set_bind_group(0, &self.bind_group, &[]);
+        encoder.set_index_buffer(self.renderer.index_buf.slice(..), self.renderer.index_format);
+        encoder.set_vertex_buffer(0, self.renderer.vertex_buf.slice(..));
+        encoder.set_vertex_buffer(1, self.instance_buf.slice(..));
+    }
+
+    fn record_per_object<'b, E: RenderEncoder<'b>>(&'b self, encoder: &mut E) {
+        self.bind(encoder);
+        for draw in &self.draws {
+            for instance in draw.instances.clone() {
+                encoder.draw_indexed(draw.indices.clone(), 0, instance..instance + 1);
+            }
+        }
+    }
+
+    // Renders one frame and waits until the GPU finishes it
+    fn render(
+        &self,
+        strategy: Strategy,
+        view: &wgpu::TextureView,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> FrameSample {
+        let start = Instant::now();
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            match strategy {
+                Strategy::PerObject => self.record_per_object(&mut rpass),
+                Strategy::Instanced => {
+                    self.bind(&mut rpass);
+                    for draw in &self.draws {
+                        rpass.draw_indexed(draw.indices.clone(), 0, draw.instances.clone());
+                    }
+                }
+                Strategy::MultiDrawIndirect => {
+                    self.bind(&mut rpass);
+                    let indirect_buf = self.indirect_buf.as_ref().expect("MULTI_DRAW_INDIRECT isn't supported");
+                    rpass.multi_draw_indexed_indirect(indirect_buf, 0, self.draws.len() as u32);
+                }
+                Strategy::RenderBundle => rpass.execute_bundles(self.bundle.iter()),
+            }
+        }
+        let command_buffer = encoder.finish();
+        let encode_time = start.elapsed();
+
+        let submit_start = Instant::now();
+        queue.submit(Some(command_buffer));
+        let submit_time = submit_start.elapsed();
+
+        device.poll(wgpu::Maintain::Wait);
+
+        FrameSample::new(start.elapsed(), encode_time, submit_time)
+    }
+}
+
+// Result of one strategy on one scene size, None if the strategy isn't supported
+struct Row {
+    objects: usize,
+    strategy: Strategy,
+    draw_calls: usize,
+    stats: Option<FrameStats>,
+}
+
+fn format_table(rows: &[Row]) -> String {
+    let mut lines = vec![
+        String::from("| Objects | Strategy | Draw calls | Encode (ms) | Submit (ms) | CPU submission (ms) | Frame p50 (ms) | Frame p95 (ms) |"),
+        String::from("|--------:|:---------|-----------:|------------:|------------:|--------------------:|---------------:|---------------:|"),
+    ];
+
+    for row in rows {
+        let stats = match &row.stats {
+            Some(stats) => stats,
+            None => {
+                lines.push(format!(
+                    "| {} | {} | - | unsupported | | | | |",
+                    row.objects,
+                    row.strategy.name()
+                ));
+                continue;
+            }
+        };
+
+        let count = stats.samples().len();
+        let encode = stats.summary(count, |s| s.encode_ms).unwrap_or_default();
+        let submit = stats.summary(count, |s| s.submit_ms).unwrap_or_default();
+        let frame = stats.summary(count, |s| s.frame_ms).unwrap_or_default();
+
+        lines.push(format!(
+            "| {} | {} | {} | {:.3} | {:.3} | {:.3} | {:.3} | {:.3} |",
+            row.objects,
+            row.strategy.name(),
+            row.draw_calls,
+            encode.mean,
+            submit.mean,
+            encode.mean + submit.mean,
+            frame.p50,
+            frame.p95,
+        ));
+    }
+
+    lines.join("\n")
+}
+
+fn parse_list<T: FromStr>(name: &str, value: &str) -> Result<Vec<T>, String> {
+    value
+        .split(',')
+        .filter(|item| !item.trim().is_empty())
+        .map(|item| item.trim().parse().map_err(|_| format!("Invalid value '{}' of --{}", item, name)))
+        .collect()
+}
+
+fn options() -> Vec<framework::CliOption> {
+    vec![
+        framework::CliOption {
+            name: "objects",
+            value_name: "<list>",
+            help: "Comma separated scene sizes (default 10,100,1000,10000,100000)",
+        },
+        framework::CliOption {
+            name: "strategies",
+            value_name: "<list>",
+            help: "Comma separated strategies: per-object, instanced, indirect, bundle",
+        },
+        framework::CliOption {
+            name: "output",
+            value_name: "<path>",
+            help: "Write the comparison table into this file",
+        },
+    ]
+}
+
+fn main() {
+    let options = options();
+    let program = std::env::args().next().unwrap_or_else(|| String::from("benchmark"));
+
+    let parsed = framework::Args::from_env(&options).and_then(|args| {
+        let sizes: Vec<usize> = parse_list("objects", args.options.get("objects").unwrap_or(DEFAULT_OBJECTS))?;
+        let strategies: Vec<Strategy> = match args.options.get("strategies") {
+            Some(list) => parse_list("strategies", list)?,
+            None => STRATEGIES.to_vec(),
+        };
+        Ok((args, sizes, strategies))
+    });
+    let (args, sizes, strategies) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, framework::Args::usage(&program, &options));
+            std::process::exit(2);
+        }
+    };
+
+    if args.help {
+        println!("{}", framework::Args::usage(&program, &options));
+        return;
+    }
+
+    let framework::HeadlessSetup { adapter, device, queue, .. } =
+        pollster::block_on(framework::setup_headless(&args));
+    let info = adapter.get_info();
+    println!("Adapter: {} ({:?})", info.name, info.backend);
+
+    let (width, height) = (
+        args.width.unwrap_or(DEFAULT_WIDTH).max(1),
+        args.height.unwrap_or(DEFAULT_HEIGHT).max(1),
+    );
+    let target = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Benchmark target"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+    });
+    let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let frames = args.frames.unwrap_or(DEFAULT_FRAMES);
+    let renderer = Renderer::new(&device);
+    let mut rows = Vec::new();
+
+    for &objects in &sizes {
+        let scene = Scene::new(&device, &renderer, objects, width as f32 / height as f32);
+
+        for &strategy in &strategies {
+            let mut row = Row {
+                objects,
+                strategy,
+                draw_calls: scene.draw_calls(strategy),
+                stats: None,
+            };
+
+            if scene.supports(strategy) {
+                for _ in 0..WARMUP_FRAMES {
+                    scene.render(strategy, &view, &device, &queue);
+                }
+
+                let mut stats = FrameStats::new();
+                for _ in 0..frames {
+                    stats.push(scene.render(strategy, &view, &device, &queue));
+                }
+                row.stats = Some(stats);
+                println!("{} objects, {}: done", objects, strategy.name());
+            } else {
+                println!("{} objects, {}: not supported by the adapter", objects, strategy.name());
+            }
+
+            rows.push(row);
+        }
+    }
+
+    let table = format_table(&rows);
+    println!("\n{}", table);
+
+    if let Some(path) = args.options.get("output") {
+        match std::fs::write(path, table + "\n") {
+            Ok(()) => println!("Table saved to {}", path),
+            Err(err) => eprintln!("Unable to save the table to {}: {}", path, err),
+        }
+    }
+}
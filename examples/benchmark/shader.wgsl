@@ -0,0 +1,35 @@
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coord: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> transform: mat4x4<f32>;
+
+// Transformation matrix and color are per-instance vertex attributes,
+// so the instance is selected by base_instance on every backend
+@vertex
+fn vs_main(
+    @location(0) position: vec4<f32>,
+    @location(1) tex_coord: vec2<f32>,
+    @location(2) model_0: vec4<f32>,
+    @location(3) model_1: vec4<f32>,
+    @location(4) model_2: vec4<f32>,
+    @location(5) model_3: vec4<f32>,
+    @location(6) color: vec4<f32>,
+) -> VertexOutput {
+    var result: VertexOutput;
+    let model = mat4x4<f32>(model_0, model_1, model_2, model_3);
+    result.position = transform * (model * position);
+    result.tex_coord = tex_coord;
+    result.color = color;
+    return result;
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    // Darken the edges a little, so the shapes are distinguishable
+    let shade = 0.75 + 0.25 * (1.0 - abs(vertex.tex_coord.x - 0.5) * 2.0);
+    return vec4<f32>(vertex.color.rgb * shade, 1.0);
+}
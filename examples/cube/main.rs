@@ -1,8 +1,7 @@
-#[path = "../framework.rs"]
-mod framework;
+use paralelizace_3d_renderovani::framework;
 
 use bytemuck::{Pod, Zeroable};
-use std::{borrow::Cow, f32::consts, future::Future, mem, pin::Pin, task};
+use std::{borrow::Cow, f32::consts, mem};
 use wgpu::util::DeviceExt;
 
 #[repr(C)]
@@ -45,7 +44,7 @@ fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
     (vertex_data.to_vec(), index_data.to_vec())
 }
 
-struct Example {
+pub struct Example {
     vertex_buf: wgpu::Buffer,
     index_buf: wgpu::Buffer,
     index_count: usize,
@@ -72,7 +71,8 @@ impl framework::Example for Example {
         config: &wgpu::SurfaceConfiguration,
         _adapter: &wgpu::Adapter,
         device: &wgpu::Device,
-        queue: &wgpu::Queue,
+        _queue: &wgpu::Queue,
+        _options: &framework::Options,
     ) -> Self {
         // Create the vertex and index buffers
         let vertex_size = mem::size_of::<Vertex>();
@@ -251,10 +251,12 @@ impl framework::Example for Example {
         view: &wgpu::TextureView,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        spawner: &framework::Spawner,
+        _spawner: &framework::Spawner,
+        profiler: &mut framework::Profiler,
     ) {
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-        
+
+        let scope = profiler.begin_pass(&mut encoder, "Cube");
         {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
@@ -278,13 +280,35 @@ impl framework::Example for Example {
             rpass.set_index_buffer(self.index_buf.slice(..), wgpu::IndexFormat::Uint16);
             rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
             rpass.draw_indexed(0..self.index_count as u32, 0, 0..1);
-            if let Some(ref pipe) = self.pipeline_wire {
+        }
+        profiler.end_pass(&mut encoder, scope);
+
+        // Wireframe is drawn in its own pass, so it can be timed separately
+        if let Some(ref pipe) = self.pipeline_wire {
+            let scope = profiler.begin_pass(&mut encoder, "Wireframe");
+            {
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
                 rpass.set_pipeline(pipe);
+                rpass.set_bind_group(0, &self.bind_group, &[]);
+                rpass.set_index_buffer(self.index_buf.slice(..), wgpu::IndexFormat::Uint16);
+                rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
                 rpass.draw_indexed(0..self.index_count as u32, 0, 0..1);
             }
+            profiler.end_pass(&mut encoder, scope);
         }
 
-        queue.submit(Some(encoder.finish()));
+        profiler.submit(queue, Some(encoder.finish()));
     }
 }
 
@@ -1,23 +1,48 @@
-#[path = "../framework.rs"]
-mod framework;
-mod shapes;
-
-use shapes::{Object, Mesh, MeshType, TextureType};
+use paralelizace_3d_renderovani::framework;
+use paralelizace_3d_renderovani::shapes::{self, GltfScene, Object, Mesh, MeshId, ObjModel, ObjScene, TextureId};
+use paralelizace_3d_renderovani::culling::{self, BaseInstances, CullInstance, DrawCommand, DrawCompaction, OcclusionCulling};
+use paralelizace_3d_renderovani::scene::{ObjectId, Scene, SceneBuffers, SceneDescription};
 use std::{borrow::Cow, f32::consts, mem, vec::Vec};
 use std::path::Path;
 use wgpu::util::DeviceExt;
 
-struct Example {
+// Texture views with the sampler, kept for recreating the bind group
+struct Textures {
+    views: Vec<wgpu::TextureView>,
+    sampler: wgpu::Sampler,
+    binding_array: bool, // One view per texture, otherwise one view of a texture array
+}
+
+pub struct Example {
     vertex_buf: wgpu::Buffer,
     index_buf: wgpu::Buffer,
+    index_format: wgpu::IndexFormat, // 16-bit unless the merged meshes need 32-bit indices
+    bind_group_layout: wgpu::BindGroupLayout,
     bind_group: wgpu::BindGroup,
+    textures: Textures,
     uniform_buf: wgpu::Buffer,
-    indirect_buf: wgpu::Buffer,
-    batches_count: u32,
+    scene: Scene,
+    scene_buffers: SceneBuffers,
+    row_objects: Vec<Object>, // Objects of the scene file, repeated in every row
+    rows: Vec<Vec<ObjectId>>, // Objects of every row
+    row_change: i32, // Rows to add (positive) or remove (negative) before the next frame
+    spin: f32, // Rotation of the objects per frame in radians
+    index_ranges: Vec<(MeshId, Vec<(u32, u32)>)>, // Number of indices and the first index of every LOD of every mesh
+    lod_size: f32, // Screen size below which the coarser levels of detail are drawn
+    capabilities: framework::Capabilities,
+    size: (u32, u32),
+    mx_total: glam::Mat4,
+    culling: OcclusionCulling,
+    compaction: Option<Vec<DrawCompaction>>, // For every culling phase, with MULTI_DRAW_INDIRECT_COUNT
+    base_instances: BaseInstances,
+    multi_draw: bool, // Otherwise every command is drawn by its own draw_indexed_indirect
     pipeline: wgpu::RenderPipeline,
     pipeline_wire: Option<wgpu::RenderPipeline>,
+    frame_count: usize,
 }
 
+const STATS_INTERVAL: usize = 100;
+
 impl Example {
     fn generate_matrix(aspect_ratio: f32) -> glam::Mat4 {
         let projection = glam::Mat4::perspective_rh(consts::FRAC_PI_4, aspect_ratio, 1.0, 20.0);
@@ -28,6 +53,243 @@ impl Example {
         );
         projection * view
     }
+
+    // Objects of the r-th row, every row is placed behind the previous one
+    fn row(objects: &[Object], r: usize) -> Vec<Object> {
+        let offset = glam::Mat4::from_translation(glam::Vec3::new(0.0, 3.0 * r as f32, 0.0));
+        objects
+            .iter()
+            .map(|o| Object { transform_m: offset * o.transform_m, ..*o })
+            .collect()
+    }
+
+    fn commands(scene: &Scene, index_ranges: &[(MeshId, Vec<(u32, u32)>)]) -> Vec<DrawCommand> {
+        scene.commands(|m_type, lod| {
+            let (_, lods) = index_ranges.iter().find(|(m, _)| *m == m_type).unwrap();
+            lods[lod as usize]
+        })
+    }
+
+    // Culling of all slots of the scene buffers (the free ones are skipped), the base instances
+    // and the draw compaction. They are recreated when the buffers grow or new batches appear.
+    fn create_culling(
+        device: &wgpu::Device,
+        scene: &Scene,
+        scene_buffers: &SceneBuffers,
+        commands: &[DrawCommand],
+        capabilities: &framework::Capabilities,
+        size: (u32, u32),
+        lod_size: f32,
+    ) -> (OcclusionCulling, BaseInstances, Option<Vec<DrawCompaction>>) {
+        let mut cull_instances = scene.cull_instances(0..scene.slot_count());
+        cull_instances.resize(scene_buffers.capacity() as usize, CullInstance::EMPTY);
+        let mut culling = OcclusionCulling::new(device, &cull_instances, commands, size);
+        culling.set_lod_size(lod_size);
+
+        // Without INDIRECT_FIRST_INSTANCE the vertex shader adds the base instance of the draw itself,
+        // every draw then needs its own dynamic offset, which rules out the multi draws
+        let base_instances = BaseInstances::new(device, culling.commands(), !capabilities.indirect_first_instance);
+
+        // Compute passes count the non-empty draw commands of both phases, if the count can be read by the draw
+        let multi_draw = capabilities.multi_draw_indirect && !base_instances.emulated();
+        let compaction = if multi_draw && capabilities.multi_draw_indirect_count {
+            let compaction = (0..2)
+                .map(|phase| {
+                    DrawCompaction::new(device, culling.indirect_buffer(), phase * culling.commands_count(), culling.commands_count())
+                })
+                .collect();
+            Some(compaction)
+        } else {
+            None
+        };
+
+        (culling, base_instances, compaction)
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        uniform_buf: &wgpu::Buffer,
+        scene_buffers: &SceneBuffers,
+        textures: &Textures,
+        visible_buf: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        let texture_views_refs = textures.views.iter().collect::<Vec<_>>();
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: scene_buffers.matrices_buffer().as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: scene_buffers.objects_buffer().as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: match textures.binding_array {
+                        true => wgpu::BindingResource::TextureViewArray(&texture_views_refs),
+                        false => wgpu::BindingResource::TextureView(texture_views_refs[0]),
+                    },
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(&textures.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: visible_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: scene_buffers.bounds_buffer().as_entire_binding(),
+                }
+            ],
+            label: None,
+        })
+    }
+
+    // Applies the changes of the scene: adds or removes the requested rows, spins the objects
+    // and uploads the changed slots
+    fn update_scene(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        while self.row_change > 0 {
+            let row = Self::row(&self.row_objects, self.rows.len());
+            let row = row.into_iter().map(|object| self.scene.insert(object)).collect();
+            self.rows.push(row);
+            self.row_change -= 1;
+        }
+        while self.row_change < 0 {
+            if let Some(row) = self.rows.pop() {
+                for id in row {
+                    self.scene.remove(id);
+                }
+            }
+            self.row_change += 1;
+        }
+
+        if self.spin != 0.0 {
+            let rotation = glam::Mat4::from_rotation_z(self.spin);
+            for id in self.rows.iter().flatten() {
+                let transform_m = self.scene.get(*id).unwrap().transform_m;
+                self.scene.set_transform(*id, transform_m * rotation);
+            }
+        }
+
+        let changes = self.scene.take_changes();
+        let recreated = self.scene_buffers.upload(device, queue, &self.scene, &changes.slots);
+
+        let commands = Self::commands(&self.scene, &self.index_ranges);
+        if recreated || changes.batches {
+            (self.culling, self.base_instances, self.compaction) = Self::create_culling(
+                device,
+                &self.scene,
+                &self.scene_buffers,
+                &commands,
+                &self.capabilities,
+                self.size,
+                self.lod_size,
+            );
+            self.culling.update_camera(self.mx_total);
+            self.bind_group = Self::create_bind_group(
+                device,
+                &self.bind_group_layout,
+                &self.uniform_buf,
+                &self.scene_buffers,
+                &self.textures,
+                self.culling.visible_buffer(),
+            );
+            return;
+        }
+
+        for slots in changes.slots {
+            self.culling.update_instances(queue, slots.start, &self.scene.cull_instances(slots));
+        }
+        if changes.counts {
+            self.culling.update_commands(queue, &commands);
+            self.base_instances.update(queue, self.culling.commands());
+        }
+    }
+
+    // Prepares the commands written by the culling phase for drawing
+    fn prepare_draws(&self, encoder: &mut wgpu::CommandEncoder, profiler: &mut framework::Profiler, phase: u32) {
+        if let Some(ref compaction) = self.compaction {
+            let scope = profiler.begin_pass(encoder, "Draw count");
+            compaction[phase as usize].compact(encoder);
+            profiler.end_pass(encoder, scope);
+        }
+
+        let count = self.culling.commands_count();
+        self.base_instances.copy(encoder, self.culling.indirect_buffer(), phase * count, count);
+    }
+
+    // Draws the commands of one culling phase, the first phase clears the frame
+    fn draw_phase(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, phase: u32) {
+        let (color_load, depth_load) = match phase {
+            0 => (wgpu::LoadOp::Clear(wgpu::Color::BLACK), wgpu::LoadOp::Clear(1.0)),
+            _ => (wgpu::LoadOp::Load, wgpu::LoadOp::Load),
+        };
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: color_load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: self.culling.depth_view(),
+                depth_ops: Some(wgpu::Operations {
+                    load: depth_load,
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_index_buffer(self.index_buf.slice(..), self.index_format);
+        rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
+        self.draw_commands(&mut rpass, phase);
+    }
+
+    // With the draw count written on the GPU the empty batches are skipped entirely
+    fn draw_commands<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>, phase: u32) {
+        if let Some(ref compaction) = self.compaction {
+            let compaction = &compaction[phase as usize];
+            rpass.set_bind_group(1, self.base_instances.bind_group(), &[0]);
+            rpass.multi_draw_indexed_indirect_count(
+                compaction.indirect_buffer(),
+                0,
+                compaction.count_buffer(),
+                0,
+                compaction.max_count(),
+            );
+        } else if self.multi_draw {
+            rpass.set_bind_group(1, self.base_instances.bind_group(), &[0]);
+            rpass.multi_draw_indexed_indirect(
+                self.culling.indirect_buffer(),
+                self.culling.phase_offset(phase),
+                self.culling.commands_count(),
+            );
+        } else {
+            // Fallback without MULTI_DRAW_INDIRECT
+            let indirect_buf = self.base_instances.indirect_buffer(self.culling.indirect_buffer());
+            let first = phase * self.culling.commands_count();
+            for command in first..first + self.culling.commands_count() {
+                rpass.set_bind_group(1, self.base_instances.bind_group(), &[self.base_instances.offset(command)]);
+                rpass.draw_indexed_indirect(indirect_buf, (command as usize * mem::size_of::<DrawCommand>()) as wgpu::BufferAddress);
+            }
+        }
+    }
 }
 
 impl framework::Example for Example {
@@ -36,103 +298,100 @@ impl framework::Example for Example {
         _adapter: &wgpu::Adapter,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
+        options: &framework::Options,
     ) -> Self {
+        // Optional features with their fallbacks, --disable-features turns them off for testing
+        let mut capabilities = framework::Capabilities::new(device);
+        if let Some(list) = options.get("disable-features") {
+            capabilities.disable(list).unwrap_or_else(|err| panic!("{}", err));
+        }
+        let missing = capabilities.missing();
+        if !missing.is_empty() {
+            println!("Using fallbacks for: {}", missing.join(", "));
+        }
+
         // Create the vertex and index buffers
         let vertex_size = mem::size_of::<shapes::Vertex>();
 
-        // Create meshes
-        let mut cube = Mesh {
-            m_type: MeshType::Cube,
-            vertices: Vec::new(),
-            indices: Vec::new()
-        };
-        let mut cylinder = Mesh {
-            m_type: MeshType::Cylinder,
-            vertices: Vec::new(),
-            indices: Vec::new()
-        };
-        let mut sphere = Mesh {
-            m_type: MeshType::Sphere,
-            vertices: Vec::new(),
-            indices: Vec::new()
-        };
-
-        cube.generate_vertices();
-        cylinder.generate_vertices();
-        sphere.generate_vertices();
-
-        let meshes: Vec<&Mesh> = vec![&cube, &cylinder, &sphere];
-
-        let index_data_len = |m_type: MeshType| -> u32 {
-            let length;
-            match m_type {
-                MeshType::Cube => length = cube.indices.len(),
-                MeshType::Cylinder => length = cylinder.indices.len(),
-                MeshType::Sphere => length = sphere.indices.len()
+        // Create the procedural meshes
+        let mut meshes: Vec<Mesh> = [MeshId::CUBE, MeshId::CYLINDER, MeshId::SPHERE]
+            .into_iter()
+            .map(|m_type| {
+                let mut mesh = Mesh {
+                    m_type,
+                    vertices: Vec::new(),
+                    indices: Vec::new(),
+                    lods: Vec::new(),
+                };
+                mesh.generate_vertices();
+                mesh
+            })
+            .collect();
+
+        // Objects, meshes and textures of the glTF file (--gltf), the OBJ file (--obj) or the scene file
+        let (row_objects, images) = match (options.get("gltf"), options.get("obj")) {
+            (Some(gltf_path), _) => {
+                let gltf = GltfScene::load(Path::new(gltf_path), MeshId::FIRST_CUSTOM, TextureId(0))
+                    .unwrap_or_else(|err| panic!("{}", err));
+                meshes.extend(gltf.meshes);
+                (gltf.objects, gltf.textures)
             }
-            length as u32
-        };
-
-        let index_offset = |mesh_type: MeshType| -> u32 {
-            let mut offset: u32 = 0;
-            for m in &meshes {
-                if m.m_type == mesh_type {
-                    return offset;
+            (None, Some(obj_path)) => {
+                let obj = ObjScene::load(Path::new(obj_path), MeshId::FIRST_CUSTOM, TextureId(0))
+                    .unwrap_or_else(|err| panic!("{}", err));
+                meshes.extend(obj.meshes);
+                (obj.objects, obj.textures)
+            }
+            (None, None) => {
+                let scene_path = options.get("scene").unwrap_or("./examples/gpu-driven-rendering/scene.ron");
+                let description = SceneDescription::load(Path::new(scene_path))
+                    .unwrap_or_else(|err| panic!("{}: {}", scene_path, err));
+
+                for (m_type, path) in description.mesh_paths() {
+                    let mesh = ObjModel::load(&path).and_then(|model| model.to_mesh(m_type));
+                    meshes.push(mesh.unwrap_or_else(|err| panic!("{}", err)));
+                }
+                for (m_type, shape) in description.shape_meshes() {
+                    meshes.push(shape.to_mesh(m_type));
                 }
-                offset += index_data_len(m.m_type);
+                let images = description
+                    .texture_paths()
+                    .iter()
+                    .map(|file_path| image::open(file_path).unwrap().to_rgba8())
+                    .collect();
+                let objects = description.objects().unwrap_or_else(|err| panic!("{}: {}", scene_path, err));
+                (objects, images)
             }
-            offset
         };
 
-        // Create objects
-        let objects = vec![
-            // Cube - blue
-            Object {
-                transform_m: glam::Mat4::from_scale_rotation_translation(
-                    glam::Vec3::ONE,
-                    glam::Quat::IDENTITY,
-                    glam::Vec3::new(-3.0, 0.0, 0.0),
-                ),
-                m_type: MeshType::Cube,
-                t_type: TextureType::Blue,
-            },
-            // Cylinder - red
-            Object {
-                transform_m: glam::Mat4::from_scale_rotation_translation(
-                    glam::Vec3::ONE,
-                    glam::Quat::IDENTITY,
-                    glam::Vec3::new(0.0, 0.0, 0.0),
-                ),
-                m_type: MeshType::Cylinder,
-                t_type: TextureType::Red,
-            },
-            // Cube - blue
-            Object {
-                transform_m: glam::Mat4::from_scale_rotation_translation(
-                    glam::Vec3::ONE,
-                    glam::Quat::IDENTITY,
-                    glam::Vec3::new(3.0, 0.0, 0.0),
-                ),
-                m_type: MeshType::Cube,
-                t_type: TextureType::Blue,
-            },
-            // Sphere - yellow
-            Object {
-                transform_m: glam::Mat4::from_scale_rotation_translation(
-                    glam::Vec3::ONE,
-                    glam::Quat::IDENTITY,
-                    glam::Vec3::new(6.0, 0.0, 0.0),
-                ),
-                m_type: MeshType::Sphere,
-                t_type: TextureType::Yellow,
-            },
-        ];
+        // Bindings can't be empty
+        let images = match images.is_empty() {
+            true => vec![image::RgbaImage::from_pixel(1, 1, image::Rgba([255; 4]))],
+            false => images,
+        };
+
+        let meshes: Vec<&Mesh> = meshes.iter().collect();
+
+        // Number of indices and the first index of every LOD of every mesh in the merged index buffer
+        let index_ranges: Vec<(MeshId, Vec<(u32, u32)>)> =
+            meshes.iter().map(|mesh| mesh.m_type).zip(shapes::lod_ranges(&meshes)).collect();
 
-        // Create batches from objects
-        let batches = shapes::get_batches_from_objects(&objects);
+        // Every row (--rows) is placed behind the previous one, Up and Down keys add and remove rows.
+        // The values of the options were checked by check_options
+        let rows: usize = options.parse("rows").unwrap().unwrap_or(1);
+        let mut scene = Scene::new(&meshes);
+        let rows: Vec<Vec<ObjectId>> = (0..rows)
+            .map(|r| Self::row(&row_objects, r).into_iter().map(|object| scene.insert(object)).collect())
+            .collect();
+        scene.take_changes();
+
+        // Degrees per frame
+        let spin: f32 = options.parse("spin").unwrap().unwrap_or(0.0);
+        let lod_size: f32 = options.parse("lod-size").unwrap().unwrap_or(culling::DEFAULT_LOD_SIZE);
 
         // Create one big vertex and index buffer from meshes
-        let (vertex_data, index_data) = shapes::merge_index_vertex_data(&meshes);
+        let (vertex_data, index_data) = shapes::merge_index_vertex_data(&meshes)
+            .unwrap_or_else(|err| panic!("{}", err));
 
         let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
@@ -142,17 +401,10 @@ impl framework::Example for Example {
 
         let index_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(&index_data),
+            contents: index_data.as_bytes(),
             usage: wgpu::BufferUsages::INDEX,
         });
 
-        // Textures to load
-        let texture_paths: [String; 3] = [
-            String::from("./examples/gpu-driven-rendering/assets/blue_texture.png"),
-            String::from("./examples/gpu-driven-rendering/assets/red_texture.png"),
-            String::from("./examples/gpu-driven-rendering/assets/yellow_texture.png")
-        ];
-
         // Create pipeline layout
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: None,
@@ -168,14 +420,14 @@ impl framework::Example for Example {
                     },
                     count: None,
                 },
-                // Transformation matrices for scene objects: array<mat4x4<f32>>
+                // Transformation matrices for scene objects, indexed by the slot: array<mat4x4<f32>>
                 wgpu::BindGroupLayoutEntry {
                     binding: 1,
                     visibility: wgpu::ShaderStages::VERTEX,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Storage { read_only: true },
                         has_dynamic_offset: false,
-                        min_binding_size: wgpu::BufferSize::new((64*objects.len()) as u64),
+                        min_binding_size: wgpu::BufferSize::new(64),
                     },
                     count: None,
                 },
@@ -186,20 +438,26 @@ impl framework::Example for Example {
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Storage { read_only: true },
                         has_dynamic_offset: false,
-                        min_binding_size: wgpu::BufferSize::new((4*objects.len()) as u64),
+                        min_binding_size: wgpu::BufferSize::new(8),
                     },
                     count: None,
                 },
-                // Texture array: binding_array<texture_2d<f32>>
+                // Textures: binding_array<texture_2d<f32>> or texture_2d_array<f32> without TEXTURE_BINDING_ARRAY
                 wgpu::BindGroupLayoutEntry {
                     binding: 3,
                     visibility: wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Texture {
                         sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
+                        view_dimension: match capabilities.texture_binding_array {
+                            true => wgpu::TextureViewDimension::D2,
+                            false => wgpu::TextureViewDimension::D2Array,
+                        },
                         multisampled: false,
                     },
-                    count: core::num::NonZeroU32::new(texture_paths.len() as u32),
+                    count: match capabilities.texture_binding_array {
+                        true => core::num::NonZeroU32::new(images.len() as u32),
+                        false => None,
+                    },
                 },
                 // Texture sampler: sampler
                 wgpu::BindGroupLayoutEntry {
@@ -207,67 +465,94 @@ impl framework::Example for Example {
                     visibility: wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                     count: None,
+                },
+                // Indices of the visible objects written by the culling (both phases): array<u32>
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(4),
+                    },
+                    count: None,
+                },
+                // World space bounds of scene objects: array<Bounds>
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(mem::size_of::<shapes::GpuBounds>() as u64),
+                    },
+                    count: None,
                 }
             ],
         });
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: None,
-            bind_group_layouts: &[&bind_group_layout],
-            push_constant_ranges: &[],
-        });
-
-        // Create textures
-        let mut texture_views = Vec::new();
 
-        for file_path in &texture_paths {
-            let texture_path = Path::new(file_path);
-            let texture_bytes_vec = std::fs::read(texture_path).unwrap();
-            let texture_bytes = bytemuck::cast_slice(&texture_bytes_vec);
-
-            let texture_image = image::load_from_memory(texture_bytes).unwrap();
-            let texture_rgba = texture_image.to_rgba8();
-
-            use image::GenericImageView;
-            let (texture_width, texture_height) = texture_image.dimensions();
+        // Create textures, one for every image or one with an image in every layer
+        let (width, height) = images[0].dimensions();
+        let textures: Vec<wgpu::Texture> = match capabilities.texture_binding_array {
+            true => images
+                .iter()
+                .map(|image| create_texture(device, image.dimensions(), 1))
+                .collect(),
+            // The GL backend creates a 2D texture instead of an array for one layer, an unused layer is added
+            false => vec![create_texture(device, (width, height), images.len().max(2) as u32)],
+        };
 
-            let texture_extent = wgpu::Extent3d {
-                width: texture_width,
-                height: texture_height,
-                depth_or_array_layers: 1,
+        for (i, texture_rgba) in images.iter().enumerate() {
+            let (texture, layer) = match capabilities.texture_binding_array {
+                true => (&textures[i], 0),
+                false => (&textures[0], i as u32),
             };
-            let texture = device.create_texture(&wgpu::TextureDescriptor {
-                label: None,
-                size: texture_extent,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            });
 
-            let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-
-            texture_views.push(texture_view);
+            // Layers of the texture array need the same size
+            let texture_rgba = match capabilities.texture_binding_array || texture_rgba.dimensions() == (width, height) {
+                true => Cow::Borrowed(texture_rgba),
+                false => Cow::Owned(image::imageops::resize(texture_rgba, width, height, image::imageops::FilterType::Triangle)),
+            };
 
             queue.write_texture(
-                texture.as_image_copy(),
+                wgpu::ImageCopyTexture {
+                    texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x: 0, y: 0, z: layer },
+                    aspect: wgpu::TextureAspect::All,
+                },
                 &texture_rgba,
                 wgpu::ImageDataLayout {
                     offset: 0,
-                    bytes_per_row: std::num::NonZeroU32::new(4*8),
+                    bytes_per_row: std::num::NonZeroU32::new(4*texture_rgba.width()),
                     rows_per_image: None,
                 },
-                texture_extent,
+                wgpu::Extent3d {
+                    width: texture_rgba.width(),
+                    height: texture_rgba.height(),
+                    depth_or_array_layers: 1,
+                },
             );
         }
 
-        // Create array of texture view references
-        let texture_views_refs = texture_views.iter().collect::<Vec<_>>();
+        let texture_views: Vec<wgpu::TextureView> = textures
+            .iter()
+            .map(|texture| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    dimension: match capabilities.texture_binding_array {
+                        true => Some(wgpu::TextureViewDimension::D2),
+                        false => Some(wgpu::TextureViewDimension::D2Array),
+                    },
+                    ..Default::default()
+                })
+            })
+            .collect();
 
         // Create texture sampler
         let texture_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            // UVs of imported models and of the icosphere's seam continue outside of 0 to 1
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
             address_mode_w: wgpu::AddressMode::ClampToEdge,
             mag_filter: wgpu::FilterMode::Linear,
             min_filter: wgpu::FilterMode::Nearest,
@@ -275,6 +560,12 @@ impl framework::Example for Example {
             ..Default::default()
         });
 
+        let textures = Textures {
+            views: texture_views,
+            sampler: texture_sampler,
+            binding_array: capabilities.texture_binding_array,
+        };
+
         // Create other resources
         let mx_total = Self::generate_matrix(config.width as f32 / config.height as f32);
         let mx_ref: &[f32; 16] = mx_total.as_ref();
@@ -284,80 +575,41 @@ impl framework::Example for Example {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
-        // Create storage buffer with transformation matrices
-        let matrices_vec = shapes::merge_matrices(&batches);
-        let matrices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Storage buffer"),
-            contents: bytemuck::cast_slice(&matrices_vec),
-            usage: wgpu::BufferUsages::STORAGE,
-        });
+        // Storage buffers with the matrices, objects and bounds of the scene's slots
+        let scene_buffers = SceneBuffers::new(device, &scene);
 
-        // Create storage buffer with object informations (transform_id, texture_id)
-        let objects_vec = shapes::merge_objects(&batches);
-        let objects_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Storage Buffer"),
-            contents: bytemuck::cast_slice(&objects_vec),
-            usage: wgpu::BufferUsages::STORAGE,
-        });
-
-        // Create indirect data for each texture type
-        let mut indirect_data: Vec<u8> = Vec::new();
-
-        let mut instance_count;
-        let mut base_instance = 0;
-
-        for b in &batches {
-            instance_count = b.transform_m.len();
-            indirect_data.extend(
-                wgpu::util::DrawIndexedIndirect {
-                    vertex_count: index_data_len(b.m_type),
-                    instance_count: instance_count as u32,
-                    base_index: index_offset(b.m_type),
-                    vertex_offset: 0,
-                    base_instance: base_instance as u32,
-                }.as_bytes()
-            );
-            base_instance += instance_count;
-        }
-
-        // Create indirect buffer
-        let indirect_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Indirect buffer"),
-            contents: bytemuck::cast_slice(&indirect_data),
-            usage: wgpu::BufferUsages::INDIRECT,
-        });
+        // Frustum and occlusion culling writes the indirect buffer every frame
+        let size = (config.width, config.height);
+        let commands = Self::commands(&scene, &index_ranges);
+        let (mut culling, base_instances, compaction) =
+            Self::create_culling(device, &scene, &scene_buffers, &commands, &capabilities, size, lod_size);
+        culling.update_camera(mx_total);
+        let multi_draw = capabilities.multi_draw_indirect && !base_instances.emulated();
 
         // Create bind group
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: uniform_buf.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: matrices_buf.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: objects_buf.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: wgpu::BindingResource::TextureViewArray(&texture_views_refs),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 4,
-                    resource: wgpu::BindingResource::Sampler(&texture_sampler),
-                }
-            ],
+        let bind_group = Self::create_bind_group(
+            device,
+            &bind_group_layout,
+            &uniform_buf,
+            &scene_buffers,
+            &textures,
+            culling.visible_buffer(),
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
+            bind_group_layouts: &[&bind_group_layout, base_instances.bind_group_layout()],
+            push_constant_ranges: &[],
         });
 
+        // The declaration of the textures depends on the capabilities
+        let textures_source = match capabilities.texture_binding_array {
+            true => include_str!("textures_binding_array.wgsl"),
+            false => include_str!("textures_array.wgsl"),
+        };
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: None,
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl"))),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(format!("{}\n{}", textures_source, include_str!("shader.wgsl")))),
         });
 
         let vertex_buffers = [wgpu::VertexBufferLayout {
@@ -374,6 +626,16 @@ impl framework::Example for Example {
                     offset: 4 * 4,
                     shader_location: 1,
                 },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x3,
+                    offset: 4 * 6,
+                    shader_location: 2,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: 4 * 9,
+                    shader_location: 3,
+                },
             ],
         }];
 
@@ -394,7 +656,13 @@ impl framework::Example for Example {
                 cull_mode: Some(wgpu::Face::Back),
                 ..Default::default()
             },
-            depth_stencil: None,
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: culling::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
             multisample: wgpu::MultisampleState::default(),
             multiview: None,
         });
@@ -443,28 +711,108 @@ impl framework::Example for Example {
         Example {
             vertex_buf,
             index_buf,
+            index_format: index_data.format(),
+            bind_group_layout,
             bind_group,
+            textures,
             uniform_buf,
-            indirect_buf,
-            batches_count: batches.len() as u32,
+            scene,
+            scene_buffers,
+            row_objects,
+            rows,
+            row_change: 0,
+            spin: spin.to_radians(),
+            index_ranges,
+            lod_size,
+            capabilities,
+            size,
+            mx_total,
+            culling,
+            compaction,
+            base_instances,
+            multi_draw,
             pipeline,
             pipeline_wire,
+            frame_count: 0,
         }
     }
 
-    fn update(&mut self, _event: winit::event::WindowEvent) {
-        //empty
+    fn options() -> Vec<framework::CliOption> {
+        vec![
+            framework::CliOption {
+                name: "rows",
+                value_name: "<count>",
+                help: "Number of rows of objects in the scene",
+            },
+            framework::CliOption {
+                name: "disable-features",
+                value_name: "<list>",
+                help: "Use the fallbacks of these features (all, multi-draw-indirect, multi-draw-indirect-count, indirect-first-instance, texture-binding-array)",
+            },
+            framework::CliOption {
+                name: "scene",
+                value_name: "<path>",
+                help: "Scene file with the textures and the objects of a row (RON)",
+            },
+            framework::CliOption {
+                name: "gltf",
+                value_name: "<path>",
+                help: "glTF file (.gltf or .glb) drawn instead of the scene file",
+            },
+            framework::CliOption {
+                name: "obj",
+                value_name: "<path>",
+                help: "Wavefront OBJ file with its materials drawn instead of the scene file",
+            },
+            framework::CliOption {
+                name: "spin",
+                value_name: "<degrees>",
+                help: "Rotation of every object per frame",
+            },
+            framework::CliOption {
+                name: "lod-size",
+                value_name: "<fraction>",
+                help: "Screen size (fraction of the frame height) below which the coarser levels of detail are drawn, 0 turns them off",
+            },
+        ]
+    }
+
+    fn check_options(options: &framework::Options) -> Result<(), String> {
+        options.parse::<usize>("rows")?;
+        options.parse::<f32>("spin")?;
+        options.parse::<f32>("lod-size")?;
+        Ok(())
+    }
+
+    fn update(&mut self, event: winit::event::WindowEvent) {
+        use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+        if let WindowEvent::KeyboardInput {
+            input: KeyboardInput { virtual_keycode: Some(key), state: ElementState::Pressed, .. },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::Up => self.row_change += 1,
+                VirtualKeyCode::Down => self.row_change -= 1,
+                _ => {}
+            }
+        }
     }
 
     fn resize(
         &mut self,
         config: &wgpu::SurfaceConfiguration,
-        _device: &wgpu::Device,
+        device: &wgpu::Device,
         queue: &wgpu::Queue,
     ) {
         let mx_total = Self::generate_matrix(config.width as f32 / config.height as f32);
         let mx_ref: &[f32; 16] = mx_total.as_ref();
         queue.write_buffer(&self.uniform_buf, 0, bytemuck::cast_slice(mx_ref));
+        self.mx_total = mx_total;
+        self.size = (config.width, config.height);
+        self.culling.update_camera(mx_total);
+        self.culling.resize(device, self.size);
     }
 
     fn render(
@@ -473,44 +821,98 @@ impl framework::Example for Example {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         _spawner: &framework::Spawner,
+        profiler: &mut framework::Profiler,
     ) {
+        self.update_scene(device, queue);
+
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
-        {
-            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.0,
-                            g: 0.0,
-                            b: 0.0,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
-            rpass.set_pipeline(&self.pipeline);
-            rpass.set_bind_group(0, &self.bind_group, &[]);
-            rpass.set_index_buffer(self.index_buf.slice(..), wgpu::IndexFormat::Uint16);
-            rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
-            rpass.multi_draw_indexed_indirect(&self.indirect_buf, 0, self.batches_count);
-
-            // Pipeline wire
-            if let Some(ref pipe) = self.pipeline_wire {
+        let scope = profiler.begin_pass(&mut encoder, "Occlusion culling (phase 1)");
+        self.culling.cull_phase1(queue, &mut encoder);
+        profiler.end_pass(&mut encoder, scope);
+        self.prepare_draws(&mut encoder, profiler, 0);
+
+        // Objects visible in the previous frame, they also fill the depth buffer for the second phase
+        let scope = profiler.begin_pass(&mut encoder, "multi_draw_indexed_indirect (phase 1)");
+        self.draw_phase(&mut encoder, view, 0);
+        profiler.end_pass(&mut encoder, scope);
+
+        let scope = profiler.begin_pass(&mut encoder, "Depth pyramid");
+        self.culling.build_pyramid(&mut encoder);
+        profiler.end_pass(&mut encoder, scope);
+
+        let scope = profiler.begin_pass(&mut encoder, "Occlusion culling (phase 2)");
+        self.culling.cull_phase2(&mut encoder);
+        profiler.end_pass(&mut encoder, scope);
+        self.prepare_draws(&mut encoder, profiler, 1);
+
+        // Objects that became visible in this frame
+        let scope = profiler.begin_pass(&mut encoder, "multi_draw_indexed_indirect (phase 2)");
+        self.draw_phase(&mut encoder, view, 1);
+        profiler.end_pass(&mut encoder, scope);
+
+        // Pipeline wire, drawn in its own pass, so it can be timed separately
+        if let Some(ref pipe) = self.pipeline_wire {
+            let scope = profiler.begin_pass(&mut encoder, "Wireframe");
+            {
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
                 rpass.set_pipeline(pipe);
-                rpass.multi_draw_indexed_indirect(&self.indirect_buf, 0, self.batches_count);
+                rpass.set_bind_group(0, &self.bind_group, &[]);
+                rpass.set_index_buffer(self.index_buf.slice(..), self.index_format);
+                rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
+                for phase in 0..2 {
+                    self.draw_commands(&mut rpass, phase);
+                }
             }
+            profiler.end_pass(&mut encoder, scope);
         }
 
-        queue.submit(Some(encoder.finish()));
+        profiler.submit(queue, Some(encoder.finish()));
+
+        self.frame_count += 1;
+        if self.frame_count % STATS_INTERVAL == 0 {
+            self.culling.request_stats(device, queue);
+        }
+        // The stats are printed a few frames later, when the GPU finished the frame
+        match self.culling.poll_stats(device, wgpu::Maintain::Poll) {
+            Some(Ok(stats)) => println!(
+                "Culled objects: frustum {}, occluded {} (phase 1) / {} (phase 2), disoccluded {}",
+                stats.frustum, stats.phase1, stats.phase2, stats.disoccluded
+            ),
+            Some(Err(err)) => eprintln!("{}", err),
+            None => {}
+        }
     }
 }
 
+// Sampled texture with the given number of layers
+fn create_texture(device: &wgpu::Device, (width, height): (u32, u32), layers: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: layers,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+    })
+}
+
 fn main() {
     framework::run::<Example>("GPU driven rendering");
 }
@@ -3,6 +3,9 @@ struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) tex_coord: vec2<f32>,
     @location(1) texture_id: u32,
+    // World space, for lighting and normal mapping
+    @location(2) normal: vec3<f32>,
+    @location(3) tangent: vec4<f32>,
 }
 
 struct Object {
@@ -10,29 +13,51 @@ struct Object {
     texture_id: u32,
 }
 
+// Base instance of the draw, if the adapter doesn't apply it to instance_index
+struct Draw {
+    base_instance: u32,
+}
+
+struct Bounds {
+    aabb_min: vec4<f32>,
+    aabb_max: vec4<f32>,
+    sphere: vec4<f32>, // Center and radius
+}
+
 @group(0) @binding(0) var<uniform> transform: mat4x4<f32>;
 @group(0) @binding(1) var<storage> matrices: array<mat4x4<f32>>;
 @group(0) @binding(2) var<storage> objects: array<Object>;
-@group(0) @binding(3) var texture_arr: binding_array<texture_2d<f32>>;
-@group(0) @binding(4) var texture_sampler: sampler;
+@group(0) @binding(5) var<storage> visible: array<u32>;
+@group(0) @binding(6) var<storage> bounds: array<Bounds>; // Indexed by transform_id
+@group(1) @binding(0) var<uniform> draw: Draw;
+
+// The textures, the sampler (bindings 3 and 4) and sample_texture are declared in textures_*.wgsl,
+// which is placed before this file
 
 @vertex
 fn vs_main(
     @location(0) position: vec4<f32>,
     @location(1) tex_coord: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) tangent: vec4<f32>, // w is the sign of the bitangent
     @builtin(instance_index) instance_id: u32,
 ) -> VertexOutput {
     var result: VertexOutput;
-    let object: Object = objects[instance_id];
-    result.position = transform * (matrices[object.transform_id] * position);
+    // Instances are compacted by the culling
+    let object: Object = objects[visible[draw.base_instance + instance_id]];
+    let model = matrices[object.transform_id];
+    result.position = transform * (model * position);
     result.tex_coord = tex_coord;
     result.texture_id = object.texture_id;
+    // Without non-uniform scaling the model matrix keeps the directions perpendicular
+    result.normal = normalize((model * vec4<f32>(normal, 0.0)).xyz);
+    result.tangent = vec4<f32>(normalize((model * vec4<f32>(tangent.xyz, 0.0)).xyz), tangent.w);
     return result;
 }
 
 @fragment
 fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(texture_arr[vertex.texture_id], texture_sampler, vertex.tex_coord);
+    return sample_texture(vertex.texture_id, vertex.tex_coord);
 }
 
 @fragment
@@ -0,0 +1,8 @@
+
+// Every texture in a layer of one texture, fallback without TEXTURE_BINDING_ARRAY
+@group(0) @binding(3) var texture_arr: texture_2d_array<f32>;
+@group(0) @binding(4) var texture_sampler: sampler;
+
+fn sample_texture(texture_id: u32, tex_coord: vec2<f32>) -> vec4<f32> {
+    return textureSample(texture_arr, texture_sampler, tex_coord, i32(texture_id));
+}
@@ -0,0 +1,8 @@
+
+// Every texture in its own binding (TEXTURE_BINDING_ARRAY)
+@group(0) @binding(3) var texture_arr: binding_array<texture_2d<f32>>;
+@group(0) @binding(4) var texture_sampler: sampler;
+
+fn sample_texture(texture_id: u32, tex_coord: vec2<f32>) -> vec4<f32> {
+    return textureSample(texture_arr[texture_id], texture_sampler, tex_coord);
+}
@@ -1,25 +1,12 @@
 
-#[path = "../framework.rs"]
-mod framework;
-mod effects;
-mod helper;
-
+use paralelizace_3d_renderovani::framework;
+use paralelizace_3d_renderovani::post_processing::{PostProcessing, PostProcessingChain, EffectType, INTERMEDIATE_FORMAT, helper};
 use wgpu::util::DeviceExt;
 use std::path::Path;
 use std::borrow::Cow;
-use bytemuck::{Pod, Zeroable};
-use effects::{PostProcessing, PostProcessingChain, EffectType};
-use helper::get_uv_from_position;
-
-// Vertex with position and texture coordinates
-#[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable, Debug)]
-pub struct UVVertex {
-    pub pos: [f32; 2],
-    pub uv_coords: [f32; 2]
-}
+use helper::{UVVertex, get_uv_from_position};
 
-struct Example {
+pub struct Example {
     vertex_buf: wgpu::Buffer,
     pipeline: wgpu::RenderPipeline,
     bind_group: wgpu::BindGroup,
@@ -34,6 +21,7 @@ impl framework::Example for Example {
         _adapter: &wgpu::Adapter,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
+        options: &framework::Options,
     ) -> Example {
 
         let vertices = [
@@ -136,7 +124,7 @@ impl framework::Example for Example {
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
                 entry_point: "fs_main",
-                targets: &[Some(config.format.into())],
+                targets: &[Some(INTERMEDIATE_FORMAT.into())],
             }),
             primitive: wgpu::PrimitiveState::default(),
             depth_stencil: None,
@@ -145,7 +133,7 @@ impl framework::Example for Example {
         });
 
         // Create input texture
-        let texture_path = Path::new("./examples/post-processing/original_scene.png");
+        let texture_path = Path::new(options.get("image").unwrap_or("./examples/post-processing/original_scene.png"));
         let texture_bytes_vec = std::fs::read(texture_path).unwrap();
         let texture_bytes = bytemuck::cast_slice(&texture_bytes_vec);
 
@@ -176,7 +164,7 @@ impl framework::Example for Example {
             &texture_rgba,
             wgpu::ImageDataLayout {
                 offset: 0,
-                bytes_per_row: std::num::NonZeroU32::new(4*1024),
+                bytes_per_row: std::num::NonZeroU32::new(4*texture_width),
                 rows_per_image: None,
             },
             texture_extent,
@@ -215,10 +203,16 @@ impl framework::Example for Example {
         // Output texture for main scene and input for post-processing
         let output_view = helper::create_output_texture_view(device, config);
 
-        // Initialize post processing
-        let mut pp_chain = PostProcessingChain::new();
-        pp_chain.add_effect(EffectType::Contour);
-        pp_chain.add_effect(EffectType::Tint(0.2, 0.4, 0.8, 1.0));
+        // Initialize post processing, --effects was checked by check_options
+        let pp_chain = match options.parse::<PostProcessingChain>("effects").unwrap() {
+            Some(chain) => chain,
+            None => {
+                let mut chain = PostProcessingChain::new();
+                chain.add_effect(EffectType::Contour);
+                chain.add_effect(EffectType::Tint(0.2, 0.4, 0.8, 1.0));
+                chain
+            }
+        };
 
         let post_processing = PostProcessing::init(&pp_chain, device, config, &output_view);
 
@@ -242,6 +236,25 @@ impl framework::Example for Example {
         queue.write_buffer(&self.aspect_ratio_buf, 0, bytemuck::cast_slice(&[ratio]));
     }
 
+    fn options() -> Vec<framework::CliOption> {
+        vec![
+            framework::CliOption {
+                name: "effects",
+                value_name: "<list>",
+                help: "Post-processing chain, e.g. contour,tint:0.2:0.4:0.8:1.0",
+            },
+            framework::CliOption {
+                name: "image",
+                value_name: "<path>",
+                help: "Image used as the rendered scene",
+            },
+        ]
+    }
+
+    fn check_options(options: &framework::Options) -> Result<(), String> {
+        options.parse::<PostProcessingChain>("effects").map(|_| ())
+    }
+
     fn update(&mut self, _event: winit::event::WindowEvent) {
         // Empty
     }
@@ -252,9 +265,11 @@ impl framework::Example for Example {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         _spawner: &framework::Spawner,
+        profiler: &mut framework::Profiler,
     ) {
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
+        let scope = profiler.begin_pass(&mut encoder, "Scene");
         {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
@@ -276,12 +291,14 @@ impl framework::Example for Example {
             rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
             rpass.draw(0..6, 0..1);
         }
+        profiler.end_pass(&mut encoder, scope);
+
+        // The scene has to be submitted before the effects read it
+        profiler.submit(queue, Some(encoder.finish()));
 
-        if let Some(_) = &self.output_view {
-            self.post_processing.resolve(device, queue, view);
+        if self.output_view.is_some() {
+            self.post_processing.resolve(device, queue, view, profiler);
         }
-        
-        queue.submit(Some(encoder.finish()));
     }
 }
 
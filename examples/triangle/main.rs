@@ -1,6 +1,5 @@
 
-#[path = "../framework.rs"]
-mod framework;
+use paralelizace_3d_renderovani::framework;
 
 use wgpu::util::DeviceExt;
 use std::borrow::Cow;
@@ -20,7 +19,7 @@ fn vertex(p: [f32; 2], c: [f32; 3]) -> Vertex {
     }
 }
 
-struct Example {
+pub struct Example {
     vertex_buf: wgpu::Buffer,
     vert_count: u32,
     pipeline: wgpu::RenderPipeline,
@@ -29,9 +28,10 @@ struct Example {
 impl framework::Example for Example {
     fn init(
         config: &wgpu::SurfaceConfiguration,
-        adapter: &wgpu::Adapter,
+        _adapter: &wgpu::Adapter,
         device: &wgpu::Device,
-        queue: &wgpu::Queue,
+        _queue: &wgpu::Queue,
+        _options: &framework::Options,
     ) -> Example {
 
         // vertex0_position, vertex0_color, vertex1_position, ...
@@ -102,9 +102,9 @@ impl framework::Example for Example {
 
     fn resize(
         &mut self,
-        config: &wgpu::SurfaceConfiguration,
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
+        _config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
     ) {
         // Empty
     }
@@ -118,10 +118,12 @@ impl framework::Example for Example {
         view: &wgpu::TextureView,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        spawner: &framework::Spawner,
+        _spawner: &framework::Spawner,
+        profiler: &mut framework::Profiler,
     ) {
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
+        let scope = profiler.begin_pass(&mut encoder, "Triangle");
         {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
@@ -144,8 +146,9 @@ impl framework::Example for Example {
             rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
             rpass.draw(0..self.vert_count, 0..1);
         }
+        profiler.end_pass(&mut encoder, scope);
 
-        queue.submit(Some(encoder.finish()));
+        profiler.submit(queue, Some(encoder.finish()));
     }
 }
 
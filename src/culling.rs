@@ -0,0 +1,156 @@
+mod depth_pyramid;
+mod occlusion_culling;
+mod draw_compaction;
+mod base_instances;
+
+pub use depth_pyramid::{DepthPyramid, DEPTH_FORMAT};
+pub use occlusion_culling::{OcclusionCulling, OcclusionStats};
+pub use draw_compaction::DrawCompaction;
+pub use base_instances::BaseInstances;
+
+use bytemuck::{Pod, Zeroable};
+use crate::shapes::{BoundingSphere, MAX_LODS};
+
+// Same layout as wgpu::util::DrawIndexedIndirect, but readable and writable by the compute shaders
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, Debug, Default, PartialEq)]
+pub struct DrawCommand {
+    pub index_count: u32,
+    pub instance_count: u32,
+    pub base_index: u32,
+    pub vertex_offset: i32,
+    pub base_instance: u32,
+}
+
+// Batch of the instances which aren't drawn, e.g. free slots of a scene
+pub const EMPTY_BATCH: u32 = u32::MAX;
+
+// Default of OcclusionCulling::set_lod_size
+pub const DEFAULT_LOD_SIZE: f32 = 0.1;
+
+// World space bounds of one instance and the batch it belongs to.
+// The batch has a draw command for every level of detail of its mesh, batch is the first of them.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, Debug, Default, PartialEq)]
+pub struct CullInstance {
+    pub sphere: [f32; 4], // Center and radius
+    pub batch: u32,
+    pub lods: u32, // Number of levels of detail (draw commands) of the batch
+    pub _padding: [u32; 2],
+}
+
+impl CullInstance {
+    pub const EMPTY: CullInstance = CullInstance {
+        sphere: [0.0; 4],
+        batch: EMPTY_BATCH,
+        lods: 0,
+        _padding: [0; 2],
+    };
+
+    // Instance of a mesh without levels of detail
+    pub fn new(sphere: BoundingSphere, batch: u32) -> Self {
+        Self::with_lods(sphere, batch, 1)
+    }
+
+    pub fn with_lods(sphere: BoundingSphere, batch: u32, lods: u32) -> Self {
+        assert!((1..=MAX_LODS as u32).contains(&lods), "Instances have 1 to {} levels of detail", MAX_LODS);
+        Self {
+            sphere: sphere.center.extend(sphere.radius).to_array(),
+            batch,
+            lods,
+            _padding: [0; 2],
+        }
+    }
+}
+
+// Six planes (left, right, bottom, top, near, far) pointing inside the view frustum
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    pub planes: [glam::Vec4; 6],
+}
+
+impl Frustum {
+    // Extracts the planes from the projection * view matrix (depth in range 0..1)
+    pub fn from_matrix(m: &glam::Mat4) -> Self {
+        let (r0, r1, r2, r3) = (m.row(0), m.row(1), m.row(2), m.row(3));
+
+        let planes = [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2]
+            .map(|plane| plane / plane.truncate().length());
+
+        Self { planes }
+    }
+
+    // Whether the sphere is at least partially inside
+    pub fn intersects_sphere(&self, center: glam::Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(center) + plane.w >= -radius)
+    }
+}
+
+// Diameter of the sphere projected by the perspective view_proj as a fraction of the frame height,
+// infinite when the camera is inside the sphere
+pub fn screen_size(view_proj: &glam::Mat4, sphere: [f32; 4]) -> f32 {
+    let [x, y, z, radius] = sphere;
+    let w = view_proj.row(3).dot(glam::Vec4::new(x, y, z, 1.0));
+    if w <= radius {
+        return f32::INFINITY;
+    }
+
+    // Row 1 of the projection is (0, f, 0, 0) and the view only rotates and moves, so the length of the row is f
+    let f = view_proj.row(1).truncate().length();
+    radius * f / w
+}
+
+// CPU reference of the level of detail chosen by OcclusionCulling: the first coarser one below lod_size,
+// every further one at half the size of the previous
+pub fn select_lod(view_proj: &glam::Mat4, sphere: [f32; 4], lods: u32, lod_size: f32) -> u32 {
+    let size = screen_size(view_proj, sphere);
+    if size >= lod_size {
+        return 0;
+    }
+    (1.0 + (lod_size / size).log2().floor()).min((lods - 1) as f32) as u32
+}
+
+// Size of the region in the visible buffer needed by the commands
+pub fn visible_size(instances: &[CullInstance], commands: &[DrawCommand]) -> usize {
+    commands
+        .iter()
+        .map(|command| (command.base_instance + command.instance_count) as usize)
+        .fold(instances.len(), usize::max)
+}
+
+// CPU reference of the culling compute shader.
+// Every command has a region of the visible buffer starting at its base_instance,
+// the indices of the visible instances are compacted into it and instance_count is set to their number.
+// Only the first command (level of detail) of the batches is used.
+// Returns the new draw commands and the visible buffer.
+pub fn cull(frustum: &Frustum, instances: &[CullInstance], commands: &[DrawCommand]) -> (Vec<DrawCommand>, Vec<u32>) {
+    let mut visible = vec![0; visible_size(instances, commands)];
+    let mut commands: Vec<DrawCommand> = commands
+        .iter()
+        .map(|command| DrawCommand { instance_count: 0, ..*command })
+        .collect();
+
+    for (i, instance) in instances.iter().enumerate() {
+        let [x, y, z, radius] = instance.sphere;
+        if instance.batch == EMPTY_BATCH || !frustum.intersects_sphere(glam::Vec3::new(x, y, z), radius) {
+            continue;
+        }
+
+        let command = &mut commands[instance.batch as usize];
+        visible[(command.base_instance + command.instance_count) as usize] = i as u32;
+        command.instance_count += 1;
+    }
+
+    (commands, visible)
+}
+
+// CPU reference of DrawCompaction, keeps the order of the commands
+pub fn compact(commands: &[DrawCommand]) -> Vec<DrawCommand> {
+    commands
+        .iter()
+        .filter(|command| command.instance_count > 0)
+        .copied()
+        .collect()
+}
@@ -0,0 +1,150 @@
+use wgpu::util::DeviceExt;
+use super::DrawCommand;
+
+// Size of the uniform read by the vertex shader
+const BASE_SIZE: u64 = 16;
+
+// Base instances of the draw commands for the vertex shader.
+// Without Features::INDIRECT_FIRST_INSTANCE the base_instance of indirect draws is ignored (must be zero),
+// so the draws use a copy of the commands with zero base_instance and the base instance of every command
+// is read from a uniform buffer at a dynamic offset instead:
+//
+//   struct Draw { base_instance: u32 }
+//   @group(1) @binding(0) var<uniform> draw: Draw;
+//   ... visible[draw.base_instance + instance_index] ...
+//
+// With the feature the base instance in the uniform is always zero and the commands aren't copied.
+pub struct BaseInstances {
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    base_buf: wgpu::Buffer,
+    indirect_buf: Option<wgpu::Buffer>, // Commands with zero base_instance, if emulated
+    stride: u32,
+}
+
+impl BaseInstances {
+    // Commands as they are in the indirect buffer written by the culling
+    pub fn new(device: &wgpu::Device, commands: &[DrawCommand], emulate: bool) -> Self {
+        let stride = device.limits().min_uniform_buffer_offset_alignment;
+
+        // The first entry is zero for the draws with base_instance applied
+        let bases: Vec<u32> = match emulate {
+            true => std::iter::once(0).chain(commands.iter().map(|command| command.base_instance)).collect(),
+            false => vec![0],
+        };
+        let mut base_data = vec![0u8; bases.len() * stride as usize];
+        for (i, base) in bases.iter().enumerate() {
+            let offset = i * stride as usize;
+            base_data[offset..offset + 4].copy_from_slice(bytemuck::bytes_of(base));
+        }
+
+        let base_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Base instances buffer"),
+            contents: &base_data,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let indirect_buf = match emulate {
+            true => {
+                let zero_base: Vec<DrawCommand> = commands
+                    .iter()
+                    .map(|command| DrawCommand { instance_count: 0, base_instance: 0, ..*command })
+                    .collect();
+                Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Indirect buffer (zero base instance)"),
+                    contents: bytemuck::cast_slice(&zero_base),
+                    usage: wgpu::BufferUsages::INDIRECT | wgpu::BufferUsages::COPY_DST,
+                }))
+            }
+            false => None,
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                // Base instance of the draw: u32
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(BASE_SIZE),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &base_buf,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(BASE_SIZE),
+                    }),
+                },
+            ],
+        });
+
+        BaseInstances {
+            bind_group_layout,
+            bind_group,
+            base_buf,
+            indirect_buf,
+            stride,
+        }
+    }
+
+    pub fn emulated(&self) -> bool {
+        self.indirect_buf.is_some()
+    }
+
+    pub fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    // Writes the base instances of the commands after they changed, the number of commands has to stay the same
+    pub fn update(&self, queue: &wgpu::Queue, commands: &[DrawCommand]) {
+        if self.emulated() {
+            for (i, command) in commands.iter().enumerate() {
+                let offset = self.offset(i as u32) as wgpu::BufferAddress;
+                queue.write_buffer(&self.base_buf, offset, bytemuck::bytes_of(&command.base_instance));
+            }
+        }
+    }
+
+    // Dynamic offset of the bind group for the command, zero for multi draws and if not emulated
+    pub fn offset(&self, command: u32) -> u32 {
+        match self.emulated() {
+            true => (command + 1) * self.stride,
+            false => 0,
+        }
+    }
+
+    // Copies the commands [first, first + count) written by the culling, if emulated.
+    // Only the instance counts change, but the copied fields have to be contiguous.
+    pub fn copy(&self, encoder: &mut wgpu::CommandEncoder, source: &wgpu::Buffer, first: u32, count: u32) {
+        if let Some(ref indirect_buf) = self.indirect_buf {
+            let size = std::mem::size_of::<DrawCommand>() as wgpu::BufferAddress;
+            for command in first..first + count {
+                let offset = command as wgpu::BufferAddress * size;
+                // index_count, instance_count, base_index, vertex_offset
+                encoder.copy_buffer_to_buffer(source, offset, indirect_buf, offset, size - 4);
+            }
+        }
+    }
+
+    // Buffer the draws have to use: the copy if emulated, the source otherwise
+    pub fn indirect_buffer<'a>(&'a self, source: &'a wgpu::Buffer) -> &'a wgpu::Buffer {
+        self.indirect_buf.as_ref().unwrap_or(source)
+    }
+}
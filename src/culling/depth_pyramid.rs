@@ -0,0 +1,260 @@
+use wgpu::util::DeviceExt;
+use std::borrow::Cow;
+
+// Format of the depth buffer the pyramid is built from
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+const MAX_LEVELS: usize = 16;
+const WORKGROUP_SIZE: u32 = 8;
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct PyramidUniform {
+    count: u32,
+    _padding: [u32; 3],
+    levels: [[u32; 4]; MAX_LEVELS], // Offset in the buffer, width and height
+}
+
+// Resources depending on the size of the frame
+struct Targets {
+    depth_view: wgpu::TextureView,
+    uniform_buf: wgpu::Buffer,
+    pyramid_buf: wgpu::Buffer,
+    level_sizes: Vec<(u32, u32)>,
+    bind_group: wgpu::BindGroup,
+}
+
+// Depth buffer and its hierarchical (Hi-Z) pyramid built by compute downsampling.
+// All levels are stored in one storage buffer, see depth_pyramid.wgsl for the layout.
+pub struct DepthPyramid {
+    copy_pipeline: wgpu::ComputePipeline,
+    downsample_pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    level_buf: wgpu::Buffer, // Index of every level at a dynamic offset
+    level_stride: u32,
+    targets: Targets,
+}
+
+impl DepthPyramid {
+    pub fn new(device: &wgpu::Device, size: (u32, u32)) -> Self {
+        let level_stride = device.limits().min_uniform_buffer_offset_alignment;
+        let mut level_data = vec![0u8; MAX_LEVELS * level_stride as usize];
+        for level in 0..MAX_LEVELS {
+            let offset = level * level_stride as usize;
+            level_data[offset..offset + 4].copy_from_slice(bytemuck::bytes_of(&(level as u32)));
+        }
+
+        let level_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Depth pyramid level buffer"),
+            contents: &level_data,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                // Offsets and sizes of the levels
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<PyramidUniform>() as u64),
+                    },
+                    count: None,
+                },
+                // Written level
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(4),
+                    },
+                    count: None,
+                },
+                // Pyramid: array<f32>
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Depth buffer
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("depth_pyramid.wgsl"))),
+        });
+
+        let create_pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Depth pyramid"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point,
+            })
+        };
+
+        let targets = Self::create_targets(device, &bind_group_layout, &level_buf, size);
+
+        DepthPyramid {
+            copy_pipeline: create_pipeline("cs_copy"),
+            downsample_pipeline: create_pipeline("cs_downsample"),
+            bind_group_layout,
+            level_buf,
+            level_stride,
+            targets,
+        }
+    }
+
+    fn create_targets(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        level_buf: &wgpu::Buffer,
+        (width, height): (u32, u32),
+    ) -> Targets {
+        let (width, height) = (width.max(1), height.max(1));
+        let levels = (32 - width.max(height).leading_zeros()).min(MAX_LEVELS as u32);
+        let level_sizes: Vec<(u32, u32)> = (0..levels)
+            .map(|level| ((width >> level).max(1), (height >> level).max(1)))
+            .collect();
+
+        let mut uniform = PyramidUniform {
+            count: levels,
+            _padding: [0; 3],
+            levels: [[0; 4]; MAX_LEVELS],
+        };
+        let mut offset = 0;
+        for (level, &(width, height)) in level_sizes.iter().enumerate() {
+            uniform.levels[level] = [offset, width, height, 0];
+            offset += width * height;
+        }
+
+        let depth = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth buffer"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let depth_view = depth.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let uniform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Depth pyramid uniform buffer"),
+            contents: bytemuck::bytes_of(&uniform),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let pyramid_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Depth pyramid buffer"),
+            size: 4 * offset as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: level_buf,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(4),
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: pyramid_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&depth_view),
+                },
+            ],
+        });
+
+        Targets {
+            depth_view,
+            uniform_buf,
+            pyramid_buf,
+            level_sizes,
+            bind_group,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, size: (u32, u32)) {
+        self.targets = Self::create_targets(device, &self.bind_group_layout, &self.level_buf, size);
+    }
+
+    // Depth attachment the scene has to be rendered with
+    pub fn depth_view(&self) -> &wgpu::TextureView {
+        &self.targets.depth_view
+    }
+
+    // Offsets and sizes of the levels: uniform Pyramid in depth_pyramid.wgsl
+    pub fn uniform_buffer(&self) -> &wgpu::Buffer {
+        &self.targets.uniform_buf
+    }
+
+    // All levels of the pyramid: array<f32>
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.targets.pyramid_buf
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.targets.level_sizes[0]
+    }
+
+    // Records the building of the pyramid from the current content of the depth buffer
+    pub fn build(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Depth pyramid"),
+        });
+
+        for (level, &(width, height)) in self.targets.level_sizes.iter().enumerate() {
+            let pipeline = match level {
+                0 => &self.copy_pipeline,
+                _ => &self.downsample_pipeline,
+            };
+            cpass.set_pipeline(pipeline);
+            cpass.set_bind_group(0, &self.targets.bind_group, &[level as u32 * self.level_stride]);
+            cpass.dispatch_workgroups(width.div_ceil(WORKGROUP_SIZE), height.div_ceil(WORKGROUP_SIZE), 1);
+        }
+    }
+}
@@ -0,0 +1,58 @@
+
+// Level 0 is a copy of the depth buffer, every other level keeps the farthest depth of the texels below it.
+// The levels are stored one after another in a storage buffer (storage textures aren't supported by the GL backend).
+
+struct Pyramid {
+    count: u32,
+    levels: array<vec4<u32>, 16>, // Offset in the buffer, width and height
+}
+
+struct Level {
+    index: u32,
+}
+
+@group(0) @binding(0) var<uniform> pyramid: Pyramid;
+@group(0) @binding(1) var<uniform> level: Level;
+@group(0) @binding(2) var<storage, read_write> hiz: array<f32>;
+@group(0) @binding(3) var depth: texture_2d<f32>;
+
+fn texel_index(level: u32, coords: vec2<u32>) -> u32 {
+    let info = pyramid.levels[level];
+    return info.x + coords.y * info.y + coords.x;
+}
+
+@compute @workgroup_size(8, 8)
+fn cs_copy(@builtin(global_invocation_id) id: vec3<u32>) {
+    let dims = pyramid.levels[0].yz;
+    if (id.x >= dims.x || id.y >= dims.y) {
+        return;
+    }
+
+    hiz[texel_index(0u, id.xy)] = textureLoad(depth, vec2<i32>(id.xy), 0).r;
+}
+
+@compute @workgroup_size(8, 8)
+fn cs_downsample(@builtin(global_invocation_id) id: vec3<u32>) {
+    let dims = pyramid.levels[level.index].yz;
+    if (id.x >= dims.x || id.y >= dims.y) {
+        return;
+    }
+
+    // With odd size of the input the last texel also covers the remaining row / column
+    let input = level.index - 1u;
+    let input_dims = pyramid.levels[input].yz;
+    let extra = vec2<u32>(
+        select(0u, 1u, id.x == dims.x - 1u && input_dims.x > dims.x * 2u),
+        select(0u, 1u, id.y == dims.y - 1u && input_dims.y > dims.y * 2u),
+    );
+
+    var result = 0.0;
+    for (var y = 0u; y <= 1u + extra.y; y = y + 1u) {
+        for (var x = 0u; x <= 1u + extra.x; x = x + 1u) {
+            let texel = min(id.xy * 2u + vec2<u32>(x, y), input_dims - 1u);
+            result = max(result, hiz[texel_index(input, texel)]);
+        }
+    }
+
+    hiz[texel_index(level.index, id.xy)] = result;
+}
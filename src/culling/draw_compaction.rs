@@ -0,0 +1,162 @@
+use wgpu::util::DeviceExt;
+use std::borrow::Cow;
+use super::DrawCommand;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct RangeUniform {
+    first: u32,
+    count: u32,
+    _padding: [u32; 2],
+}
+
+// Removes the draw commands without instances and writes the number of the remaining ones into the count buffer,
+// for multi_draw_indexed_indirect_count (Features::MULTI_DRAW_INDIRECT_COUNT).
+// The order of the compacted commands isn't preserved.
+pub struct DrawCompaction {
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    compacted_buf: wgpu::Buffer,
+    count_buf: wgpu::Buffer,
+    max_count: u32,
+}
+
+impl DrawCompaction {
+    // Compacts count commands starting at the command first of the buffer (needs BufferUsages::STORAGE)
+    pub fn new(device: &wgpu::Device, commands: &wgpu::Buffer, first: u32, count: u32) -> Self {
+        let uniform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Draw compaction uniform buffer"),
+            contents: bytemuck::bytes_of(&RangeUniform { first, count, _padding: [0; 2] }),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let compacted_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Compacted indirect buffer"),
+            size: (count.max(1) as usize * std::mem::size_of::<DrawCommand>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::INDIRECT | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let count_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Draw count buffer"),
+            size: 4,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::INDIRECT
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let storage_entry = |binding: u32, read_only: bool| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                // Range of the compacted commands
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<RangeUniform>() as u64),
+                    },
+                    count: None,
+                },
+                // Draw commands written by the culling: array<DrawCommand>
+                storage_entry(1, true),
+                // Non-empty draw commands: array<DrawCommand>
+                storage_entry(2, false),
+                // Number of the non-empty draw commands: atomic<u32>
+                storage_entry(3, false),
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: commands.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: compacted_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: count_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("draw_compaction.wgsl"))),
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Draw compaction"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        DrawCompaction {
+            pipeline,
+            bind_group,
+            compacted_buf,
+            count_buf,
+            max_count: count,
+        }
+    }
+
+    // Records the compaction, has to follow the pass writing the commands
+    pub fn compact(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.clear_buffer(&self.count_buf, 0, None);
+
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Draw compaction"),
+        });
+        cpass.set_pipeline(&self.pipeline);
+        cpass.set_bind_group(0, &self.bind_group, &[]);
+        cpass.dispatch_workgroups(self.max_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+
+    // Non-empty draw commands, only the first count_buffer commands are valid
+    pub fn indirect_buffer(&self) -> &wgpu::Buffer {
+        &self.compacted_buf
+    }
+
+    // Number of the non-empty draw commands: u32
+    pub fn count_buffer(&self) -> &wgpu::Buffer {
+        &self.count_buf
+    }
+
+    // Upper bound of the count, max_count of multi_draw_indexed_indirect_count
+    pub fn max_count(&self) -> u32 {
+        self.max_count
+    }
+}
@@ -0,0 +1,35 @@
+
+struct DrawCommand {
+    index_count: u32,
+    instance_count: u32,
+    base_index: u32,
+    vertex_offset: i32,
+    base_instance: u32,
+}
+
+struct Range {
+    first: u32,
+    count: u32,
+}
+
+@group(0) @binding(0) var<uniform> range: Range;
+@group(0) @binding(1) var<storage, read> commands: array<DrawCommand>;
+@group(0) @binding(2) var<storage, read_write> compacted: array<DrawCommand>;
+@group(0) @binding(3) var<storage, read_write> draw_count: atomic<u32>;
+
+// Copies the commands with at least one instance to the front of the compacted buffer
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if (i >= range.count) {
+        return;
+    }
+
+    let command = commands[range.first + i];
+    if (command.instance_count == 0u) {
+        return;
+    }
+
+    let slot = atomicAdd(&draw_count, 1u);
+    compacted[slot] = command;
+}
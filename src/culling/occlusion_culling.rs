@@ -0,0 +1,469 @@
+use wgpu::util::DeviceExt;
+use std::borrow::Cow;
+use std::sync::mpsc;
+use super::{visible_size, CullInstance, DepthPyramid, DrawCommand, Frustum, DEFAULT_LOD_SIZE};
+use crate::shapes::MAX_LODS;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct CullUniform {
+    planes: [[f32; 4]; 6],
+    view_proj: [[f32; 4]; 4],
+    size: [f32; 2],
+    instance_count: u32,
+    history: u32,
+    commands_count: u32,
+    lod_size: f32,
+    _padding: [u32; 2],
+}
+
+// Number of instances culled in each step of the last frame
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable, Debug, Default, PartialEq, Eq)]
+pub struct OcclusionStats {
+    pub frustum: u32,     // Outside of the view frustum
+    pub phase1: u32,      // Occluded by the depth pyramid of the previous frame
+    pub phase2: u32,      // Still occluded by the depth pyramid of the first phase
+    pub disoccluded: u32, // Occluded in the first phase, but drawn in the second
+}
+
+// Two-phase occlusion culling against a hierarchical depth buffer (Hi-Z).
+// Every frame:
+//   1. cull_phase1 - frustum culling and test against the pyramid of the previous frame
+//   2. draw the commands of phase 0 into depth_view
+//   3. build_pyramid - pyramid of the depth of the visible objects
+//   4. cull_phase2 - the instances occluded in phase 1 are tested again
+//   5. draw the commands of phase 1 (disoccluded instances) keeping the content of depth_view
+// The draws have to read the index of the instance from the visible buffer (visible[instance_index]).
+// Without the depth of a previous frame (the first frame, after resize) phase 1 is plain frustum culling.
+// Every drawn instance goes to the command of its batch with the level of detail for its size on the screen.
+pub struct OcclusionCulling {
+    phase1_pipeline: wgpu::ComputePipeline,
+    phase2_pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    uniform_buf: wgpu::Buffer,
+    instances_buf: wgpu::Buffer,
+    reset_buf: wgpu::Buffer, // Commands of both phases with zero instance_count followed by zeroed stats
+    indirect_buf: wgpu::Buffer,
+    visible_buf: wgpu::Buffer,
+    occluded_buf: wgpu::Buffer,
+    stats_buf: wgpu::Buffer,
+    stats_readback_buf: wgpu::Buffer,
+    stats_receiver: Option<mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>>, // Readback waiting for the mapping
+    pyramid: DepthPyramid,
+    commands: Vec<DrawCommand>, // Commands of both phases with zero instance_count
+    view_proj: glam::Mat4,
+    lod_size: f32,
+    history: bool, // Whether the pyramid holds the depth of the previous frame
+    commands_count: u32,
+    instance_count: u32,
+    phase_size: u32, // Region of the commands of one phase in the visible buffer
+}
+
+impl OcclusionCulling {
+    // Region of every command in the visible buffer starts at its base_instance and has to be large enough
+    // for all instances of the command, size is the size of the rendered frame
+    pub fn new(device: &wgpu::Device, instances: &[CullInstance], commands: &[DrawCommand], size: (u32, u32)) -> Self {
+        // Bindings can't be empty
+        let instances_data = match instances.is_empty() {
+            true => vec![CullInstance::default()],
+            false => instances.to_vec(),
+        };
+        let commands_data = match commands.is_empty() {
+            true => vec![DrawCommand::default()],
+            false => commands.to_vec(),
+        };
+
+        // An instance can be drawn by any command of its batch, so the regions of the commands of a batch
+        // together take up to MAX_LODS times the instances
+        let phase_size = visible_size(&instances_data, &commands_data).max(MAX_LODS * instances_data.len()) as u32;
+
+        // The second phase has its own commands with the region of the visible buffer after the first phase
+        let phase2_commands = commands_data.iter().map(|command| DrawCommand {
+            base_instance: command.base_instance + phase_size,
+            ..*command
+        });
+        let all_commands: Vec<DrawCommand> = commands_data.iter().copied().chain(phase2_commands).collect();
+        let reset_commands: Vec<DrawCommand> = all_commands
+            .iter()
+            .map(|command| DrawCommand { instance_count: 0, ..*command })
+            .collect();
+
+        let mut reset_data: Vec<u8> = bytemuck::cast_slice(&reset_commands).to_vec();
+        reset_data.extend_from_slice(bytemuck::bytes_of(&OcclusionStats::default()));
+
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Culling uniform buffer"),
+            size: std::mem::size_of::<CullUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let instances_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Culling instances buffer"),
+            contents: bytemuck::cast_slice(&instances_data),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let reset_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Culling reset buffer"),
+            contents: &reset_data,
+            usage: wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let indirect_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indirect buffer"),
+            contents: bytemuck::cast_slice(&reset_commands),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::INDIRECT
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+        });
+
+        let visible_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Visible instances buffer"),
+            size: (2 * 4 * phase_size) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let occluded_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Occluded instances buffer"),
+            size: (4 * instances_data.len()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let stats_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Culling stats buffer"),
+            size: std::mem::size_of::<OcclusionStats>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let stats_readback_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Culling stats readback buffer"),
+            size: stats_buf.size(),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let storage_entry = |binding: u32, read_only: bool| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                // Frustum planes, view projection matrix and sizes
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<CullUniform>() as u64),
+                    },
+                    count: None,
+                },
+                // Bounding spheres: array<Instance>
+                storage_entry(1, true),
+                // Draw commands of both phases: array<DrawCommand>
+                storage_entry(2, false),
+                // Indices of the visible instances: array<u32>
+                storage_entry(3, false),
+                // Instances occluded in the first phase: array<u32>
+                storage_entry(4, false),
+                // Stats
+                storage_entry(5, false),
+                // Depth pyramid: array<f32>
+                storage_entry(6, true),
+                // Offsets and sizes of the pyramid levels
+                wgpu::BindGroupLayoutEntry {
+                    binding: 7,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pyramid = DepthPyramid::new(device, size);
+        let bind_group = Self::create_bind_group(
+            device,
+            &bind_group_layout,
+            [&uniform_buf, &instances_buf, &indirect_buf, &visible_buf, &occluded_buf, &stats_buf],
+            &pyramid,
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("occlusion_culling.wgsl"))),
+        });
+
+        let create_pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Occlusion culling"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point,
+            })
+        };
+
+        OcclusionCulling {
+            phase1_pipeline: create_pipeline("cs_phase1"),
+            phase2_pipeline: create_pipeline("cs_phase2"),
+            bind_group_layout,
+            bind_group,
+            uniform_buf,
+            instances_buf,
+            reset_buf,
+            indirect_buf,
+            visible_buf,
+            occluded_buf,
+            stats_buf,
+            stats_readback_buf,
+            stats_receiver: None,
+            pyramid,
+            commands: reset_commands[..2 * commands.len()].to_vec(),
+            view_proj: glam::Mat4::IDENTITY,
+            lod_size: DEFAULT_LOD_SIZE,
+            history: false,
+            commands_count: commands.len() as u32,
+            instance_count: instances.len() as u32,
+            phase_size,
+        }
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        buffers: [&wgpu::Buffer; 6],
+        pyramid: &DepthPyramid,
+    ) -> wgpu::BindGroup {
+        let entries: Vec<wgpu::BindGroupEntry> = buffers
+            .into_iter()
+            .chain([pyramid.buffer(), pyramid.uniform_buffer()])
+            .enumerate()
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout,
+            entries: &entries,
+        })
+    }
+
+    // Recreates the depth buffer, the first frame after a resize isn't occlusion culled
+    pub fn resize(&mut self, device: &wgpu::Device, size: (u32, u32)) {
+        self.pyramid.resize(device, size);
+        self.bind_group = Self::create_bind_group(
+            device,
+            &self.bind_group_layout,
+            [
+                &self.uniform_buf,
+                &self.instances_buf,
+                &self.indirect_buf,
+                &self.visible_buf,
+                &self.occluded_buf,
+                &self.stats_buf,
+            ],
+            &self.pyramid,
+        );
+        self.history = false;
+    }
+
+    // Has to be called whenever the camera changes (projection * view)
+    pub fn update_camera(&mut self, view_proj: glam::Mat4) {
+        self.view_proj = view_proj;
+    }
+
+    // Instances whose diameter on the screen is below the fraction of the frame height are drawn
+    // by their first coarser level of detail, every further level is used at half the size.
+    // Zero always draws the full meshes.
+    pub fn set_lod_size(&mut self, lod_size: f32) {
+        self.lod_size = lod_size;
+    }
+
+    // Replaces the instances starting at first, the number of instances can't grow
+    pub fn update_instances(&self, queue: &wgpu::Queue, first: u32, instances: &[CullInstance]) {
+        assert!(first as usize + instances.len() <= self.instance_count as usize, "Instances out of range");
+        let offset = first as usize * std::mem::size_of::<CullInstance>();
+        queue.write_buffer(&self.instances_buf, offset as wgpu::BufferAddress, bytemuck::cast_slice(instances));
+    }
+
+    // Replaces the draw commands (only instance_count and base_instance are expected to change),
+    // the number of commands has to stay the same and their regions have to fit the visible buffer
+    pub fn update_commands(&mut self, queue: &wgpu::Queue, commands: &[DrawCommand]) {
+        assert_eq!(commands.len(), self.commands_count as usize, "Number of commands can't change");
+        assert!(visible_size(&[], commands) <= self.phase_size as usize, "Commands don't fit the visible buffer");
+        if commands.is_empty() {
+            return;
+        }
+
+        let phase2_commands = commands.iter().map(|command| DrawCommand {
+            base_instance: command.base_instance + self.phase_size,
+            ..*command
+        });
+        self.commands = commands
+            .iter()
+            .copied()
+            .chain(phase2_commands)
+            .map(|command| DrawCommand { instance_count: 0, ..command })
+            .collect();
+        queue.write_buffer(&self.reset_buf, 0, bytemuck::cast_slice(&self.commands));
+    }
+
+    // Records the first phase: frustum culling and test against the previous frame
+    pub fn cull_phase1(&self, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder) {
+        let (width, height) = self.pyramid.size();
+        let uniform = CullUniform {
+            planes: Frustum::from_matrix(&self.view_proj).planes.map(|plane| plane.to_array()),
+            view_proj: self.view_proj.to_cols_array_2d(),
+            size: [width as f32, height as f32],
+            instance_count: self.instance_count,
+            history: self.history as u32,
+            commands_count: self.commands_count,
+            lod_size: self.lod_size,
+            _padding: [0; 2],
+        };
+        queue.write_buffer(&self.uniform_buf, 0, bytemuck::bytes_of(&uniform));
+
+        // Reset instance counts of both phases and the stats
+        let commands_size = self.phase_offset(2);
+        encoder.copy_buffer_to_buffer(&self.reset_buf, 0, &self.indirect_buf, 0, commands_size);
+        encoder.copy_buffer_to_buffer(
+            &self.reset_buf,
+            self.reset_buf.size() - self.stats_buf.size(),
+            &self.stats_buf,
+            0,
+            self.stats_buf.size(),
+        );
+
+        self.dispatch(encoder, &self.phase1_pipeline, "Occlusion culling (phase 1)");
+    }
+
+    // Records the building of the depth pyramid, the depth buffer has to contain the objects drawn in the first phase
+    pub fn build_pyramid(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        self.pyramid.build(encoder);
+        self.history = true;
+    }
+
+    // Records the second phase: instances occluded in the first phase tested against the new pyramid
+    pub fn cull_phase2(&self, encoder: &mut wgpu::CommandEncoder) {
+        self.dispatch(encoder, &self.phase2_pipeline, "Occlusion culling (phase 2)");
+    }
+
+    fn dispatch(&self, encoder: &mut wgpu::CommandEncoder, pipeline: &wgpu::ComputePipeline, label: &str) {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some(label) });
+        cpass.set_pipeline(pipeline);
+        cpass.set_bind_group(0, &self.bind_group, &[]);
+        cpass.dispatch_workgroups(self.instance_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+
+    // Depth attachment for both phases
+    pub fn depth_view(&self) -> &wgpu::TextureView {
+        self.pyramid.depth_view()
+    }
+
+    // Draw commands: commands_count commands of phase 0 followed by the commands of phase 1
+    pub fn indirect_buffer(&self) -> &wgpu::Buffer {
+        &self.indirect_buf
+    }
+
+    // Offset of the commands of the phase in the indirect buffer
+    pub fn phase_offset(&self, phase: u32) -> wgpu::BufferAddress {
+        (phase as usize * self.commands_count as usize * std::mem::size_of::<DrawCommand>()) as wgpu::BufferAddress
+    }
+
+    // Indices of the visible instances: array<u32>
+    pub fn visible_buffer(&self) -> &wgpu::Buffer {
+        &self.visible_buf
+    }
+
+    pub fn commands_count(&self) -> u32 {
+        self.commands_count
+    }
+
+    pub fn instance_count(&self) -> u32 {
+        self.instance_count
+    }
+
+    // Commands of both phases as they are in the indirect buffer, with zero instance_count
+    pub fn commands(&self) -> &[DrawCommand] {
+        &self.commands
+    }
+
+    // Starts reading back the stats of the last submitted frame, nothing happens while the previous
+    // readback is still pending. The stats are returned by poll_stats once the GPU finishes the frame.
+    pub fn request_stats(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.stats_receiver.is_some() {
+            return;
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Culling stats readback"),
+        });
+        encoder.copy_buffer_to_buffer(&self.stats_buf, 0, &self.stats_readback_buf, 0, self.stats_buf.size());
+        queue.submit(Some(encoder.finish()));
+
+        let (sender, receiver) = mpsc::channel();
+        self.stats_readback_buf.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.stats_receiver = Some(receiver);
+    }
+
+    // Stats requested by request_stats, None while they aren't read back yet (or weren't requested).
+    // Maintain::Poll doesn't block, Maintain::Wait waits for the GPU.
+    pub fn poll_stats(&mut self, device: &wgpu::Device, maintain: wgpu::Maintain) -> Option<Result<OcclusionStats, String>> {
+        let receiver = self.stats_receiver.as_ref()?;
+        device.poll(maintain);
+
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return None,
+            Err(mpsc::TryRecvError::Disconnected) => Err(wgpu::BufferAsyncError),
+        };
+        self.stats_receiver = None;
+        if result.is_err() {
+            return Some(Err("Unable to read back the culling stats".to_string()));
+        }
+
+        let stats = *bytemuck::from_bytes(&self.stats_readback_buf.slice(..).get_mapped_range());
+        self.stats_readback_buf.unmap();
+        Some(Ok(stats))
+    }
+
+    // Reads the stats of the last submitted frame, blocks until the GPU finishes it
+    pub fn read_stats(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<OcclusionStats, String> {
+        // A pending readback holds stats of an older frame
+        self.poll_stats(device, wgpu::Maintain::Wait);
+        self.request_stats(device, queue);
+        self.poll_stats(device, wgpu::Maintain::Wait)
+            .unwrap_or_else(|| Err("Culling stats weren't read back".to_string()))
+    }
+}
@@ -0,0 +1,179 @@
+
+struct DrawCommand {
+    index_count: u32,
+    instance_count: atomic<u32>,
+    base_index: u32,
+    vertex_offset: i32,
+    base_instance: u32,
+}
+
+struct Instance {
+    sphere: vec4<f32>, // Center and radius
+    batch: u32, // First command of the batch
+    lods: u32, // Number of commands of the batch, one for every level of detail
+}
+
+// Batch of the instances which aren't drawn
+let EMPTY_BATCH: u32 = 0xffffffffu;
+
+struct Cull {
+    planes: array<vec4<f32>, 6>,
+    view_proj: mat4x4<f32>,
+    size: vec2<f32>, // Size of the depth buffer in pixels
+    instance_count: u32,
+    history: u32, // Whether the pyramid contains the depth of the previous frame
+    commands_count: u32, // Number of commands of one phase
+    lod_size: f32, // Screen size below which the coarser levels of detail are used
+}
+
+struct Pyramid {
+    count: u32,
+    levels: array<vec4<u32>, 16>, // Offset in the buffer, width and height
+}
+
+struct Stats {
+    frustum: atomic<u32>, // Outside of the view frustum
+    phase1: atomic<u32>, // Occluded by the depth of the previous frame
+    phase2: atomic<u32>, // Still occluded by the depth of the first phase
+    disoccluded: atomic<u32>, // Drawn in the second phase
+}
+
+@group(0) @binding(0) var<uniform> cull: Cull;
+@group(0) @binding(1) var<storage, read> instances: array<Instance>;
+@group(0) @binding(2) var<storage, read_write> commands: array<DrawCommand>;
+@group(0) @binding(3) var<storage, read_write> visible: array<u32>;
+@group(0) @binding(4) var<storage, read_write> occluded: array<u32>;
+@group(0) @binding(5) var<storage, read_write> stats: Stats;
+@group(0) @binding(6) var<storage, read> hiz: array<f32>;
+@group(0) @binding(7) var<uniform> pyramid: Pyramid;
+
+fn load_hiz(level: u32, coords: vec2<u32>) -> f32 {
+    let info = pyramid.levels[level];
+    return hiz[info.x + coords.y * info.y + coords.x];
+}
+
+fn in_frustum(sphere: vec4<f32>) -> bool {
+    for (var p = 0u; p < 6u; p = p + 1u) {
+        let plane = cull.planes[p];
+        if (dot(plane.xyz, sphere.xyz) + plane.w < -sphere.w) {
+            return false;
+        }
+    }
+    return true;
+}
+
+// Tests the screen space bounds of the sphere against the depth pyramid
+fn is_occluded(sphere: vec4<f32>) -> bool {
+    var min_uv = vec2<f32>(1.0, 1.0);
+    var max_uv = vec2<f32>(0.0, 0.0);
+    var min_depth = 1.0;
+
+    // Project the corners of the box around the sphere
+    for (var c = 0u; c < 8u; c = c + 1u) {
+        let corner = vec3<f32>(
+            select(-1.0, 1.0, (c & 1u) != 0u),
+            select(-1.0, 1.0, (c & 2u) != 0u),
+            select(-1.0, 1.0, (c & 4u) != 0u),
+        );
+        let clip = cull.view_proj * vec4<f32>(sphere.xyz + corner * sphere.w, 1.0);
+
+        // The box crosses the camera plane
+        if (clip.w <= 0.0) {
+            return false;
+        }
+
+        let ndc = clip.xyz / clip.w;
+        let uv = vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+        min_uv = min(min_uv, uv);
+        max_uv = max(max_uv, uv);
+        min_depth = min(min_depth, ndc.z);
+    }
+
+    min_uv = clamp(min_uv, vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 1.0));
+    max_uv = clamp(max_uv, vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 1.0));
+
+    // Level at which the bounds cover at most 2x2 texels
+    let size = (max_uv - min_uv) * cull.size;
+    let level = min(u32(ceil(log2(max(max(size.x, size.y), 1.0)))), pyramid.count - 1u);
+
+    let dims = pyramid.levels[level].yz;
+    let a = min(vec2<u32>(min_uv * vec2<f32>(dims)), dims - 1u);
+    let b = min(vec2<u32>(max_uv * vec2<f32>(dims)), dims - 1u);
+
+    let depth = max(
+        max(load_hiz(level, a), load_hiz(level, vec2<u32>(b.x, a.y))),
+        max(load_hiz(level, vec2<u32>(a.x, b.y)), load_hiz(level, b)),
+    );
+
+    return min_depth > depth;
+}
+
+// Level of detail by the diameter of the sphere as a fraction of the frame height,
+// the first coarser one below lod_size and every further one at half the size
+fn select_lod(sphere: vec4<f32>, lods: u32) -> u32 {
+    let w = (cull.view_proj * vec4<f32>(sphere.xyz, 1.0)).w;
+    if (w <= sphere.w) {
+        return 0u;
+    }
+
+    // Length of the row 1 of the view projection is the vertical scale of the projection
+    let m = cull.view_proj;
+    let f = length(vec3<f32>(m[0].y, m[1].y, m[2].y));
+    let size = sphere.w * f / w;
+    if (size >= cull.lod_size) {
+        return 0u;
+    }
+    return u32(min(1.0 + floor(log2(cull.lod_size / size)), f32(lods - 1u)));
+}
+
+// Compacts the instance into the region of the command of its level of detail in the given phase
+fn draw(instance: Instance, i: u32, phase: u32) {
+    let c = instance.batch + select_lod(instance.sphere, instance.lods) + phase * cull.commands_count;
+    let slot = atomicAdd(&commands[c].instance_count, 1u);
+    visible[commands[c].base_instance + slot] = i;
+}
+
+@compute @workgroup_size(64)
+fn cs_phase1(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if (i >= cull.instance_count) {
+        return;
+    }
+
+    occluded[i] = 0u;
+    let instance = instances[i];
+    if (instance.batch == EMPTY_BATCH) {
+        return;
+    }
+
+    if (!in_frustum(instance.sphere)) {
+        atomicAdd(&stats.frustum, 1u);
+        return;
+    }
+
+    if (cull.history != 0u && is_occluded(instance.sphere)) {
+        // Tested again in the second phase
+        occluded[i] = 1u;
+        atomicAdd(&stats.phase1, 1u);
+        return;
+    }
+
+    draw(instance, i, 0u);
+}
+
+@compute @workgroup_size(64)
+fn cs_phase2(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if (i >= cull.instance_count || occluded[i] == 0u) {
+        return;
+    }
+
+    let instance = instances[i];
+    if (is_occluded(instance.sphere)) {
+        atomicAdd(&stats.phase2, 1u);
+        return;
+    }
+
+    atomicAdd(&stats.disoccluded, 1u);
+    draw(instance, i, 1u);
+}
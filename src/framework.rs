@@ -0,0 +1,733 @@
+mod present;
+mod cli;
+mod stats;
+mod profiler;
+mod capabilities;
+
+pub use present::{PresentSettings, FrameLimiter, parse_present_mode};
+pub use cli::{Args, CliOption, Options};
+pub use stats::{FrameStats, FrameSample, Metric, Summary};
+pub use profiler::{Profiler, PassScope};
+pub use capabilities::Capabilities;
+
+use crate::post_processing::Blit;
+use winit::event_loop::{ EventLoop, ControlFlow };
+use winit::event;
+use winit::event::WindowEvent;
+use std::time::{Duration, Instant};
+use std::path::Path;
+
+// Size of the offscreen frame when --width or --height isn't specified
+const DEFAULT_WIDTH: u32 = 800;
+const DEFAULT_HEIGHT: u32 = 600;
+
+// Formats the offscreen frame can have, the format policy of the example chooses from them
+const HEADLESS_FORMATS: [wgpu::TextureFormat; 4] = [
+    wgpu::TextureFormat::Rgba8Unorm,
+    wgpu::TextureFormat::Rgba8UnormSrgb,
+    wgpu::TextureFormat::Bgra8Unorm,
+    wgpu::TextureFormat::Bgra8UnormSrgb,
+];
+
+// Number of frames summarized in the periodic print
+const STATS_INTERVAL: usize = 100;
+
+pub trait Example: 'static + Sized {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        options: &Options,
+    ) -> Self;
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    );
+    fn update(&mut self, event: WindowEvent);
+    fn render(
+        &mut self,
+        view: &wgpu::TextureView,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        spawner: &Spawner,
+        profiler: &mut Profiler,
+    );
+    // Policy used to choose the surface format
+    fn format_policy() -> FormatPolicy {
+        FormatPolicy::default()
+    }
+    // Extra command line options of the example, their values are passed to init
+    fn options() -> Vec<CliOption> {
+        Vec::new()
+    }
+    // Checks the values of the options before init, errors are reported like the invalid framework options
+    fn check_options(_options: &Options) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+// Describes which surface format should be chosen from the formats supported by the adapter
+#[derive(Clone, Debug)]
+pub struct FormatPolicy {
+    pub preferred: Vec<wgpu::TextureFormat>, // Formats to try first (in order)
+    pub srgb: bool, // Whether sRGB or linear format should be chosen if none of the preferred is supported
+}
+
+impl Default for FormatPolicy {
+    fn default() -> Self {
+        Self {
+            preferred: vec![wgpu::TextureFormat::Rgba8Unorm, wgpu::TextureFormat::Bgra8Unorm],
+            srgb: false,
+        }
+    }
+}
+
+impl FormatPolicy {
+    // Picks the first supported preferred format, then any format matching the sRGB choice
+    // and falls back to the first supported format
+    pub fn select(&self, supported: &[wgpu::TextureFormat]) -> Option<wgpu::TextureFormat> {
+        self.preferred
+            .iter()
+            .find(|format| supported.contains(format))
+            .or_else(|| supported.iter().find(|format| format.describe().srgb == self.srgb))
+            .or_else(|| supported.first())
+            .copied()
+    }
+}
+
+struct Setup {
+    window: winit::window::Window,
+    event_loop: EventLoop<()>,
+    instance: wgpu::Instance,
+    size: winit::dpi::PhysicalSize<u32>,
+    surface: wgpu::Surface,
+    adapter: wgpu::Adapter,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+}
+
+pub struct Spawner<'a> {
+    executor: async_executor::LocalExecutor<'a>,
+}
+
+impl<'a> Spawner<'a> {
+    fn new() -> Self {
+        Self {
+            executor: async_executor::LocalExecutor::new(),
+        }
+    }
+
+    fn run_until_stalled(&self) {
+        while self.executor.try_tick() {}
+    }
+}
+
+// Picks the adapter whose name contains --adapter, otherwise the one specified by WGPU_ADAPTER_NAME or the default one
+async fn request_adapter(
+    instance: &wgpu::Instance,
+    backend: wgpu::Backends,
+    name: Option<&str>,
+    compatible_surface: Option<&wgpu::Surface>,
+) -> Option<wgpu::Adapter> {
+    match name {
+        Some(name) => {
+            let name = name.to_lowercase();
+            instance.enumerate_adapters(backend).find(|adapter| {
+                adapter.get_info().name.to_lowercase().contains(&name)
+                    && compatible_surface.is_none_or(|surface| adapter.is_surface_supported(surface))
+            })
+        }
+        None => wgpu::util::initialize_adapter_from_env_or_default(instance, backend, compatible_surface).await,
+    }
+}
+
+async fn setup(title: &str, args: &Args) -> Setup {
+    // Create window builder to build our window
+    let event_loop = EventLoop::new();
+    let mut builder = winit::window::WindowBuilder::new();
+    builder = builder.with_title(title);
+    if args.width.is_some() || args.height.is_some() {
+        builder = builder.with_inner_size(winit::dpi::PhysicalSize::new(
+            args.width.unwrap_or(DEFAULT_WIDTH),
+            args.height.unwrap_or(DEFAULT_HEIGHT),
+        ));
+    }
+
+    // Building the window
+    let window = builder.build(&event_loop).unwrap();
+
+    // Describes which backends we want to use (Vulkan, DirectX11, ...)
+    let backend = args.backend
+        .or_else(wgpu::util::backend_bits_from_env)
+        .unwrap_or_else(wgpu::Backends::all);
+    let instance = wgpu::Instance::new(backend); // Create WGPU instance (context for all other wgpu objects)
+
+    // Physical size of window's client area (content of the window, excluding the title bar and borders)
+    let size = window.inner_size();
+    // Surface represents a surface (window) onto which rendered images may be presented
+    let surface = unsafe { instance.create_surface(&window) };
+
+    // Handle to our graphical or compute device
+    let adapter = request_adapter(
+        &instance,
+        backend,
+        args.adapter.as_deref(),
+        Some(&surface),
+    ).await.expect("No suitable GPU adapters on the system!");
+    
+    // Device represents a connection to our graphics or compute device
+    // It is responsible of most rendering and compute resources
+    // Queue is a representation of a queue of work that is submitted to the GPU for execution
+    let (device, queue) = adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: None,
+            features: adapter.features(), // Set features to adapter features
+            limits: adapter.limits() // Sets the limits to the limits of our actual graphics adapter
+        },
+        None
+    ).await.expect("Unable to find suitable GPU adapter!");
+
+    Setup {
+        window,
+        event_loop,
+        instance,
+        size,
+        surface,
+        adapter,
+        device,
+        queue
+    }
+}
+
+// Device without a window, also used by the tools that render offscreen themselves
+pub struct HeadlessSetup {
+    pub instance: wgpu::Instance,
+    pub adapter: wgpu::Adapter,
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+}
+
+pub async fn setup_headless(args: &Args) -> HeadlessSetup {
+    let backend = args.backend
+        .or_else(wgpu::util::backend_bits_from_env)
+        .unwrap_or_else(wgpu::Backends::all);
+    let instance = wgpu::Instance::new(backend);
+
+    // There is no surface to be compatible with, so any adapter will do.
+    // If there is no hardware adapter, try the fallback (software) one.
+    let adapter = match request_adapter(&instance, backend, args.adapter.as_deref(), None).await {
+        Some(adapter) => adapter,
+        None if args.adapter.is_some() => panic!("No adapter matching '{}'", args.adapter.as_deref().unwrap_or_default()),
+        None => instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: true,
+            compatible_surface: None,
+        }).await.expect("No suitable GPU adapters on the system!"),
+    };
+
+    let (device, queue) = adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: None,
+            features: adapter.features(),
+            limits: adapter.limits()
+        },
+        None
+    ).await.expect("Unable to find suitable GPU adapter!");
+
+    HeadlessSetup {
+        instance,
+        adapter,
+        device,
+        queue
+    }
+}
+
+// Copies the texture into a mappable buffer and reads it back as an RGBA image,
+// only 8-bit RGBA and BGRA formats can be captured
+pub fn capture_frame(
+    texture: &wgpu::Texture,
+    format: wgpu::TextureFormat,
+    size: (u32, u32),
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    spawner: &Spawner,
+) -> Result<image::RgbaImage, String> {
+    use wgpu::TextureFormat::{Bgra8Unorm, Bgra8UnormSrgb, Rgba8Unorm, Rgba8UnormSrgb};
+    if !matches!(format, Rgba8Unorm | Rgba8UnormSrgb | Bgra8Unorm | Bgra8UnormSrgb) {
+        return Err(format!("Frames of format {:?} can't be captured", format));
+    }
+    let (width, height) = size;
+
+    // Every row in the buffer has to be aligned to 256 bytes
+    let unpadded_bytes_per_row = format.describe().block_size as u32 * width;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+    let output_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Capture buffer"),
+        size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &output_buf,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(padded_bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit(Some(encoder.finish()));
+
+    // Wait until the buffer is mapped
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let buffer_slice = output_buf.slice(..);
+    buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    spawner.run_until_stalled();
+    receiver
+        .recv()
+        .map_err(|_| "Capture buffer was dropped before being mapped".to_string())?
+        .map_err(|err| format!("Unable to map the capture buffer: {}", err))?;
+
+    // Strip the row padding
+    let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+    {
+        let data = buffer_slice.get_mapped_range();
+        for row in data.chunks(padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
+    }
+    output_buf.unmap();
+
+    // BGRA surfaces have the red and blue channels swapped
+    if let Bgra8Unorm | Bgra8UnormSrgb = format {
+        for pixel in pixels.chunks_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+
+    image::RgbaImage::from_raw(width, height, pixels).ok_or_else(|| "Captured frame has unexpected size".to_string())
+}
+
+// Captures the texture and writes it into a PNG file
+pub fn save_frame(
+    path: &Path,
+    texture: &wgpu::Texture,
+    format: wgpu::TextureFormat,
+    size: (u32, u32),
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    spawner: &Spawner,
+) {
+    let result = capture_frame(texture, format, size, device, queue, spawner)
+        .and_then(|image| image.save_with_format(path, image::ImageFormat::Png).map_err(|err| err.to_string()));
+    match result {
+        Ok(()) => println!("Frame saved to {}", path.display()),
+        Err(err) => eprintln!("Unable to save frame to {}: {}", path.display(), err),
+    }
+}
+
+// Renders the frame and returns its CPU time split into encoding and submission
+fn timed_render<E: Example>(
+    example: &mut E,
+    view: &wgpu::TextureView,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    spawner: &Spawner,
+    profiler: &mut Profiler,
+) -> (Duration, Duration) {
+    profiler.take_submit_time();
+    let start = Instant::now();
+    example.render(view, device, queue, spawner, profiler);
+    let render_time = start.elapsed();
+    let submit_time = profiler.take_submit_time();
+    profiler.end_frame(device, queue);
+
+    (render_time.saturating_sub(submit_time), submit_time)
+}
+
+// Prints the statistics of the whole run and exports the samples if --stats is specified
+fn finish_stats(stats: &FrameStats, profiler: &mut Profiler, device: &wgpu::Device, args: &Args) {
+    if stats.samples().is_empty() {
+        return;
+    }
+
+    println!("{}", stats.report());
+    println!("{}", stats.histogram());
+
+    profiler.flush(device);
+    if let Some(report) = profiler.report() {
+        println!("{}", report);
+    }
+
+    if let Some(path) = &args.stats {
+        match stats.export(path) {
+            Ok(()) => println!("Frame timings saved to {}", path.display()),
+            Err(err) => eprintln!("Unable to save frame timings to {}: {}", path.display(), err),
+        }
+    }
+}
+
+// Offscreen render target used instead of the surface texture
+fn create_headless_target(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Headless target"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: config.usage | wgpu::TextureUsages::COPY_SRC,
+    })
+}
+
+fn start_headless<E: Example>(
+    HeadlessSetup {
+        instance: _instance,
+        adapter,
+        device,
+        queue
+    }: HeadlessSetup,
+    args: &Args,
+) -> image::RgbaImage {
+    let spawner = Spawner::new();
+    let frames = args.frames.unwrap_or(1);
+
+    // Chosen like the surface format, --format makes it the only supported one
+    let formats = args.format.map_or(HEADLESS_FORMATS.to_vec(), |format| vec![format]);
+    let format = E::format_policy().select(&formats).unwrap();
+
+    // The examples only read the format and size from the configuration
+    let config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        format,
+        width: args.width.unwrap_or(DEFAULT_WIDTH).max(1),
+        height: args.height.unwrap_or(DEFAULT_HEIGHT).max(1),
+        present_mode: wgpu::PresentMode::Fifo,
+        alpha_mode: wgpu::CompositeAlphaMode::Auto,
+    };
+
+    let target = create_headless_target(&device, &config);
+    let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let mut example = E::init(&config, &adapter, &device, &queue, &args.options);
+
+    let mut profiler = Profiler::new(&device, &queue);
+    let mut stats = FrameStats::new();
+
+    for _ in 0..frames {
+        let frame_start = Instant::now();
+        let (encode_time, submit_time) = timed_render(&mut example, &view, &device, &queue, &spawner, &mut profiler);
+        device.poll(wgpu::Maintain::Wait);
+        spawner.run_until_stalled();
+        stats.push(FrameSample::new(frame_start.elapsed(), encode_time, submit_time));
+    }
+
+    // Headless runs (e.g. the tests) print nothing unless the timings are exported
+    if args.stats.is_some() {
+        finish_stats(&stats, &mut profiler, &device, args);
+    }
+
+    // The headless formats can always be captured
+    capture_frame(&target, config.format, (config.width, config.height), &device, &queue, &spawner).unwrap()
+}
+
+fn start<E: Example>(
+    Setup {
+        window,
+        event_loop,
+        instance,
+        size,
+        surface,
+        adapter,
+        device,
+        queue
+    }: Setup,
+    args: Args,
+) {
+    let spawner = Spawner::new();
+
+    let formats = surface.get_supported_formats(&adapter);
+    let format = E::format_policy()
+        .select(&formats)
+        .expect("Surface isn't supported by the adapter");
+    println!("Surface format: {:?}", format);
+
+    let present_modes = surface.get_supported_present_modes(&adapter);
+    let mut present_settings = args.present;
+    let present_mode = present::supported_present_mode(present_settings.present_mode, &present_modes);
+    if present_mode != present_settings.present_mode {
+        eprintln!("Present mode {:?} isn't supported, using {:?}", present_settings.present_mode, present_mode);
+        present_settings.present_mode = present_mode;
+    }
+
+    // Configuration of the surface
+    let mut config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        format,
+        width: size.width,
+        height: size.height,
+        present_mode,
+        alpha_mode: wgpu::CompositeAlphaMode::Auto,
+    };
+    surface.configure(&device, &config);
+
+    let mut frame_limiter = FrameLimiter::new();
+    let mut last_frame_inst = Instant::now();
+    let mut profiler = Profiler::new(&device, &queue);
+    let mut stats = FrameStats::new();
+    // Frames since the last periodic print
+    let mut frame_count = 0;
+
+    let mut example = E::init(&config, &adapter, &device, &queue, &args.options);
+
+    // Set by F12, the next frame is rendered offscreen, saved as PNG and copied onto the surface
+    let mut capture_requested = false;
+    let screenshot_path = args.screenshot.clone().unwrap_or_else(|| "screenshot.png".into());
+
+    // Number of rendered frames, used for --frames
+    let mut rendered_frames = 0;
+
+    event_loop.run(move |event, _, control_flow| {
+        let _ = (&instance, &adapter); // force ownership by the closure
+        *control_flow = ControlFlow::Poll;
+        match event {
+            event::Event::RedrawEventsCleared => {
+                spawner.run_until_stalled();
+                window.request_redraw();
+            }
+            event::Event::WindowEvent {
+                event:
+                    WindowEvent::Resized(size)
+                    | WindowEvent::ScaleFactorChanged {
+                        new_inner_size: &mut size,
+                        ..
+                    },
+                ..
+            } => {
+                config.width = size.width.max(1);
+                config.height = size.height.max(1);
+                example.resize(&config, &device, &queue);
+                surface.configure(&device, &config);
+            }
+            event::Event::WindowEvent { event, .. } => match event {
+                WindowEvent::KeyboardInput {
+                    input:
+                        event::KeyboardInput {
+                            virtual_keycode: Some(event::VirtualKeyCode::Escape),
+                            state: event::ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                }
+                | WindowEvent::CloseRequested => {
+                    *control_flow = ControlFlow::Exit;
+                }
+                WindowEvent::KeyboardInput {
+                    input:
+                        event::KeyboardInput {
+                            virtual_keycode: Some(event::VirtualKeyCode::R),
+                            state: event::ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => {
+                    println!("{:#?}", instance.generate_report());
+                }
+                WindowEvent::KeyboardInput {
+                    input:
+                        event::KeyboardInput {
+                            virtual_keycode: Some(event::VirtualKeyCode::H),
+                            state: event::ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => {
+                    println!("{}", stats.histogram());
+                }
+                WindowEvent::KeyboardInput {
+                    input:
+                        event::KeyboardInput {
+                            virtual_keycode: Some(event::VirtualKeyCode::F12),
+                            state: event::ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => {
+                    capture_requested = true;
+                }
+                WindowEvent::KeyboardInput {
+                    input:
+                        event::KeyboardInput {
+                            virtual_keycode: Some(event::VirtualKeyCode::V),
+                            state: event::ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => {
+                    present_settings.present_mode = present::next_present_mode(config.present_mode, &present_modes);
+                    config.present_mode = present_settings.present_mode;
+                    surface.configure(&device, &config);
+                    println!("Present mode: {}", present_settings);
+
+                    // Don't mix frames from different present modes into the periodic print
+                    frame_count = 0;
+                    last_frame_inst = Instant::now();
+                }
+                _ => {
+                    example.update(event);
+                }
+            },
+            event::Event::RedrawRequested(_) => {
+                frame_limiter.wait(present_settings.fps_limit);
+
+                let frame_time = last_frame_inst.elapsed();
+                last_frame_inst = Instant::now();
+
+                let frame = match surface.get_current_texture() {
+                    Ok(frame) => frame,
+                    Err(_) => {
+                        surface.configure(&device, &config);
+                        surface.get_current_texture().expect("Failed to acquire next surface texture!")
+                    }
+                };
+
+                let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+                // The last frame is saved if --screenshot is specified
+                rendered_frames += 1;
+                let last_frame = args.frames == Some(rendered_frames);
+                if last_frame && args.screenshot.is_some() {
+                    capture_requested = true;
+                }
+
+                // The surface texture can't be copied from on every platform, so the captured frame
+                // is rendered into an offscreen texture, which is saved and copied onto the surface
+                let (encode_time, submit_time) = if capture_requested {
+                    capture_requested = false;
+                    let target = create_headless_target(&device, &config);
+                    let target_view = target.create_view(&wgpu::TextureViewDescriptor::default());
+                    let times = timed_render(&mut example, &target_view, &device, &queue, &spawner, &mut profiler);
+                    Blit::copy(&device, &target_view, config.format).resolve(&device, &queue, &view, &mut profiler);
+                    save_frame(
+                        &screenshot_path,
+                        &target,
+                        config.format,
+                        (config.width, config.height),
+                        &device,
+                        &queue,
+                        &spawner,
+                    );
+                    times
+                } else {
+                    timed_render(&mut example, &view, &device, &queue, &spawner, &mut profiler)
+                };
+                frame.present();
+
+                stats.push(FrameSample::new(frame_time, encode_time, submit_time));
+                frame_count += 1;
+                if frame_count == STATS_INTERVAL {
+                    if let Some(frame) = stats.summary(frame_count, |s| s.frame_ms) {
+                        let encode = stats.summary(frame_count, |s| s.encode_ms).unwrap_or_default();
+                        let submit = stats.summary(frame_count, |s| s.submit_ms).unwrap_or_default();
+                        println!(
+                            "Average fps: {:.1} ({}), frame {}, encode {:.2} ms, submit {:.2} ms",
+                            1000.0 / frame.mean, present_settings, frame, encode.mean, submit.mean
+                        );
+                    }
+                    if let Some(passes) = profiler.summary(frame_count) {
+                        println!("  {}", passes);
+                    }
+                    frame_count = 0;
+                }
+
+                if last_frame {
+                    *control_flow = ControlFlow::Exit;
+                }
+            }
+            event::Event::LoopDestroyed => {
+                finish_stats(&stats, &mut profiler, &device, &args);
+            }
+            _ => {}
+        }
+    });
+}
+
+// Parses the command line and runs the example in a window or offscreen (--headless)
+pub fn run<E: Example>(title: &str) {
+    let options = E::options();
+    let program = std::env::args().next().unwrap_or_else(|| String::from(title));
+
+    let args = match Args::from_env(&options).and_then(|args| E::check_options(&args.options).map(|()| args)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, Args::usage(&program, &options));
+            std::process::exit(2);
+        }
+    };
+
+    if args.help {
+        println!("{}", Args::usage(&program, &options));
+        return;
+    }
+
+    if args.headless {
+        let setup = pollster::block_on(setup_headless(&args));
+        let frame = start_headless::<E>(setup, &args);
+        if let Some(path) = &args.screenshot {
+            match frame.save_with_format(path, image::ImageFormat::Png) {
+                Ok(()) => println!("Frame saved to {}", path.display()),
+                Err(err) => eprintln!("Unable to save frame to {}: {}", path.display(), err),
+            }
+        }
+        return;
+    }
+
+    let setup = pollster::block_on(setup(title, &args));
+    start::<E>(setup, args);
+}
+
+// Runs the example without a window, rendering `frames` frames into an offscreen texture
+// Returns the last rendered frame
+pub fn run_headless<E: Example>(width: u32, height: u32, frames: u32) -> image::RgbaImage {
+    run_headless_with_format::<E>(width, height, frames, None)
+}
+
+// Like run_headless, but the offscreen frame has the given format instead of the one chosen by the format policy
+pub fn run_headless_with_format<E: Example>(
+    width: u32,
+    height: u32,
+    frames: u32,
+    format: Option<wgpu::TextureFormat>,
+) -> image::RgbaImage {
+    let args = Args {
+        width: Some(width),
+        height: Some(height),
+        headless: true,
+        frames: Some(frames),
+        format,
+        ..Default::default()
+    };
+    let setup = pollster::block_on(setup_headless(&args));
+    start_headless::<E>(setup, &args)
+}
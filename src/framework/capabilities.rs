@@ -0,0 +1,77 @@
+// Optional features used by the examples, each of them has a fallback when it's missing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    pub multi_draw_indirect: bool,
+    pub multi_draw_indirect_count: bool,
+    pub indirect_first_instance: bool, // base_instance of indirect draws is applied
+    pub texture_binding_array: bool,   // binding_array of textures indexed by non-uniform values
+}
+
+// Names accepted by Capabilities::disable
+const NAMES: [&str; 4] = [
+    "multi-draw-indirect",
+    "multi-draw-indirect-count",
+    "indirect-first-instance",
+    "texture-binding-array",
+];
+
+impl Capabilities {
+    pub fn from_features(features: wgpu::Features) -> Self {
+        let multi_draw_indirect = features.contains(wgpu::Features::MULTI_DRAW_INDIRECT);
+        Capabilities {
+            multi_draw_indirect,
+            multi_draw_indirect_count: multi_draw_indirect
+                && features.contains(wgpu::Features::MULTI_DRAW_INDIRECT_COUNT),
+            indirect_first_instance: features.contains(wgpu::Features::INDIRECT_FIRST_INSTANCE),
+            texture_binding_array: features.contains(
+                wgpu::Features::TEXTURE_BINDING_ARRAY
+                    | wgpu::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
+            ),
+        }
+    }
+
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self::from_features(device.features())
+    }
+
+    // Turns off the capabilities in the comma separated list (or all of them), so the fallbacks can be tested
+    pub fn disable(&mut self, list: &str) -> Result<(), String> {
+        for name in list.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            match name {
+                "all" => {
+                    *self = Capabilities {
+                        multi_draw_indirect: false,
+                        multi_draw_indirect_count: false,
+                        indirect_first_instance: false,
+                        texture_binding_array: false,
+                    }
+                }
+                "multi-draw-indirect" => {
+                    self.multi_draw_indirect = false;
+                    self.multi_draw_indirect_count = false;
+                }
+                "multi-draw-indirect-count" => self.multi_draw_indirect_count = false,
+                "indirect-first-instance" => self.indirect_first_instance = false,
+                "texture-binding-array" => self.texture_binding_array = false,
+                _ => return Err(format!("Unknown capability '{}', expected all or one of: {}", name, NAMES.join(", "))),
+            }
+        }
+        Ok(())
+    }
+
+    // Names of the missing capabilities
+    pub fn missing(&self) -> Vec<&'static str> {
+        let available = [
+            self.multi_draw_indirect,
+            self.multi_draw_indirect_count,
+            self.indirect_first_instance,
+            self.texture_binding_array,
+        ];
+        NAMES
+            .iter()
+            .zip(available)
+            .filter(|(_, available)| !available)
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use super::PresentSettings;
+
+// Extra command line option registered by an example, always takes a value
+#[derive(Clone, Copy, Debug)]
+pub struct CliOption {
+    pub name: &'static str, // Name without the leading dashes
+    pub value_name: &'static str, // Shown in the usage, e.g. <count>
+    pub help: &'static str,
+}
+
+// Values of the options registered by the example
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    values: HashMap<String, String>,
+}
+
+impl Options {
+    pub fn set(&mut self, name: &str, value: &str) {
+        self.values.insert(name.to_string(), value.to_string());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    // Parses the value of the option, None if it isn't specified
+    pub fn parse<T: FromStr>(&self, name: &str) -> Result<Option<T>, String>
+    where
+        T::Err: std::fmt::Display,
+    {
+        self.get(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|err| format!("Invalid value '{}' of --{}: {}", value, name, err))
+            })
+            .transpose()
+    }
+}
+
+// Command line arguments shared by all examples
+#[derive(Clone, Debug, Default)]
+pub struct Args {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub backend: Option<wgpu::Backends>,
+    pub adapter: Option<String>, // Substring of the adapter name
+    pub present: PresentSettings,
+    pub headless: bool,
+    pub frames: Option<u32>, // Number of frames to render before exiting
+    pub screenshot: Option<PathBuf>, // Where the last frame is saved
+    pub format: Option<wgpu::TextureFormat>, // Format of the offscreen frame, chosen by the format policy otherwise
+    pub stats: Option<PathBuf>, // Where the frame timings are exported at exit
+    pub help: bool,
+    pub options: Options, // Options registered by the example
+}
+
+// Options of the framework: (name, value name, help)
+const FRAMEWORK_OPTIONS: [(&str, &str, &str); 12] = [
+    ("width", "<pixels>", "Width of the window or the offscreen frame"),
+    ("height", "<pixels>", "Height of the window or the offscreen frame"),
+    ("backend", "<list>", "Comma separated backends: vulkan, metal, dx12, dx11, gl"),
+    ("adapter", "<name>", "Use the adapter whose name contains this text"),
+    ("present-mode", "<mode>", "fifo, mailbox, immediate, auto-vsync or auto-no-vsync"),
+    ("fps-limit", "<fps>", "Cap the frame rate"),
+    ("headless", "", "Render offscreen without a window"),
+    ("frames", "<count>", "Exit after rendering this number of frames"),
+    ("screenshot", "<path>", "Save the last frame into this PNG file"),
+    ("format", "<format>", "Offscreen frame format: rgba8unorm, rgba8unorm-srgb, bgra8unorm or bgra8unorm-srgb"),
+    ("stats", "<path>", "Export frame timings at exit (.csv or .json)"),
+    ("help", "", "Print this help"),
+];
+
+impl Args {
+    // Parses the arguments of the current process
+    pub fn from_env(options: &[CliOption]) -> Result<Args, String> {
+        Self::parse(std::env::args().skip(1), options)
+    }
+
+    // Parses the arguments (without the program name), values can be passed as `--name value` or `--name=value`
+    pub fn parse<I: IntoIterator<Item = String>>(args: I, options: &[CliOption]) -> Result<Args, String> {
+        let mut result = Args {
+            present: PresentSettings::from_env(),
+            ..Default::default()
+        };
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let name = arg
+                .strip_prefix("--")
+                .ok_or_else(|| format!("Unexpected argument '{}'", arg))?;
+            let (name, inline_value) = match name.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (name, None),
+            };
+
+            // Flags
+            match name {
+                "headless" => { result.headless = true; continue; }
+                "help" => { result.help = true; continue; }
+                _ => {}
+            }
+
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("Missing value of --{}", name))
+            };
+
+            match name {
+                "width" => result.width = Some(parse_value(name, &value()?)?),
+                "height" => result.height = Some(parse_value(name, &value()?)?),
+                "backend" => {
+                    let value = value()?;
+                    let backends = wgpu::util::parse_backends_from_comma_list(&value.to_lowercase());
+                    if backends.is_empty() {
+                        return Err(format!("Unknown backend '{}'", value));
+                    }
+                    result.backend = Some(backends);
+                }
+                "adapter" => result.adapter = Some(value()?),
+                "present-mode" => {
+                    let value = value()?;
+                    result.present.present_mode = super::parse_present_mode(&value)
+                        .ok_or_else(|| format!("Unknown present mode '{}'", value))?;
+                }
+                "fps-limit" => {
+                    let limit: f32 = parse_value(name, &value()?)?;
+                    if limit <= 0.0 {
+                        return Err(String::from("Value of --fps-limit has to be positive"));
+                    }
+                    result.present.fps_limit = Some(limit);
+                }
+                "frames" => result.frames = Some(parse_value(name, &value()?)?),
+                "screenshot" => result.screenshot = Some(PathBuf::from(value()?)),
+                "format" => {
+                    let value = value()?;
+                    result.format = Some(parse_format(&value).ok_or_else(|| format!("Unknown format '{}'", value))?);
+                }
+                "stats" => result.stats = Some(PathBuf::from(value()?)),
+                _ => match options.iter().find(|option| option.name == name) {
+                    Some(option) => result.options.set(option.name, &value()?),
+                    None => return Err(format!("Unknown option --{}", name)),
+                },
+            }
+        }
+
+        Ok(result)
+    }
+
+    // Text printed for --help
+    pub fn usage(program: &str, options: &[CliOption]) -> String {
+        let mut lines = vec![format!("Usage: {} [OPTIONS]", program), String::new()];
+
+        let framework_options = FRAMEWORK_OPTIONS.iter().copied();
+        let example_options = options
+            .iter()
+            .map(|option| (option.name, option.value_name, option.help));
+
+        for (name, value_name, help) in framework_options.chain(example_options) {
+            let flag = format!("--{} {}", name, value_name);
+            lines.push(format!("    {:<28} {}", flag.trim_end(), help));
+        }
+
+        lines.join("\n")
+    }
+}
+
+// Formats of the offscreen frame, which can be captured into an RGBA image
+fn parse_format(name: &str) -> Option<wgpu::TextureFormat> {
+    match name.to_lowercase().as_str() {
+        "rgba8unorm" => Some(wgpu::TextureFormat::Rgba8Unorm),
+        "rgba8unorm-srgb" => Some(wgpu::TextureFormat::Rgba8UnormSrgb),
+        "bgra8unorm" => Some(wgpu::TextureFormat::Bgra8Unorm),
+        "bgra8unorm-srgb" => Some(wgpu::TextureFormat::Bgra8UnormSrgb),
+        _ => None,
+    }
+}
+
+fn parse_value<T: FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value '{}' of --{}", value, name))
+}
@@ -0,0 +1,136 @@
+use std::time::{Duration, Instant};
+
+// Order in which the present modes are switched with the hotkey
+const PRESENT_MODES: [wgpu::PresentMode; 4] = [
+    wgpu::PresentMode::Fifo,
+    wgpu::PresentMode::Mailbox,
+    wgpu::PresentMode::Immediate,
+    wgpu::PresentMode::AutoNoVsync,
+];
+
+// How the frames are presented to the window
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PresentSettings {
+    pub present_mode: wgpu::PresentMode,
+    pub fps_limit: Option<f32>, // Software frame rate cap, None means unlimited
+}
+
+impl Default for PresentSettings {
+    fn default() -> Self {
+        Self {
+            present_mode: wgpu::PresentMode::Fifo,
+            fps_limit: None,
+        }
+    }
+}
+
+impl PresentSettings {
+    // Reads the settings from PRESENT_MODE and FPS_LIMIT environment variables
+    // Command line arguments --present-mode and --fps-limit are applied on top of them
+    pub fn from_env() -> Self {
+        let mut settings = Self::default();
+
+        if let Ok(name) = std::env::var("PRESENT_MODE") {
+            match parse_present_mode(&name) {
+                Some(mode) => settings.present_mode = mode,
+                None => eprintln!("Unknown present mode '{}', using {:?}", name, settings.present_mode),
+            }
+        }
+
+        if let Ok(limit) = std::env::var("FPS_LIMIT") {
+            match limit.parse::<f32>() {
+                Ok(limit) if limit > 0.0 => settings.fps_limit = Some(limit),
+                _ => eprintln!("Invalid frame rate limit '{}', frame rate won't be limited", limit),
+            }
+        }
+
+        settings
+    }
+}
+
+impl std::fmt::Display for PresentSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self.present_mode)?;
+        if let Some(limit) = self.fps_limit {
+            write!(f, ", limited to {} fps", limit)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_present_mode(name: &str) -> Option<wgpu::PresentMode> {
+    match name.to_lowercase().replace(['-', '_'], "").as_str() {
+        "fifo" | "vsync" => Some(wgpu::PresentMode::Fifo),
+        "mailbox" => Some(wgpu::PresentMode::Mailbox),
+        "immediate" => Some(wgpu::PresentMode::Immediate),
+        "autovsync" => Some(wgpu::PresentMode::AutoVsync),
+        "autonovsync" | "novsync" => Some(wgpu::PresentMode::AutoNoVsync),
+        _ => None,
+    }
+}
+
+// Returns the requested mode if the surface supports it, Fifo otherwise
+// Auto modes are always supported, wgpu resolves them itself
+pub fn supported_present_mode(
+    requested: wgpu::PresentMode,
+    supported: &[wgpu::PresentMode],
+) -> wgpu::PresentMode {
+    match requested {
+        wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync => requested,
+        _ if supported.contains(&requested) => requested,
+        _ => wgpu::PresentMode::Fifo,
+    }
+}
+
+// Next present mode supported by the surface (used by the hotkey)
+pub fn next_present_mode(
+    current: wgpu::PresentMode,
+    supported: &[wgpu::PresentMode],
+) -> wgpu::PresentMode {
+    let position = PRESENT_MODES.iter().position(|&mode| mode == current).unwrap_or(0);
+
+    PRESENT_MODES
+        .iter()
+        .cycle()
+        .skip(position + 1)
+        .take(PRESENT_MODES.len())
+        .copied()
+        .find(|&mode| supported_present_mode(mode, supported) == mode)
+        .unwrap_or(wgpu::PresentMode::Fifo)
+}
+
+// Software frame rate cap, sleeps until the next frame is due
+pub struct FrameLimiter {
+    next_frame: Instant,
+}
+
+impl FrameLimiter {
+    pub fn new() -> Self {
+        Self {
+            next_frame: Instant::now(),
+        }
+    }
+
+    pub fn wait(&mut self, fps_limit: Option<f32>) {
+        let limit = match fps_limit {
+            Some(limit) => limit,
+            None => return,
+        };
+        let frame_time = Duration::from_secs_f32(1.0 / limit);
+
+        let now = Instant::now();
+        if self.next_frame > now {
+            std::thread::sleep(self.next_frame - now);
+            self.next_frame += frame_time;
+        } else {
+            // We are late, don't try to catch up
+            self.next_frame = now + frame_time;
+        }
+    }
+}
+
+impl Default for FrameLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,272 @@
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use super::Summary;
+
+// Maximum number of timestamps written in one frame (two per pass)
+const MAX_QUERIES: u32 = 64;
+
+// How the started pass is timed
+enum Timing {
+    Gpu(u32), // Index of the query written before the pass
+    Cpu(Instant),
+    Skipped, // The timestamps of a previous frame are still being read back
+}
+
+// Pass started by Profiler::begin_pass, has to be passed to Profiler::end_pass
+pub struct PassScope {
+    label: &'static str,
+    timing: Timing,
+}
+
+// Timestamps of a submitted frame waiting for the buffer to be mapped
+struct Readback {
+    passes: Vec<(&'static str, u32)>, // Label and index of the first query of the pass
+    receiver: mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>,
+}
+
+// Resources for measuring the passes with timestamp queries
+struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buf: wgpu::Buffer,
+    readback_buf: wgpu::Buffer,
+    period: f32, // Nanoseconds per timestamp tick
+    next_query: u32,
+    passes: Vec<(&'static str, u32)>, // Passes of the current frame
+    readback: Option<Readback>,
+}
+
+impl GpuTimer {
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let size = MAX_QUERIES as wgpu::BufferAddress * 8;
+
+        Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("Profiler timestamps"),
+                ty: wgpu::QueryType::Timestamp,
+                count: MAX_QUERIES,
+            }),
+            resolve_buf: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Profiler resolve buffer"),
+                size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback_buf: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Profiler readback buffer"),
+                size,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            period: queue.get_timestamp_period(),
+            next_query: 0,
+            passes: Vec::new(),
+            readback: None,
+        }
+    }
+}
+
+// Measured durations of one pass
+struct PassTimes {
+    label: &'static str,
+    samples: Vec<f64>, // Milliseconds
+}
+
+// Measures the CPU time spent by submitting the work and the time of the individual passes.
+// The passes are timed on the GPU with timestamp queries if Features::TIMESTAMP_QUERY is available,
+// otherwise the CPU time of their encoding is measured.
+// The examples submit their command buffers through it instead of calling queue.submit directly.
+pub struct Profiler {
+    submit_time: Duration, // Accumulated since the last take_submit_time
+    gpu: Option<GpuTimer>,
+    passes: Vec<PassTimes>, // In order of the first appearance
+}
+
+impl Profiler {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let gpu = device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+            .then(|| GpuTimer::new(device, queue));
+
+        Self {
+            submit_time: Duration::ZERO,
+            gpu,
+            passes: Vec::new(),
+        }
+    }
+
+    // Whether the passes are timed on the GPU
+    pub fn gpu_timing(&self) -> bool {
+        self.gpu.is_some()
+    }
+
+    // Submits the command buffers and measures how long the submission took
+    pub fn submit<I: IntoIterator<Item = wgpu::CommandBuffer>>(
+        &mut self,
+        queue: &wgpu::Queue,
+        command_buffers: I,
+    ) {
+        let start = Instant::now();
+        queue.submit(command_buffers);
+        self.submit_time += start.elapsed();
+    }
+
+    // Returns the submission time of the frame and resets it
+    pub fn take_submit_time(&mut self) -> Duration {
+        std::mem::take(&mut self.submit_time)
+    }
+
+    // Call before the pass is recorded into the encoder
+    pub fn begin_pass(&mut self, encoder: &mut wgpu::CommandEncoder, label: &'static str) -> PassScope {
+        let timing = match &mut self.gpu {
+            Some(gpu) if gpu.readback.is_none() && gpu.next_query + 2 <= MAX_QUERIES => {
+                let query = gpu.next_query;
+                gpu.next_query += 2;
+                encoder.write_timestamp(&gpu.query_set, query);
+                Timing::Gpu(query)
+            }
+            Some(_) => Timing::Skipped,
+            None => Timing::Cpu(Instant::now()),
+        };
+
+        PassScope { label, timing }
+    }
+
+    // Call after the pass is recorded into the encoder
+    pub fn end_pass(&mut self, encoder: &mut wgpu::CommandEncoder, scope: PassScope) {
+        match scope.timing {
+            Timing::Gpu(query) => {
+                if let Some(gpu) = &mut self.gpu {
+                    encoder.write_timestamp(&gpu.query_set, query + 1);
+                    gpu.passes.push((scope.label, query));
+                }
+            }
+            Timing::Cpu(start) => self.record(scope.label, start.elapsed().as_secs_f64() * 1000.0),
+            Timing::Skipped => {}
+        }
+    }
+
+    // Reads back the timestamps of the previous frames and resolves the ones of the current frame
+    // Called by the framework once the frame is submitted
+    pub fn end_frame(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.collect(device, wgpu::Maintain::Poll);
+
+        let gpu = match &mut self.gpu {
+            Some(gpu) if gpu.readback.is_none() && !gpu.passes.is_empty() => gpu,
+            _ => return,
+        };
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Profiler resolve"),
+        });
+        encoder.resolve_query_set(&gpu.query_set, 0..gpu.next_query, &gpu.resolve_buf, 0);
+        encoder.copy_buffer_to_buffer(
+            &gpu.resolve_buf,
+            0,
+            &gpu.readback_buf,
+            0,
+            gpu.next_query as wgpu::BufferAddress * 8,
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let (sender, receiver) = mpsc::channel();
+        gpu.readback_buf.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+
+        gpu.readback = Some(Readback {
+            passes: std::mem::take(&mut gpu.passes),
+            receiver,
+        });
+        gpu.next_query = 0;
+    }
+
+    // Waits for the timestamps that are still being read back
+    pub fn flush(&mut self, device: &wgpu::Device) {
+        self.collect(device, wgpu::Maintain::Wait);
+    }
+
+    fn collect(&mut self, device: &wgpu::Device, maintain: wgpu::Maintain) {
+        let gpu = match &mut self.gpu {
+            Some(gpu) if gpu.readback.is_some() => gpu,
+            _ => return,
+        };
+
+        device.poll(maintain);
+
+        let result = match &gpu.readback {
+            Some(readback) => match readback.receiver.try_recv() {
+                Ok(result) => result,
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => Err(wgpu::BufferAsyncError),
+            },
+            None => return,
+        };
+        let readback = gpu.readback.take().unwrap();
+
+        if result.is_err() {
+            eprintln!("Unable to read back the GPU timestamps");
+            return;
+        }
+
+        let mut durations = Vec::with_capacity(readback.passes.len());
+        {
+            let data = gpu.readback_buf.slice(..).get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+            for (label, query) in readback.passes {
+                let ticks = timestamps[query as usize + 1].saturating_sub(timestamps[query as usize]);
+                durations.push((label, ticks as f64 * gpu.period as f64 / 1_000_000.0));
+            }
+        }
+        gpu.readback_buf.unmap();
+
+        for (label, duration) in durations {
+            self.record(label, duration);
+        }
+    }
+
+    fn record(&mut self, label: &'static str, duration: f64) {
+        match self.passes.iter_mut().find(|pass| pass.label == label) {
+            Some(pass) => pass.samples.push(duration),
+            None => self.passes.push(PassTimes { label, samples: vec![duration] }),
+        }
+    }
+
+    fn source(&self) -> &'static str {
+        if self.gpu_timing() { "GPU" } else { "CPU" }
+    }
+
+    // Mean time of every pass over its last `count` samples, e.g. "GPU: Contour 0.12 ms, Tint 0.05 ms"
+    pub fn summary(&self, count: usize) -> Option<String> {
+        let passes: Vec<String> = self
+            .passes
+            .iter()
+            .filter_map(|pass| {
+                let start = pass.samples.len().saturating_sub(count);
+                Summary::from_values(&pass.samples[start..])
+                    .map(|summary| format!("{} {:.3} ms", pass.label, summary.mean))
+            })
+            .collect();
+
+        match passes.is_empty() {
+            true => None,
+            false => Some(format!("{}: {}", self.source(), passes.join(", "))),
+        }
+    }
+
+    // Statistics of every pass over the whole run
+    pub fn report(&self) -> Option<String> {
+        if self.passes.is_empty() {
+            return None;
+        }
+
+        let mut lines = vec![format!("Pass timings ({}):", self.source())];
+        for pass in &self.passes {
+            if let Some(summary) = Summary::from_values(&pass.samples) {
+                lines.push(format!("  {:<28} {}", pass.label, summary));
+            }
+        }
+
+        Some(lines.join("\n"))
+    }
+}
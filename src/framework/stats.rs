@@ -0,0 +1,184 @@
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+// Number of the latest frames kept for the rolling histogram
+const ROLLING_WINDOW: usize = 1000;
+// Width of a histogram bucket in milliseconds
+const BUCKET_MS: f64 = 1.0;
+// Last bucket collects all slower frames
+const BUCKET_COUNT: usize = 50;
+
+// Timings of a single frame in milliseconds
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameSample {
+    pub frame_ms: f64, // Time since the previous frame
+    pub encode_ms: f64, // CPU time spent in Example::render apart from the submission
+    pub submit_ms: f64, // CPU time spent in queue.submit
+}
+
+impl FrameSample {
+    pub fn new(frame: Duration, encode: Duration, submit: Duration) -> Self {
+        Self {
+            frame_ms: frame.as_secs_f64() * 1000.0,
+            encode_ms: encode.as_secs_f64() * 1000.0,
+            submit_ms: submit.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+// Selects one of the timings of the sample
+pub type Metric = fn(&FrameSample) -> f64;
+
+// Statistics of one metric
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Summary {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+impl Summary {
+    // Returns None if there are no values
+    pub fn from_values(values: &[f64]) -> Option<Summary> {
+        if values.is_empty() {
+            return None;
+        }
+
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+
+        // Nearest-rank percentile
+        let percentile = |p: f64| {
+            let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+
+        Some(Summary {
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            p50: percentile(50.0),
+            p95: percentile(95.0),
+            p99: percentile(99.0),
+        })
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "min {:.2} / mean {:.2} / p50 {:.2} / p95 {:.2} / p99 {:.2} / max {:.2} ms",
+            self.min, self.mean, self.p50, self.p95, self.p99, self.max
+        )
+    }
+}
+
+// Collects frame timings of the whole run
+#[derive(Debug, Default)]
+pub struct FrameStats {
+    samples: Vec<FrameSample>,
+    rolling: VecDeque<FrameSample>, // Latest frames for the histogram
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, sample: FrameSample) {
+        self.samples.push(sample);
+        if self.rolling.len() == ROLLING_WINDOW {
+            self.rolling.pop_front();
+        }
+        self.rolling.push_back(sample);
+    }
+
+    pub fn samples(&self) -> &[FrameSample] {
+        &self.samples
+    }
+
+    // Summary of the last `count` frames for the selected metric
+    pub fn summary(&self, count: usize, metric: Metric) -> Option<Summary> {
+        let start = self.samples.len().saturating_sub(count);
+        let values: Vec<f64> = self.samples[start..].iter().map(metric).collect();
+        Summary::from_values(&values)
+    }
+
+    // Frame time histogram of the latest frames, one line per non-empty bucket
+    pub fn histogram(&self) -> String {
+        let mut buckets = [0usize; BUCKET_COUNT];
+        for sample in &self.rolling {
+            let bucket = (sample.frame_ms / BUCKET_MS) as usize;
+            buckets[bucket.min(BUCKET_COUNT - 1)] += 1;
+        }
+
+        let max_count = buckets.iter().copied().max().unwrap_or(0).max(1);
+        let mut lines = vec![format!("Frame time histogram (last {} frames):", self.rolling.len())];
+
+        for (i, &count) in buckets.iter().enumerate().filter(|(_, &count)| count > 0) {
+            let from = i as f64 * BUCKET_MS;
+            let label = if i == BUCKET_COUNT - 1 {
+                format!("{:>6.1}+     ms", from)
+            } else {
+                format!("{:>6.1}-{:<5.1}ms", from, from + BUCKET_MS)
+            };
+            let bar = "#".repeat((count * 50).div_ceil(max_count));
+            lines.push(format!("{} {:>6} {}", label, count, bar));
+        }
+
+        lines.join("\n")
+    }
+
+    // Writes all samples as CSV or JSON, depending on the file extension
+    pub fn export(&self, path: &Path) -> std::io::Result<()> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+
+        let is_json = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+
+        if is_json {
+            writeln!(file, "[")?;
+            for (i, s) in self.samples.iter().enumerate() {
+                let separator = if i + 1 < self.samples.len() { "," } else { "" };
+                writeln!(
+                    file,
+                    "  {{\"frame\": {}, \"frame_ms\": {}, \"encode_ms\": {}, \"submit_ms\": {}}}{}",
+                    i, s.frame_ms, s.encode_ms, s.submit_ms, separator
+                )?;
+            }
+            writeln!(file, "]")?;
+        } else {
+            writeln!(file, "frame,frame_ms,encode_ms,submit_ms")?;
+            for (i, s) in self.samples.iter().enumerate() {
+                writeln!(file, "{},{},{},{}", i, s.frame_ms, s.encode_ms, s.submit_ms)?;
+            }
+        }
+
+        file.flush()
+    }
+
+    // Summary of the whole run printed at exit
+    pub fn report(&self) -> String {
+        let metrics: [(&str, Metric); 3] = [
+            ("frame", |s| s.frame_ms),
+            ("encode", |s| s.encode_ms),
+            ("submit", |s| s.submit_ms),
+        ];
+
+        let mut lines = vec![format!("Frame statistics ({} frames):", self.samples.len())];
+        for (name, metric) in metrics {
+            if let Some(summary) = self.summary(self.samples.len(), metric) {
+                lines.push(format!("  {:<7} {}", name, summary));
+            }
+        }
+
+        lines.join("\n")
+    }
+}
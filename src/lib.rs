@@ -0,0 +1,5 @@
+pub mod framework;
+pub mod shapes;
+pub mod post_processing;
+pub mod culling;
+pub mod scene;
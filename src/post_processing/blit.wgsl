@@ -0,0 +1,40 @@
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) uv_coords: vec2<f32>
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv_coords: vec2<f32>
+}
+
+@group(0)
+@binding(0)
+var in_texture: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var texture_sampler: sampler;
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = vec4<f32>(in.position, 0.0, 1.0);
+    out.uv_coords = in.uv_coords;
+    return out;
+}
+
+@fragment
+fn fs_main(vert: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(in_texture, texture_sampler, vert.uv_coords);
+}
+
+// The intermediate textures already hold sRGB encoded values,
+// convert them to linear so the sRGB target doesn't encode them twice
+@fragment
+fn fs_srgb(vert: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(in_texture, texture_sampler, vert.uv_coords);
+    let low = color.rgb / 12.92;
+    let high = pow((color.rgb + 0.055) / 1.055, vec3<f32>(2.4));
+    return vec4<f32>(select(high, low, color.rgb <= vec3<f32>(0.04045)), color.a);
+}
@@ -2,7 +2,8 @@
 use wgpu::util::DeviceExt;
 use std::borrow::Cow;
 use super::EffectType;
-use crate::{UVVertex, get_uv_from_position};
+use crate::framework::Profiler;
+use super::helper::{UVVertex, get_uv_from_position};
 
 // Preforms edge detection using sobel operator to isolate the contours
 pub struct Contour {
@@ -100,7 +101,7 @@ impl super::Effect for Contour {
                 module: &contour_shader,
                 entry_point: "fs_main",
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    format: super::INTERMEDIATE_FORMAT,
                     blend: None,
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
@@ -148,10 +149,12 @@ impl super::Effect for Contour {
         &self, 
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        output_view: &wgpu::TextureView
+        output_view: &wgpu::TextureView,
+        profiler: &mut Profiler,
     ) {
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
+        let scope = profiler.begin_pass(&mut encoder, "Contour");
         {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
@@ -170,7 +173,8 @@ impl super::Effect for Contour {
             rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
             rpass.draw(0..6, 0..1);
         }
+        profiler.end_pass(&mut encoder, scope);
 
-        queue.submit(Some(encoder.finish()));
+        profiler.submit(queue, Some(encoder.finish()));
     }
 }
\ No newline at end of file
@@ -1,7 +1,18 @@
 
+use bytemuck::{Pod, Zeroable};
+use super::INTERMEDIATE_FORMAT;
+
+// Vertex with position and texture coordinates
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+pub struct UVVertex {
+    pub pos: [f32; 2],
+    pub uv_coords: [f32; 2]
+}
+
 fn abs(x: f32) -> f32 {
     if x < 0.0 {
-        return x*(-1.0);
+        return -x;
     }
     x
 }
@@ -37,12 +48,10 @@ pub fn create_output_texture_view(
         mip_level_count: 1,
         sample_count: 1,
         dimension: wgpu::TextureDimension::D2,
-        format: wgpu::TextureFormat::Rgba8Unorm,
+        format: INTERMEDIATE_FORMAT,
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
     });
-    let output_view = output_texture.create_view(&wgpu::TextureViewDescriptor::default());
-
-    output_view
+    output_texture.create_view(&wgpu::TextureViewDescriptor::default())
 }
 
 // Creates specified number of texture views
@@ -1,13 +1,22 @@
+pub mod helper;
 mod tint;
 mod contour;
+mod blit;
 
-use tint::Tint;
-use contour::Contour;
+pub use tint::Tint;
+pub use contour::Contour;
+pub use blit::Blit;
 
 use std::vec::Vec;
 use std::iter::IntoIterator;
 use std::ops::Index;
-use crate::helper::{create_output_texture_view, create_output_texture_views};
+use std::str::FromStr;
+use helper::{create_output_texture_view, create_output_texture_views};
+use crate::framework::Profiler;
+
+// Format of the textures the effects render into
+// If the frame buffer has a different format, the result is blitted into it
+pub const INTERMEDIATE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
 
 // Defines the type of the post-processing effect and encapsulates some additional parameters
 #[derive(Clone, Copy, Debug)]
@@ -16,6 +25,29 @@ pub enum EffectType {
     Contour
 }
 
+// Parses `contour` or `tint:r:g:b:a`
+impl FromStr for EffectType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().split(':');
+        match parts.next() {
+            Some("contour") => Ok(EffectType::Contour),
+            Some("tint") => {
+                let color = parts
+                    .map(|c| c.parse::<f32>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| format!("Invalid tint color in '{}'", s))?;
+                match color[..] {
+                    [r, g, b, a] => Ok(EffectType::Tint(r, g, b, a)),
+                    _ => Err(format!("Tint expects four color components (tint:r:g:b:a), got '{}'", s)),
+                }
+            }
+            _ => Err(format!("Unknown effect '{}'", s)),
+        }
+    }
+}
+
 // Represents the post-processing effect
 pub trait Effect {
     // Initializes the resources for the effect
@@ -30,6 +62,7 @@ pub trait Effect {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         output_view: &wgpu::TextureView,
+        profiler: &mut Profiler,
     );
 }
 
@@ -55,6 +88,25 @@ impl PostProcessingChain {
     }
 }
 
+impl Default for PostProcessingChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Parses comma separated list of effects, e.g. `contour,tint:0.2:0.4:0.8:1.0`
+impl FromStr for PostProcessingChain {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chain = PostProcessingChain::new();
+        for effect in s.split(',').filter(|effect| !effect.trim().is_empty()) {
+            chain.add_effect(effect.parse()?);
+        }
+        Ok(chain)
+    }
+}
+
 // Make the struct iterable
 impl IntoIterator for PostProcessingChain {
     type Item = EffectType;
@@ -78,6 +130,7 @@ impl Index<usize> for PostProcessingChain {
 pub struct PostProcessing {
     effects: Vec<Box<dyn Effect>>, // Effect instances (in order of post-processing chain)
     texture_views: Vec<wgpu::TextureView>, // Swap chain
+    blit: Option<Blit>, // Copies the result into the frame buffer of a different format
 }
 
 impl PostProcessing {
@@ -91,16 +144,21 @@ impl PostProcessing {
         let mut effects: Vec<Box<dyn Effect>> = Vec::new();
         let mut texture_views = Vec::new();
 
-        if effects_count == 0 {
-            return PostProcessing { effects, texture_views };
+        let needs_blit = config.format != INTERMEDIATE_FORMAT;
+
+        if effects_count == 0 && !needs_blit {
+            return PostProcessing { effects, texture_views, blit: None };
         }
 
-        // zero effects -> no need for output textures
-        // one effect -> no need for output textures
-        // two effects -> 1 texture
-        // more effects -> swap chain (2 textures)
-        match effects_count {
-            2 => texture_views.push(create_output_texture_view(device, config)),
+        // Without blit the last effect renders straight into the frame buffer
+        let outputs_count = if needs_blit { effects_count } else { effects_count - 1 };
+
+        // zero outputs -> no need for output textures
+        // one output -> 1 texture
+        // more outputs -> swap chain (2 textures)
+        match outputs_count {
+            0 => {},
+            1 => texture_views.push(create_output_texture_view(device, config)),
             _ => texture_views.extend(create_output_texture_views(device, config, 2))
         }
 
@@ -136,9 +194,21 @@ impl PostProcessing {
             in_texture_id += 1;
         }
 
+        // The blit reads the output of the last effect
+        let blit = if needs_blit {
+            let blit_input = match effects_count {
+                0 => input_frame,
+                _ => &texture_views[(effects_count - 1) % 2],
+            };
+            Some(Blit::init(device, blit_input, config.format))
+        } else {
+            None
+        };
+
         PostProcessing {
             effects,
             texture_views,
+            blit,
         }
 	}
 
@@ -147,6 +217,7 @@ impl PostProcessing {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         output_frame: &wgpu::TextureView, // frame buffer
+        profiler: &mut Profiler,
     ) {
         let effects_count = self.effects.len();
         let mut out_texture_id = 0;
@@ -157,15 +228,19 @@ impl PostProcessing {
                 out_texture_id = 0;
             }
 
-            if i == effects_count-1 {
-                (*self.effects[i]).resolve(device, queue, output_frame);
+            if i == effects_count-1 && self.blit.is_none() {
+                (*self.effects[i]).resolve(device, queue, output_frame, profiler);
             }
             else {
-                (*self.effects[i]).resolve(device, queue, &self.texture_views[out_texture_id]);
+                (*self.effects[i]).resolve(device, queue, &self.texture_views[out_texture_id], profiler);
             }
 
             out_texture_id += 1;
         }
+
+        if let Some(blit) = &self.blit {
+            blit.resolve(device, queue, output_frame, profiler);
+        }
     }
 }
 
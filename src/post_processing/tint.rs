@@ -2,7 +2,8 @@
 use wgpu::util::DeviceExt;
 use std::borrow::Cow;
 use super::EffectType;
-use crate::{UVVertex, get_uv_from_position};
+use crate::framework::Profiler;
+use super::helper::{UVVertex, get_uv_from_position};
 
 // Tint effect changes the tone of the entire scene based on the input color
 pub struct Tint {
@@ -127,7 +128,7 @@ impl super::Effect for Tint {
                 module: &tint_shader,
                 entry_point: "fs_main",
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    format: super::INTERMEDIATE_FORMAT,
                     blend: None,
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
@@ -179,10 +180,12 @@ impl super::Effect for Tint {
         &self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        output_view: &wgpu::TextureView
+        output_view: &wgpu::TextureView,
+        profiler: &mut Profiler,
     ) {
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
+        let scope = profiler.begin_pass(&mut encoder, "Tint");
         {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
@@ -201,7 +204,8 @@ impl super::Effect for Tint {
             rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
             rpass.draw(0..6, 0..1);
         }
+        profiler.end_pass(&mut encoder, scope);
 
-        queue.submit(Some(encoder.finish()));
+        profiler.submit(queue, Some(encoder.finish()));
     }
 }
\ No newline at end of file
@@ -0,0 +1,276 @@
+mod buffers;
+mod description;
+
+pub use buffers::SceneBuffers;
+pub use description::{MeshDescription, ObjectDescription, SceneDescription, SceneError, ShapeDescription, TextureDescription};
+
+use bytemuck::{Pod, Zeroable};
+use std::ops::Range;
+use crate::culling::{CullInstance, DrawCommand};
+use crate::shapes::{Bounds, GpuBounds, Mesh, MeshId, Object, TextureId};
+
+// Handle of an object in the scene, stays valid until the object is removed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ObjectId {
+    slot: u32,
+    generation: u32, // Distinguishes the objects which used the same slot
+}
+
+impl ObjectId {
+    // Index of the object in the storage buffers
+    pub fn slot(&self) -> u32 {
+        self.slot
+    }
+}
+
+// Object information in the objects storage buffer, indexed by the slot
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, Debug, Default, PartialEq)]
+pub struct GpuObject {
+    pub transform_id: u32,
+    pub texture_id: u32,
+}
+
+// Dirty ranges closer than this number of slots are merged, one longer write is cheaper than several short ones
+pub const MERGE_GAP: u32 = 4;
+
+// What changed since the last call of Scene::take_changes
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Changes {
+    pub slots: Vec<Range<u32>>,    // Sorted ranges of slots whose data has to be uploaded
+    pub counts: bool,              // Numbers of objects in the batches, so the draw commands
+    pub batches: bool,             // New batches were added, so the number of draw commands
+}
+
+struct Slot {
+    object: Option<Object>,
+    batch: u32,
+    generation: u32,
+}
+
+// Objects of the scene in stable slots, which can be inserted, removed and moved every frame.
+// The slots of the removed objects are reused by the next inserted objects.
+// The objects are drawn in batches (pairs of mesh and texture) like in shapes::get_batches_from_objects,
+// but the slots of a batch aren't contiguous, the culling compacts them into the region of the batch.
+// A batch has a draw command for every level of detail of its mesh.
+pub struct Scene {
+    slots: Vec<Slot>,
+    free: Vec<u32>, // The last freed slot is reused first
+    meshes: Vec<(MeshId, Bounds, u32)>, // Model space bounds and the number of levels of detail of the meshes
+    batches: Vec<(MeshId, TextureId)>,
+    batch_counts: Vec<u32>,
+    changes: Changes,
+}
+
+impl Scene {
+    // Objects can only use the given meshes
+    pub fn new(meshes: &[&Mesh]) -> Self {
+        Scene {
+            slots: Vec::new(),
+            free: Vec::new(),
+            meshes: meshes.iter().map(|mesh| (mesh.m_type, mesh.bounds(), mesh.lod_count() as u32)).collect(),
+            batches: Vec::new(),
+            batch_counts: Vec::new(),
+            changes: Changes::default(),
+        }
+    }
+
+    pub fn insert(&mut self, object: Object) -> ObjectId {
+        assert!(
+            self.meshes.iter().any(|(m_type, _, _)| *m_type == object.m_type),
+            "Mesh {:?} isn't in the scene", object.m_type
+        );
+
+        let batch = match self.batches.iter().position(|&b| b == (object.m_type, object.t_type)) {
+            Some(batch) => batch,
+            None => {
+                self.batches.push((object.m_type, object.t_type));
+                self.batch_counts.push(0);
+                self.changes.batches = true;
+                self.batches.len() - 1
+            }
+        };
+        self.batch_counts[batch] += 1;
+        self.changes.counts = true;
+
+        let slot = match self.free.pop() {
+            Some(slot) => slot,
+            None => {
+                self.slots.push(Slot { object: None, batch: 0, generation: 0 });
+                self.slots.len() as u32 - 1
+            }
+        };
+
+        let entry = &mut self.slots[slot as usize];
+        entry.object = Some(object);
+        entry.batch = batch as u32;
+        let generation = entry.generation;
+        self.mark_dirty(slot);
+
+        ObjectId { slot, generation }
+    }
+
+    pub fn remove(&mut self, id: ObjectId) -> Option<Object> {
+        let entry = self.slots.get_mut(id.slot as usize).filter(|entry| entry.generation == id.generation)?;
+        let object = entry.object.take()?;
+        entry.generation += 1;
+
+        self.batch_counts[entry.batch as usize] -= 1;
+        self.changes.counts = true;
+        self.free.push(id.slot);
+        self.mark_dirty(id.slot);
+
+        Some(object)
+    }
+
+    pub fn get(&self, id: ObjectId) -> Option<&Object> {
+        self.slots
+            .get(id.slot as usize)
+            .filter(|entry| entry.generation == id.generation)
+            .and_then(|entry| entry.object.as_ref())
+    }
+
+    // Moves the object, returns false if it was removed
+    pub fn set_transform(&mut self, id: ObjectId, transform_m: glam::Mat4) -> bool {
+        let object = self.slots
+            .get_mut(id.slot as usize)
+            .filter(|entry| entry.generation == id.generation)
+            .and_then(|entry| entry.object.as_mut());
+
+        match object {
+            Some(object) => {
+                object.transform_m = transform_m;
+                self.mark_dirty(id.slot);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Adds the slot to the dirty ranges, merging the ranges which get closer than MERGE_GAP
+    fn mark_dirty(&mut self, slot: u32) {
+        let ranges = &mut self.changes.slots;
+        let i = ranges.partition_point(|range| range.end + MERGE_GAP < slot);
+        if i == ranges.len() || ranges[i].start > slot + 1 + MERGE_GAP {
+            ranges.insert(i, slot..slot + 1);
+            return;
+        }
+
+        ranges[i] = ranges[i].start.min(slot)..ranges[i].end.max(slot + 1);
+        while i + 1 < ranges.len() && ranges[i + 1].start <= ranges[i].end + MERGE_GAP {
+            let next = ranges.remove(i + 1);
+            ranges[i].end = ranges[i].end.max(next.end);
+        }
+    }
+
+    // Returns the changes since the last call and clears them
+    pub fn take_changes(&mut self) -> Changes {
+        std::mem::take(&mut self.changes)
+    }
+
+    // Number of objects
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Number of slots including the free ones, the storage buffers need at least this capacity
+    pub fn slot_count(&self) -> u32 {
+        self.slots.len() as u32
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ObjectId, &Object)> {
+        self.slots.iter().enumerate().filter_map(|(slot, entry)| {
+            entry.object.as_ref().map(|object| (ObjectId { slot: slot as u32, generation: entry.generation }, object))
+        })
+    }
+
+    pub fn batches(&self) -> &[(MeshId, TextureId)] {
+        &self.batches
+    }
+
+    // Draw commands of the levels of detail of every batch, index_range returns the number of indices
+    // and the first index of the level of detail (0 is the full mesh) of the mesh.
+    // Every command has a region for all instances of its batch, the regions (base_instance)
+    // follow each other in the order of the commands.
+    pub fn commands(&self, index_range: impl Fn(MeshId, u32) -> (u32, u32)) -> Vec<DrawCommand> {
+        let mut commands = Vec::new();
+        let mut base_instance = 0;
+        for (&(m_type, _), &count) in self.batches.iter().zip(&self.batch_counts) {
+            for lod in 0..self.lod_count(m_type) {
+                let (index_count, base_index) = index_range(m_type, lod);
+                commands.push(DrawCommand {
+                    index_count,
+                    instance_count: count,
+                    base_index,
+                    vertex_offset: 0,
+                    base_instance,
+                });
+                base_instance += count;
+            }
+        }
+        commands
+    }
+
+    fn mesh(&self, m_type: MeshId) -> &(MeshId, Bounds, u32) {
+        self.meshes.iter().find(|(m, _, _)| *m == m_type).unwrap()
+    }
+
+    // Number of levels of detail of the mesh, so of the draw commands of its batches
+    pub fn lod_count(&self, m_type: MeshId) -> u32 {
+        self.mesh(m_type).2
+    }
+
+    fn bounds(&self, object: &Object) -> Bounds {
+        self.mesh(object.m_type).1.transform(&object.transform_m)
+    }
+
+    // Data of the slots for the storage buffers, free slots are zeroed
+
+    pub fn matrices(&self, slots: Range<u32>) -> Vec<[f32; 16]> {
+        self.slots[slots.start as usize..slots.end as usize]
+            .iter()
+            .map(|entry| entry.object.map_or([0.0; 16], |object| object.transform_m.to_cols_array()))
+            .collect()
+    }
+
+    pub fn objects(&self, slots: Range<u32>) -> Vec<GpuObject> {
+        (slots.start..slots.end)
+            .map(|slot| match self.slots[slot as usize].object {
+                Some(object) => GpuObject { transform_id: slot, texture_id: object.t_type.index() },
+                None => GpuObject::default(),
+            })
+            .collect()
+    }
+
+    pub fn gpu_bounds(&self, slots: Range<u32>) -> Vec<GpuBounds> {
+        self.slots[slots.start as usize..slots.end as usize]
+            .iter()
+            .map(|entry| entry.object.map_or(GpuBounds::default(), |object| self.bounds(&object).to_gpu()))
+            .collect()
+    }
+
+    // Free slots are skipped by the culling, the others refer to the first command of their batch
+    pub fn cull_instances(&self, slots: Range<u32>) -> Vec<CullInstance> {
+        let mut first_commands = Vec::with_capacity(self.batches.len());
+        let mut command = 0;
+        for &(m_type, _) in &self.batches {
+            first_commands.push(command);
+            command += self.lod_count(m_type);
+        }
+
+        self.slots[slots.start as usize..slots.end as usize]
+            .iter()
+            .map(|entry| match entry.object {
+                Some(object) => {
+                    let lods = self.lod_count(object.m_type);
+                    CullInstance::with_lods(self.bounds(&object).sphere, first_commands[entry.batch as usize], lods)
+                }
+                None => CullInstance::EMPTY,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,90 @@
+use wgpu::util::DeviceExt;
+use std::ops::Range;
+use super::Scene;
+
+// Storage buffers with the data of the scene's slots: matrices (mat4x4<f32>), objects (GpuObject)
+// and world space bounds (GpuBounds), all indexed by the slot
+pub struct SceneBuffers {
+    matrices_buf: wgpu::Buffer,
+    objects_buf: wgpu::Buffer,
+    bounds_buf: wgpu::Buffer,
+    capacity: u32, // Number of slots
+}
+
+impl SceneBuffers {
+    pub fn new(device: &wgpu::Device, scene: &Scene) -> Self {
+        Self::create(device, scene, scene.slot_count().max(1))
+    }
+
+    fn create(device: &wgpu::Device, scene: &Scene, capacity: u32) -> Self {
+        // The slots after the scene's slots are empty
+        let slots = 0..scene.slot_count();
+        let padding = (capacity - scene.slot_count()) as usize;
+
+        let mut matrices = scene.matrices(slots.clone());
+        matrices.resize(matrices.len() + padding, Default::default());
+        let mut objects = scene.objects(slots.clone());
+        objects.resize(objects.len() + padding, Default::default());
+        let mut bounds = scene.gpu_bounds(slots);
+        bounds.resize(bounds.len() + padding, Default::default());
+
+        // COPY_SRC for reading the buffers back
+        let usage = wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC;
+
+        SceneBuffers {
+            matrices_buf: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Matrices buffer"),
+                contents: bytemuck::cast_slice(&matrices),
+                usage,
+            }),
+            objects_buf: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Objects buffer"),
+                contents: bytemuck::cast_slice(&objects),
+                usage,
+            }),
+            bounds_buf: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Bounds buffer"),
+                contents: bytemuck::cast_slice(&bounds),
+                usage,
+            }),
+            capacity,
+        }
+    }
+
+    // Uploads the data of the ranges of slots, one write per range. If the scene outgrew the buffers, they are
+    // recreated with (at least) twice the capacity and true is returned, the bind groups using them have to be
+    // recreated then.
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, scene: &Scene, slots: &[Range<u32>]) -> bool {
+        if scene.slot_count() > self.capacity {
+            *self = Self::create(device, scene, scene.slot_count().max(2 * self.capacity));
+            return true;
+        }
+
+        for range in slots.iter().filter(|range| !range.is_empty()) {
+            let write = |buffer: &wgpu::Buffer, element_size: usize, data: &[u8]| {
+                let offset = (range.start as usize * element_size) as wgpu::BufferAddress;
+                queue.write_buffer(buffer, offset, data);
+            };
+            write(&self.matrices_buf, 64, bytemuck::cast_slice(&scene.matrices(range.clone())));
+            write(&self.objects_buf, 8, bytemuck::cast_slice(&scene.objects(range.clone())));
+            write(&self.bounds_buf, 48, bytemuck::cast_slice(&scene.gpu_bounds(range.clone())));
+        }
+        false
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    pub fn matrices_buffer(&self) -> &wgpu::Buffer {
+        &self.matrices_buf
+    }
+
+    pub fn objects_buffer(&self) -> &wgpu::Buffer {
+        &self.objects_buf
+    }
+
+    pub fn bounds_buffer(&self) -> &wgpu::Buffer {
+        &self.bounds_buf
+    }
+}
@@ -0,0 +1,421 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use crate::shapes::{MeshId, Object, Shape, TextureId};
+
+// Scene file in the RON format, e.g.
+//
+// (
+//     meshes: [
+//         (name: "teapot", path: "assets/teapot.obj"),
+//     ],
+//     shapes: [
+//         (name: "plank", shape: Box(size: (4.0, 0.5, 0.2))),
+//     ],
+//     textures: [
+//         (name: "blue", path: "assets/blue_texture.png"),
+//     ],
+//     objects: [
+//         (mesh: "cube", texture: "blue", position: (-3.0, 0.0, 0.0), rotation: (0.0, 0.0, 45.0)),
+//         (mesh: "teapot", texture: "blue", scale: (0.5, 0.5, 0.5)),
+//     ],
+// )
+//
+// Objects use the procedural meshes ("cube", "cylinder" and "sphere"), the meshes loaded from OBJ files
+// or the generated shapes. The meshes and then the shapes get the handles from MeshId::FIRST_CUSTOM,
+// the textures from 0, in the order they are listed.
+// Paths are relative to the scene file, rotations are XYZ Euler angles in degrees.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SceneDescription {
+    #[serde(default)]
+    pub meshes: Vec<MeshDescription>,
+    #[serde(default)]
+    pub shapes: Vec<ShapeDescription>,
+    #[serde(default)]
+    pub textures: Vec<TextureDescription>,
+    #[serde(default)]
+    pub objects: Vec<ObjectDescription>,
+}
+
+// Mesh loaded from an OBJ file, all its groups are merged
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MeshDescription {
+    pub name: String,
+    pub path: String,
+}
+
+// Mesh generated by shapes::Shape
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShapeDescription {
+    pub name: String,
+    pub shape: Shape,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TextureDescription {
+    pub name: String,
+    pub path: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ObjectDescription {
+    pub mesh: String,
+    pub texture: String,
+    #[serde(default)]
+    pub position: [f32; 3],
+    #[serde(default)]
+    pub rotation: [f32; 3],
+    #[serde(default = "unit_scale")]
+    pub scale: [f32; 3],
+}
+
+fn unit_scale() -> [f32; 3] {
+    [1.0; 3]
+}
+
+const PROCEDURAL_MESHES: [(&str, MeshId); 3] = [
+    ("cube", MeshId::CUBE),
+    ("cylinder", MeshId::CYLINDER),
+    ("sphere", MeshId::SPHERE),
+];
+
+// Error of the scene file, line is 0 if it isn't known (e.g. the file can't be read)
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            0 => write!(f, "{}", self.message),
+            line => write!(f, "line {}: {}", line, self.message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl SceneDescription {
+    // Description of objects created by the code, so they can be saved. The meshes (name and path)
+    // followed by the shapes (name and shape) have the handles starting with MeshId::FIRST_CUSTOM,
+    // the textures (name and path) the handles starting with 0, the same as the loaded scene gives them.
+    // Errors have no line, e.g. an object whose mesh has no name.
+    pub fn from_objects(
+        objects: &[Object],
+        meshes: &[(String, String)],
+        shapes: &[(String, Shape)],
+        textures: &[(String, String)],
+    ) -> Result<Self, SceneError> {
+        let mut scene = SceneDescription {
+            meshes: meshes
+                .iter()
+                .map(|(name, path)| MeshDescription { name: name.clone(), path: path.clone() })
+                .collect(),
+            shapes: shapes
+                .iter()
+                .map(|(name, shape)| ShapeDescription { name: name.clone(), shape: shape.clone() })
+                .collect(),
+            textures: textures
+                .iter()
+                .map(|(name, path)| TextureDescription { name: name.clone(), path: path.clone() })
+                .collect(),
+            objects: Vec::with_capacity(objects.len()),
+        };
+
+        for (i, object) in objects.iter().enumerate() {
+            let error = |message: String| SceneError { line: 0, message };
+            let (scale, rotation, position) = object.transform_m.to_scale_rotation_translation();
+            let (x, y, z) = rotation.to_euler(glam::EulerRot::XYZ);
+            let mesh = scene
+                .mesh_name(object.m_type)
+                .ok_or_else(|| error(format!("Mesh {:?} of object {} has no name", object.m_type, i)))?;
+            let texture = scene
+                .textures
+                .get(object.t_type.index() as usize)
+                .ok_or_else(|| error(format!("Texture {:?} of object {} has no name", object.t_type, i)))?;
+            scene.objects.push(ObjectDescription {
+                mesh: mesh.to_string(),
+                texture: texture.name.clone(),
+                position: position.to_array(),
+                rotation: [x.to_degrees(), y.to_degrees(), z.to_degrees()],
+                scale: scale.to_array(),
+            });
+        }
+
+        scene.validate("")?;
+        Ok(scene)
+    }
+
+    fn mesh_id(&self, name: &str) -> Option<MeshId> {
+        PROCEDURAL_MESHES
+            .iter()
+            .find(|(procedural, _)| *procedural == name)
+            .map(|(_, id)| *id)
+            .or_else(|| {
+                let i = self.custom_mesh_names().position(|mesh| mesh == name)?;
+                Some(MeshId(MeshId::FIRST_CUSTOM.0 + i as u32))
+            })
+    }
+
+    // Names of the meshes and the shapes in the order of their handles
+    fn custom_mesh_names(&self) -> impl Iterator<Item = &str> {
+        let meshes = self.meshes.iter().map(|mesh| mesh.name.as_str());
+        meshes.chain(self.shapes.iter().map(|shape| shape.name.as_str()))
+    }
+
+    fn texture_id(&self, name: &str) -> Option<TextureId> {
+        let i = self.textures.iter().position(|texture| texture.name == name)?;
+        Some(TextureId(i as u32))
+    }
+
+    fn mesh_name(&self, id: MeshId) -> Option<&str> {
+        match PROCEDURAL_MESHES.iter().find(|(_, procedural)| *procedural == id) {
+            Some((name, _)) => Some(name),
+            None => {
+                let i = id.0.checked_sub(MeshId::FIRST_CUSTOM.0)?;
+                self.custom_mesh_names().nth(i as usize)
+            }
+        }
+    }
+
+    // Parses and validates the scene
+    pub fn parse(source: &str) -> Result<Self, SceneError> {
+        let scene: SceneDescription = ron::from_str(source).map_err(|err| SceneError {
+            line: err.position.line,
+            message: err.code.to_string(),
+        })?;
+        scene.validate(source)?;
+        Ok(scene)
+    }
+
+    // Loads the scene file, the mesh and texture paths are resolved relative to it and have to exist
+    pub fn load(path: &Path) -> Result<Self, SceneError> {
+        let source = std::fs::read_to_string(path).map_err(|err| SceneError {
+            line: 0,
+            message: format!("Can't read {}: {}", path.display(), err),
+        })?;
+        let mut scene = Self::parse(&source)?;
+
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let resolve = |field: &str, paths: Vec<&mut String>| {
+            let lines = element_lines(&source, field);
+            for (i, path) in paths.into_iter().enumerate() {
+                let resolved = dir.join(&*path);
+                if !resolved.is_file() {
+                    return Err(SceneError {
+                        line: lines.get(i).copied().unwrap_or(0),
+                        message: format!("File {} doesn't exist", resolved.display()),
+                    });
+                }
+                *path = resolved.to_string_lossy().into_owned();
+            }
+            Ok(())
+        };
+        resolve("meshes", scene.meshes.iter_mut().map(|mesh| &mut mesh.path).collect())?;
+        resolve("textures", scene.textures.iter_mut().map(|texture| &mut texture.path).collect())?;
+
+        Ok(scene)
+    }
+
+    // Pretty printed RON
+    pub fn to_ron(&self) -> String {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new()).unwrap()
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_ron())
+    }
+
+    // Validated first, the fields can be changed after parsing
+    pub fn objects(&self) -> Result<Vec<Object>, SceneError> {
+        self.validate("")?;
+        let objects = self
+            .objects
+            .iter()
+            .map(|object| {
+                let [x, y, z] = object.rotation.map(f32::to_radians);
+                Object {
+                    transform_m: glam::Mat4::from_scale_rotation_translation(
+                        glam::Vec3::from(object.scale),
+                        glam::Quat::from_euler(glam::EulerRot::XYZ, x, y, z),
+                        glam::Vec3::from(object.position),
+                    ),
+                    // Both names exist, validate checked them
+                    m_type: self.mesh_id(&object.mesh).unwrap(),
+                    t_type: self.texture_id(&object.texture).unwrap(),
+                }
+            })
+            .collect();
+        Ok(objects)
+    }
+
+    // Handles and paths of the meshes to load
+    pub fn mesh_paths(&self) -> Vec<(MeshId, PathBuf)> {
+        self.meshes
+            .iter()
+            .enumerate()
+            .map(|(i, mesh)| (MeshId(MeshId::FIRST_CUSTOM.0 + i as u32), PathBuf::from(&mesh.path)))
+            .collect()
+    }
+
+    // Handles and shapes of the meshes to generate, following the meshes
+    pub fn shape_meshes(&self) -> Vec<(MeshId, Shape)> {
+        let first = MeshId::FIRST_CUSTOM.0 + self.meshes.len() as u32;
+        self.shapes
+            .iter()
+            .enumerate()
+            .map(|(i, shape)| (MeshId(first + i as u32), shape.shape.clone()))
+            .collect()
+    }
+
+    // Texture paths, indexed by TextureId::index
+    pub fn texture_paths(&self) -> Vec<PathBuf> {
+        self.textures.iter().map(|texture| PathBuf::from(&texture.path)).collect()
+    }
+
+    fn validate(&self, source: &str) -> Result<(), SceneError> {
+        let mesh_lines = element_lines(source, "meshes");
+        for (i, mesh) in self.meshes.iter().enumerate() {
+            let error = |message: String| SceneError { line: mesh_lines.get(i).copied().unwrap_or(0), message };
+
+            if PROCEDURAL_MESHES.iter().any(|(name, _)| *name == mesh.name) {
+                return Err(error(format!("Mesh name {:?} is used by a procedural mesh", mesh.name)));
+            }
+            if self.meshes[..i].iter().any(|m| m.name == mesh.name) {
+                return Err(error(format!("Mesh {:?} is listed twice", mesh.name)));
+            }
+        }
+
+        let shape_lines = element_lines(source, "shapes");
+        for (i, shape) in self.shapes.iter().enumerate() {
+            let error = |message: String| SceneError { line: shape_lines.get(i).copied().unwrap_or(0), message };
+
+            if PROCEDURAL_MESHES.iter().any(|(name, _)| *name == shape.name) {
+                return Err(error(format!("Shape name {:?} is used by a procedural mesh", shape.name)));
+            }
+            if self.meshes.iter().any(|m| m.name == shape.name) || self.shapes[..i].iter().any(|s| s.name == shape.name) {
+                return Err(error(format!("Mesh {:?} is listed twice", shape.name)));
+            }
+            shape.shape.validate().map_err(|err| error(format!("Shape {:?}: {}", shape.name, err)))?;
+        }
+
+        let texture_lines = element_lines(source, "textures");
+        for (i, texture) in self.textures.iter().enumerate() {
+            if self.textures[..i].iter().any(|t| t.name == texture.name) {
+                return Err(SceneError {
+                    line: texture_lines.get(i).copied().unwrap_or(0),
+                    message: format!("Texture {:?} is listed twice", texture.name),
+                });
+            }
+        }
+
+        let object_lines = element_lines(source, "objects");
+        for (i, object) in self.objects.iter().enumerate() {
+            let error = |message: String| SceneError { line: object_lines.get(i).copied().unwrap_or(0), message };
+
+            if self.mesh_id(&object.mesh).is_none() {
+                return Err(error(format!("Mesh {:?} of object {} is neither procedural nor in the meshes or shapes", object.mesh, i)));
+            }
+            if self.texture_id(&object.texture).is_none() {
+                return Err(error(format!("Texture {:?} of object {} isn't in the textures", object.texture, i)));
+            }
+            if object.position.iter().chain(&object.rotation).any(|v| !v.is_finite()) {
+                return Err(error(format!("Position and rotation of object {} have to be finite", i)));
+            }
+            if object.scale.iter().any(|v| !v.is_finite() || *v <= 0.0) {
+                return Err(error(format!("Scale of object {} has to be positive", i)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Lines where the elements of the list (or map) in the top-level field start, serde doesn't keep them
+fn element_lines(source: &str, field: &str) -> Vec<usize> {
+    let mut lines = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+    let mut depth = 0;
+    let mut list_depth = None; // Depth inside the brackets of the field's list
+    let mut after_field = false; // The field's name was read, its list follows
+    let mut expect_element = false;
+
+    while let Some(c) = chars.next() {
+        // Whitespace and comments
+        match c {
+            '\n' => {
+                line += 1;
+                continue;
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+                continue;
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            _ => {}
+        }
+
+        if expect_element && list_depth == Some(depth) && !matches!(c, ')' | ']' | '}') {
+            lines.push(line);
+            expect_element = false;
+        }
+
+        match c {
+            '"' => {
+                let mut escaped = false;
+                for c in chars.by_ref() {
+                    match c {
+                        '\n' => line += 1,
+                        '"' if !escaped => break,
+                        _ => {}
+                    }
+                    escaped = c == '\\' && !escaped;
+                }
+            }
+            '(' | '[' | '{' => {
+                depth += 1;
+                if after_field {
+                    list_depth = Some(depth);
+                    expect_element = true;
+                    after_field = false;
+                }
+            }
+            ')' | ']' | '}' => {
+                if list_depth == Some(depth) {
+                    break;
+                }
+                depth -= 1;
+            }
+            ',' if list_depth == Some(depth) => expect_element = true,
+            c if depth == 1 && list_depth.is_none() && (c.is_alphabetic() || c == '_') => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+                    word.push(c);
+                    chars.next();
+                }
+                after_field = word == field;
+            }
+            _ => {}
+        }
+    }
+
+    lines
+}
@@ -0,0 +1,391 @@
+mod bounds;
+mod obj;
+mod gltf_scene;
+mod tangents;
+mod generators;
+mod simplify;
+
+pub use bounds::{Aabb, BoundingSphere, Bounds, GpuBounds};
+pub use obj::{parse_mtl, ObjGroup, ObjMaterial, ObjModel, ObjScene};
+pub use gltf_scene::GltfScene;
+pub use generators::Shape;
+pub use simplify::Simplified;
+
+use bytemuck::{Pod, Zeroable};
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, Debug, PartialEq)]
+pub struct Vertex {
+    _pos: [f32; 4],
+    _tex_coord: [f32; 2],
+    _normal: [f32; 3],
+    _tangent: [f32; 4], // w is the sign of the bitangent, see Mesh::generate_tangents
+}
+
+impl Vertex {
+    // Without a normal and a tangent, they are filled by Mesh::generate_normals and Mesh::generate_tangents
+    pub fn new(pos: [f32; 3], tex_coord: [f32; 2]) -> Self {
+        Self::with_normal(pos, tex_coord, [0.0; 3])
+    }
+
+    pub fn with_normal(pos: [f32; 3], tex_coord: [f32; 2], normal: [f32; 3]) -> Self {
+        Vertex {
+            _pos: [pos[0], pos[1], pos[2], 1.0],
+            _tex_coord: tex_coord,
+            _normal: normal,
+            _tangent: [0.0; 4],
+        }
+    }
+
+    pub fn position(&self) -> [f32; 3] {
+        [self._pos[0], self._pos[1], self._pos[2]]
+    }
+
+    pub fn tex_coord(&self) -> [f32; 2] {
+        self._tex_coord
+    }
+
+    pub fn normal(&self) -> [f32; 3] {
+        self._normal
+    }
+
+    pub fn tangent(&self) -> [f32; 4] {
+        self._tangent
+    }
+}
+
+// Limit of the levels of detail of a mesh, including the full one
+pub const MAX_LODS: usize = 4;
+
+// Handle of a mesh. The procedural meshes have fixed handles, other meshes (e.g. loaded from files)
+// can use any of the following ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MeshId(pub u32);
+
+impl MeshId {
+    pub const CUBE: MeshId = MeshId(0);
+    pub const CYLINDER: MeshId = MeshId(1);
+    pub const SPHERE: MeshId = MeshId(2);
+    // First handle which isn't used by the procedural meshes
+    pub const FIRST_CUSTOM: MeshId = MeshId(3);
+
+    // Shape of a procedural mesh
+    pub fn shape(&self) -> Option<Shape> {
+        match *self {
+            MeshId::CUBE => Some(Shape::Box { size: [2.0; 3] }),
+            MeshId::CYLINDER => Some(Shape::Cylinder { radius: 1.0, height: 2.0, segments: 20 }),
+            MeshId::SPHERE => Some(Shape::Sphere { radius: 1.0, segments: 20, rings: 20 }),
+            _ => None,
+        }
+    }
+}
+
+// Handle of a texture, its index in the texture array of the renderer
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TextureId(pub u32);
+
+impl TextureId {
+    // Textures of the examples
+    pub const BLUE: TextureId = TextureId(0);
+    pub const RED: TextureId = TextureId(1);
+    pub const YELLOW: TextureId = TextureId(2);
+
+    // Index of the texture in the texture array
+    pub fn index(&self) -> u32 {
+        self.0
+    }
+}
+
+pub struct Mesh {
+    pub m_type: MeshId,
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub lods: Vec<Vec<u32>>, // Indices of the coarser levels of detail into the same vertices, from the finest
+}
+
+impl Mesh {
+    // Vertices of the procedural mesh with its levels of detail
+    pub fn generate_vertices(&mut self) {
+        let shape = self.m_type.shape().unwrap_or_else(|| panic!("Mesh {:?} isn't procedural", self.m_type));
+        *self = shape.to_mesh(self.m_type);
+    }
+
+    // Number of levels of detail including the full mesh
+    pub fn lod_count(&self) -> usize {
+        1 + self.lods.len()
+    }
+
+    // Indices of the level of detail, 0 is the full mesh
+    pub fn lod_indices(&self, lod: usize) -> &[u32] {
+        match lod {
+            0 => &self.indices,
+            _ => &self.lods[lod - 1],
+        }
+    }
+
+    // Appends a coarser level of detail with its own vertices, e.g. the same shape in a lower resolution.
+    // The vertices need their normals and tangents.
+    pub fn push_lod(&mut self, vertices: Vec<Vertex>, indices: Vec<u32>) {
+        let offset = self.vertices.len() as u32;
+        self.vertices.extend(vertices);
+        self.lods.push(indices.into_iter().map(|index| index + offset).collect());
+    }
+
+    // Indices of all levels of detail one after another
+    fn all_indices(&self) -> Vec<u32> {
+        (0..self.lod_count()).flat_map(|lod| self.lod_indices(lod)).copied().collect()
+    }
+
+    // Normals of the vertices which don't have one, averaged from their triangles
+    pub fn generate_normals(&mut self) {
+        let indices = self.all_indices();
+        tangents::generate_normals(&mut self.vertices, &indices);
+    }
+
+    // MikkTSpace-style tangents of all vertices from their normals and UVs
+    pub fn generate_tangents(&mut self) {
+        let indices = self.all_indices();
+        tangents::generate_tangents(&mut self.vertices, &indices);
+    }
+
+    // Fewer triangles over the same vertices by quadric error edge collapse, at most target_ratio of the triangles
+    // of the full mesh when the borders and seams allow it. Fails for a ratio outside of (0, 1] or invalid indices.
+    pub fn simplify(&self, target_ratio: f32) -> Result<Simplified, String> {
+        simplify::simplify(&self.vertices, &self.indices, target_ratio)
+    }
+
+    // Levels of detail of a mesh loaded from a file by simplification, every one with about half the triangles
+    // of the previous one. Stops when the simplification can't remove a quarter of the triangles anymore.
+    pub fn generate_lods(&mut self) -> Result<(), String> {
+        self.lods.clear();
+        let mut ratio = 1.0;
+        while self.lod_count() < MAX_LODS {
+            ratio /= 2.0;
+            let lod = self.simplify(ratio)?;
+            if lod.indices.len() * 4 > self.lod_indices(self.lod_count() - 1).len() * 3 {
+                break;
+            }
+            self.lods.push(lod.indices);
+        }
+        Ok(())
+    }
+
+    fn positions(&self) -> impl Iterator<Item = glam::Vec3> + Clone + '_ {
+        self.vertices.iter().map(|v| glam::Vec3::from_slice(&v._pos[..3]))
+    }
+
+    // Box of all vertices, empty for a mesh without vertices
+    pub fn aabb(&self) -> Aabb {
+        Aabb::from_points(self.positions())
+    }
+
+    // Sphere around the center of the mesh's bounding box containing all its vertices
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        let aabb = self.aabb();
+        if aabb.is_empty() {
+            return BoundingSphere::EMPTY;
+        }
+
+        let center = aabb.center();
+        let radius = self.positions().map(|p| p.distance(center)).fold(0.0, f32::max);
+
+        BoundingSphere { center, radius }
+    }
+
+    // Model space bounds
+    pub fn bounds(&self) -> Bounds {
+        Bounds {
+            aabb: self.aabb(),
+            sphere: self.bounding_sphere(),
+        }
+    }
+}
+
+// Batch is a pair of mesh and texture.
+// The number of objects we want to draw from this batch
+// is defined with number of transform matrices in transform_m vector.
+#[repr(C)]
+#[derive(Debug)]
+pub struct Batch {
+    pub transform_m: Vec<glam::Mat4>,
+    pub m_type: MeshId,
+    pub t_type: TextureId,
+}
+
+// Represents an object from the scene
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Object {
+    pub transform_m: glam::Mat4,
+    pub m_type: MeshId,
+    pub t_type: TextureId,
+}
+
+impl Object {
+    // World space bounds, the mesh has to be the one of m_type
+    pub fn bounds(&self, mesh: &Mesh) -> Bounds {
+        debug_assert_eq!(mesh.m_type, self.m_type);
+        mesh.bounds().transform(&self.transform_m)
+    }
+}
+
+// Indices of merged meshes, 16-bit while they can address every vertex
+#[derive(Clone, Debug, PartialEq)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, i: usize) -> Option<u32> {
+        match self {
+            Indices::U16(indices) => indices.get(i).map(|&index| index as u32),
+            Indices::U32(indices) => indices.get(i).copied(),
+        }
+    }
+
+    // Format for set_index_buffer
+    pub fn format(&self) -> wgpu::IndexFormat {
+        match self {
+            Indices::U16(_) => wgpu::IndexFormat::Uint16,
+            Indices::U32(_) => wgpu::IndexFormat::Uint32,
+        }
+    }
+
+    // Contents of the index buffer
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Indices::U16(indices) => bytemuck::cast_slice(indices),
+            Indices::U32(indices) => bytemuck::cast_slice(indices),
+        }
+    }
+}
+
+// Vertices and indices of all meshes, the indices are 16-bit when there are at most 65536 vertices.
+// The levels of detail of every mesh follow each other, see lod_ranges.
+// Fails when the vertices can't be addressed by 32-bit indices.
+pub fn merge_index_vertex_data(meshes: &Vec<&Mesh>) -> Result<(Vec<Vertex>, Indices), String> {
+    let total: usize = meshes.iter().map(|m| m.vertices.len()).sum();
+    if total > u32::MAX as usize + 1 {
+        return Err(format!("{} vertices can't be indexed, at most {} are supported", total, u32::MAX as usize + 1));
+    }
+
+    let mut vertices: Vec<Vertex> = Vec::<Vertex>::with_capacity(total);
+    let mut indices: Vec<u32> = Vec::<u32>::new();
+
+    // Vertex count of meshes before
+    // We need to add this number to indices of the next mesh
+    let mut vertex_count = 0;
+
+    for m in meshes {
+        let mesh_indices = m.all_indices();
+        if let Some(&index) = mesh_indices.iter().find(|&&index| index as usize >= m.vertices.len()) {
+            return Err(format!("Mesh {:?} has index {} but only {} vertices", m.m_type, index, m.vertices.len()));
+        }
+        vertices.extend(&m.vertices);
+
+        for index in mesh_indices {
+            indices.push(index + vertex_count as u32);
+        }
+        vertex_count += m.vertices.len();
+    }
+
+    let indices = match total <= u16::MAX as usize + 1 {
+        true => Indices::U16(indices.into_iter().map(|index| index as u16).collect()),
+        false => Indices::U32(indices),
+    };
+
+    Ok((vertices, indices))
+}
+
+// Number of indices and the first index of every level of detail of the meshes
+// in the index buffer of merge_index_vertex_data
+pub fn lod_ranges(meshes: &[&Mesh]) -> Vec<Vec<(u32, u32)>> {
+    let mut base_index = 0;
+    meshes
+        .iter()
+        .map(|mesh| {
+            (0..mesh.lod_count())
+                .map(|lod| {
+                    let count = mesh.lod_indices(lod).len() as u32;
+                    base_index += count;
+                    (count, base_index - count)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+pub fn get_batches_from_objects(objects: &Vec<Object>) -> Vec<Batch> {
+    let mut batches = Vec::<Batch>::new();
+
+    for o in objects {
+        let batch = batches.iter_mut().find(|b| b.m_type == o.m_type && b.t_type == o.t_type);
+
+        match batch {
+            Some(x) => x.transform_m.push(o.transform_m),
+            None => batches.push(
+                Batch {
+                    transform_m: vec![o.transform_m],
+                    m_type: o.m_type,
+                    t_type: o.t_type,
+                } 
+            )
+        }
+    }
+
+    batches    
+}
+
+pub fn merge_matrices(batches: &Vec<Batch>) -> Vec<f32> {
+    let mut matrices = Vec::<f32>::new();
+
+    for b in batches {
+        for m in &b.transform_m {
+            matrices.extend(m.to_cols_array());
+        }
+    }
+
+    matrices
+}
+
+// World space bounds of the objects in the same order as merge_matrices
+pub fn merge_bounds(batches: &Vec<Batch>, meshes: &Vec<&Mesh>) -> Vec<GpuBounds> {
+    let mut bounds = Vec::<GpuBounds>::new();
+
+    for b in batches {
+        let mesh = meshes.iter().find(|m| m.m_type == b.m_type).unwrap().bounds();
+        for m in &b.transform_m {
+            bounds.push(mesh.transform(m).to_gpu());
+        }
+    }
+
+    bounds
+}
+
+pub fn merge_objects(batches: &Vec<Batch>) -> Vec<u32> {
+    let mut objects = Vec::<u32>::new();
+    let mut transform_id = 0;
+
+    for b in batches {
+        for _m in 0..b.transform_m.len() {
+            objects.push(transform_id);
+            transform_id += 1;
+
+            objects.push(b.t_type.index()); // Add objects texture_id
+        }
+    }
+
+    objects
+}
\ No newline at end of file
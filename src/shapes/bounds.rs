@@ -0,0 +1,193 @@
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3};
+
+// Axis-aligned bounding box, empty when min > max
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub const EMPTY: Aabb = Aabb {
+        min: Vec3::splat(f32::MAX),
+        max: Vec3::splat(f32::MIN),
+    };
+
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Aabb {
+        points.into_iter().fold(Aabb::EMPTY, |aabb, p| aabb.extend(p))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.cmpgt(self.max).any()
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) / 2.0
+    }
+
+    // Half of the size along every axis
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) / 2.0
+    }
+
+    pub fn extend(&self, point: Vec3) -> Aabb {
+        Aabb {
+            min: self.min.min(point),
+            max: self.max.max(point),
+        }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+
+    // Box containing this box after the transformation
+    pub fn transform(&self, transform_m: &Mat4) -> Aabb {
+        if self.is_empty() {
+            return *self;
+        }
+
+        let center = transform_m.transform_point3(self.center());
+        let (x, y, z) = (transform_m.x_axis.truncate(), transform_m.y_axis.truncate(), transform_m.z_axis.truncate());
+        let extents = self.half_extents();
+        let half = x.abs() * extents.x + y.abs() * extents.y + z.abs() * extents.z;
+
+        Aabb {
+            min: center - half,
+            max: center + half,
+        }
+    }
+
+    // Sphere around the box, not the smallest sphere of the contained points
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        match self.is_empty() {
+            true => BoundingSphere::EMPTY,
+            false => BoundingSphere {
+                center: self.center(),
+                radius: self.half_extents().length(),
+            },
+        }
+    }
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Aabb::EMPTY
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub const EMPTY: BoundingSphere = BoundingSphere {
+        center: Vec3::ZERO,
+        radius: 0.0,
+    };
+
+    // Sphere containing this sphere after the transformation
+    pub fn transform(&self, transform_m: &Mat4) -> BoundingSphere {
+        let scale = transform_m.x_axis.truncate().length()
+            .max(transform_m.y_axis.truncate().length())
+            .max(transform_m.z_axis.truncate().length());
+
+        BoundingSphere {
+            center: transform_m.transform_point3(self.center),
+            radius: self.radius * scale,
+        }
+    }
+
+    // Smallest sphere containing both spheres
+    pub fn union(&self, other: &BoundingSphere) -> BoundingSphere {
+        let offset = other.center - self.center;
+        let distance = offset.length();
+
+        // One sphere is inside the other
+        if distance + other.radius <= self.radius {
+            return *self;
+        }
+        if distance + self.radius <= other.radius {
+            return *other;
+        }
+
+        let radius = (distance + self.radius + other.radius) / 2.0;
+        BoundingSphere {
+            center: self.center + offset * ((radius - self.radius) / distance),
+            radius,
+        }
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        point.distance_squared(self.center) <= self.radius * self.radius
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        Aabb {
+            min: self.center - Vec3::splat(self.radius),
+            max: self.center + Vec3::splat(self.radius),
+        }
+    }
+}
+
+impl Default for BoundingSphere {
+    fn default() -> Self {
+        BoundingSphere::EMPTY
+    }
+}
+
+// Box and sphere of a mesh (in model space) or of an object (in world space)
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct Bounds {
+    pub aabb: Aabb,
+    pub sphere: BoundingSphere,
+}
+
+impl Bounds {
+    pub fn transform(&self, transform_m: &Mat4) -> Bounds {
+        Bounds {
+            aabb: self.aabb.transform(transform_m),
+            sphere: self.sphere.transform(transform_m),
+        }
+    }
+
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        Bounds {
+            aabb: self.aabb.union(&other.aabb),
+            sphere: self.sphere.union(&other.sphere),
+        }
+    }
+
+    // Layout used in the storage buffers, see GpuBounds
+    pub fn to_gpu(&self) -> GpuBounds {
+        GpuBounds {
+            aabb_min: self.aabb.min.extend(0.0).to_array(),
+            aabb_max: self.aabb.max.extend(0.0).to_array(),
+            sphere: self.sphere.center.extend(self.sphere.radius).to_array(),
+        }
+    }
+}
+
+// Bounds as stored in the storage buffers:
+// struct Bounds { aabb_min: vec4<f32>, aabb_max: vec4<f32>, sphere: vec4<f32> }
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, Debug, Default, PartialEq)]
+pub struct GpuBounds {
+    pub aabb_min: [f32; 4],
+    pub aabb_max: [f32; 4],
+    pub sphere: [f32; 4], // Center and radius
+}
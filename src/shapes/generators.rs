@@ -0,0 +1,414 @@
+use super::{tangents, Mesh, MeshId, Vertex, MAX_LODS};
+use glam::{Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+// Parameterised procedural mesh, centered at the origin with Z up. In scene files e.g. Box(size: (2.0, 1.0, 1.0)).
+// All shapes have normals and UVs, the round ones wrap the texture around the Z axis with V growing downwards.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Shape {
+    // Box with the given size along X, Y and Z, every face has its own vertices and the whole texture
+    Box { size: [f32; 3] },
+    // Box whose faces are split into a grid, subdivisions along X, Y and Z
+    SubdividedBox { size: [f32; 3], subdivisions: [u32; 3] },
+    // Box with edges and corners rounded by the radius, in segments
+    RoundedBox { size: [f32; 3], radius: f32, segments: u32 },
+    // Grid in the XY plane facing +Z, subdivisions along X and Y
+    Plane { size: [f32; 2], subdivisions: [u32; 2] },
+    // UV sphere, segments around the Z axis and rings from pole to pole
+    Sphere { radius: f32, segments: u32, rings: u32 },
+    // Sphere from a subdivided icosahedron, every subdivision splits a triangle into 4
+    Icosphere { radius: f32, subdivisions: u32 },
+    Cylinder { radius: f32, height: f32, segments: u32 },
+    // Base at the bottom, apex at the top
+    Cone { radius: f32, height: f32, segments: u32 },
+    // Cylinder with hemispheres at its ends, height includes them, rings of every hemisphere
+    Capsule { radius: f32, height: f32, segments: u32, rings: u32 },
+    // Ring around the Z axis, radius to the center of the tube
+    Torus { radius: f32, tube_radius: f32, segments: u32, tube_segments: u32 },
+}
+
+// Limit of the icosphere subdivisions, 20 * 4^8 triangles
+const MAX_ICOSPHERE_SUBDIVISIONS: u32 = 8;
+
+impl Shape {
+    pub fn validate(&self) -> Result<(), String> {
+        let positive = |values: &[f32], name: &str| match values.iter().all(|v| v.is_finite() && *v > 0.0) {
+            true => Ok(()),
+            false => Err(format!("{} has to be positive", name)),
+        };
+        let at_least = |value: u32, min: u32, name: &str| match value >= min {
+            true => Ok(()),
+            false => Err(format!("{} have to be at least {}", name, min)),
+        };
+
+        match *self {
+            Shape::Box { size } => positive(&size, "Size"),
+            Shape::SubdividedBox { size, subdivisions } => {
+                positive(&size, "Size")?;
+                subdivisions.iter().try_for_each(|&s| at_least(s, 1, "Subdivisions"))
+            }
+            Shape::RoundedBox { size, radius, segments } => {
+                positive(&size, "Size")?;
+                positive(&[radius], "Radius")?;
+                if 2.0 * radius > size.iter().copied().fold(f32::MAX, f32::min) {
+                    return Err("Radius can be at most half of the smallest size".to_string());
+                }
+                at_least(segments, 1, "Segments")
+            }
+            Shape::Plane { size, subdivisions } => {
+                positive(&size, "Size")?;
+                subdivisions.iter().try_for_each(|&s| at_least(s, 1, "Subdivisions"))
+            }
+            Shape::Sphere { radius, segments, rings } => {
+                positive(&[radius], "Radius")?;
+                at_least(segments, 3, "Segments")?;
+                at_least(rings, 2, "Rings")
+            }
+            Shape::Icosphere { radius, subdivisions } => {
+                positive(&[radius], "Radius")?;
+                match subdivisions <= MAX_ICOSPHERE_SUBDIVISIONS {
+                    true => Ok(()),
+                    false => Err(format!("Subdivisions can be at most {}", MAX_ICOSPHERE_SUBDIVISIONS)),
+                }
+            }
+            Shape::Cylinder { radius, height, segments } | Shape::Cone { radius, height, segments } => {
+                positive(&[radius, height], "Radius and height")?;
+                at_least(segments, 3, "Segments")
+            }
+            Shape::Capsule { radius, height, segments, rings } => {
+                positive(&[radius, height], "Radius and height")?;
+                if height < 2.0 * radius {
+                    return Err("Height has to be at least the diameter".to_string());
+                }
+                at_least(segments, 3, "Segments")?;
+                at_least(rings, 1, "Rings")
+            }
+            Shape::Torus { radius, tube_radius, segments, tube_segments } => {
+                positive(&[radius, tube_radius], "Radius and tube radius")?;
+                at_least(segments, 3, "Segments")?;
+                at_least(tube_segments, 3, "Tube segments")
+            }
+        }
+    }
+
+    // Vertices with normals and UVs and the indices, the shape has to be valid
+    pub fn generate(&self) -> (Vec<Vertex>, Vec<u32>) {
+        let mut builder = Builder::default();
+        match *self {
+            Shape::Box { size } => builder.subdivided_box(Vec3::from(size), [1; 3]),
+            Shape::SubdividedBox { size, subdivisions } => builder.subdivided_box(Vec3::from(size), subdivisions),
+            Shape::RoundedBox { size, radius, segments } => builder.rounded_box(Vec3::from(size), radius, segments),
+            Shape::Plane { size, subdivisions } => {
+                let ticks = subdivisions.map(uniform_ticks);
+                builder.grid(Vec3::Z, Vec3::X, Vec3::Y, &ticks[0], &ticks[1], |t| {
+                    let p = t * Vec3::new(size[0], size[1], 0.0) / 2.0;
+                    (p, Vec3::Z)
+                });
+            }
+            Shape::Sphere { radius, segments, rings } => {
+                let profile = (0..=rings).map(|i| {
+                    let angle = i as f32 / rings as f32 * PI;
+                    let normal = Vec2::new(angle.sin(), angle.cos());
+                    (normal * radius, normal)
+                });
+                builder.revolve(profile.collect(), segments);
+            }
+            Shape::Icosphere { radius, subdivisions } => builder.icosphere(radius, subdivisions),
+            Shape::Cylinder { radius, height, segments } => {
+                let half = height / 2.0;
+                builder.revolve(vec![(Vec2::new(radius, half), Vec2::X), (Vec2::new(radius, -half), Vec2::X)], segments);
+                builder.disc(radius, half, segments);
+                builder.disc(radius, -half, segments);
+            }
+            Shape::Cone { radius, height, segments } => {
+                let half = height / 2.0;
+                let normal = Vec2::new(height, radius).normalize();
+                builder.revolve(vec![(Vec2::new(0.0, half), normal), (Vec2::new(radius, -half), normal)], segments);
+                builder.disc(radius, -half, segments);
+            }
+            Shape::Capsule { radius, height, segments, rings } => {
+                // Upper hemisphere down to the equator, lower one from its equator
+                let half = height / 2.0 - radius;
+                let profile = (0..=rings).map(|i| (i as f32 / rings as f32 * FRAC_PI_2, half));
+                let lower = (0..=rings).map(|i| (FRAC_PI_2 + i as f32 / rings as f32 * FRAC_PI_2, -half));
+                let profile = profile.chain(lower).map(|(angle, z)| {
+                    let normal = Vec2::new(angle.sin(), angle.cos());
+                    (normal * radius + Vec2::new(0.0, z), normal)
+                });
+                builder.revolve(profile.collect(), segments);
+            }
+            Shape::Torus { radius, tube_radius, segments, tube_segments } => {
+                // From the top of the tube over its outer side
+                let profile = (0..=tube_segments).map(|i| {
+                    let angle = i as f32 / tube_segments as f32 * TAU;
+                    let normal = Vec2::new(angle.sin(), angle.cos());
+                    (Vec2::new(radius, 0.0) + normal * tube_radius, normal)
+                });
+                builder.revolve(profile.collect(), segments);
+            }
+        }
+        (builder.vertices, builder.indices)
+    }
+
+    // Same shape in about half the resolution for the next level of detail,
+    // None when the resolution can't be lowered anymore
+    pub fn coarser(&self) -> Option<Shape> {
+        let half = |value: u32, min: u32| (value / 2).max(min);
+        let shape = match *self {
+            Shape::Box { .. } => return None,
+            Shape::SubdividedBox { size, subdivisions } => Shape::SubdividedBox { size, subdivisions: subdivisions.map(|s| half(s, 1)) },
+            Shape::RoundedBox { size, radius, segments } => Shape::RoundedBox { size, radius, segments: half(segments, 1) },
+            Shape::Plane { size, subdivisions } => Shape::Plane { size, subdivisions: subdivisions.map(|s| half(s, 1)) },
+            Shape::Sphere { radius, segments, rings } => Shape::Sphere { radius, segments: half(segments, 3), rings: half(rings, 2) },
+            Shape::Icosphere { radius, subdivisions } => Shape::Icosphere { radius, subdivisions: subdivisions.saturating_sub(1) },
+            Shape::Cylinder { radius, height, segments } => Shape::Cylinder { radius, height, segments: half(segments, 3) },
+            Shape::Cone { radius, height, segments } => Shape::Cone { radius, height, segments: half(segments, 3) },
+            Shape::Capsule { radius, height, segments, rings } => {
+                Shape::Capsule { radius, height, segments: half(segments, 3), rings: half(rings, 1) }
+            }
+            Shape::Torus { radius, tube_radius, segments, tube_segments } => {
+                Shape::Torus { radius, tube_radius, segments: half(segments, 3), tube_segments: half(tube_segments, 3) }
+            }
+        };
+        Some(shape).filter(|shape| shape != self)
+    }
+
+    // Mesh with tangents and up to MAX_LODS levels of detail from the coarser shapes,
+    // panics if the shape isn't valid
+    pub fn to_mesh(&self, m_type: MeshId) -> Mesh {
+        if let Err(err) = self.validate() {
+            panic!("{:?}: {}", self, err);
+        }
+
+        let (vertices, indices) = self.generate();
+        let mut mesh = Mesh { m_type, vertices, indices, lods: Vec::new() };
+        mesh.generate_tangents();
+
+        let mut shape = self.clone();
+        while mesh.lod_count() < MAX_LODS {
+            match shape.coarser() {
+                Some(coarser) => shape = coarser,
+                None => break,
+            }
+            let (mut vertices, indices) = shape.generate();
+            tangents::generate_tangents(&mut vertices, &indices);
+            mesh.push_lod(vertices, indices);
+        }
+        mesh
+    }
+}
+
+// Faces of a box as their normal, right and up direction when looking at them from the outside.
+// Up is Z for the sides, the textures are upright on them.
+const BOX_FACES: [(Vec3, Vec3, Vec3); 6] = [
+    (Vec3::X, Vec3::Y, Vec3::Z),
+    (Vec3::NEG_X, Vec3::NEG_Y, Vec3::Z),
+    (Vec3::Y, Vec3::NEG_X, Vec3::Z),
+    (Vec3::NEG_Y, Vec3::X, Vec3::Z),
+    (Vec3::Z, Vec3::X, Vec3::Y),
+    (Vec3::NEG_Z, Vec3::X, Vec3::NEG_Y),
+];
+
+// Index of the axis the direction lies on
+fn axis(direction: Vec3) -> usize {
+    direction.abs().to_array().iter().position(|&v| v == 1.0).unwrap()
+}
+
+// Parameters of the grid lines, evenly from 0 to 1
+fn uniform_ticks(subdivisions: u32) -> Vec<f32> {
+    (0..=subdivisions).map(|i| i as f32 / subdivisions as f32).collect()
+}
+
+#[derive(Default)]
+struct Builder {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+}
+
+impl Builder {
+    fn vertex(&mut self, position: Vec3, tex_coord: [f32; 2], normal: Vec3) {
+        self.vertices.push(Vertex::with_normal(position.to_array(), tex_coord, normal.to_array()));
+    }
+
+    // Quads between the rows of columns + 1 vertices starting at first, row by row from the top.
+    // Triangles collapsed to a point (at the poles) are skipped.
+    fn quads(&mut self, first: u32, columns: u32, rows: u32) {
+        let index = |i: u32, j: u32| first + j * (columns + 1) + i;
+        for j in 0..rows {
+            for i in 0..columns {
+                let (top_left, bottom_left) = (index(i, j), index(i, j + 1));
+                let (bottom_right, top_right) = (index(i + 1, j + 1), index(i + 1, j));
+                for triangle in [[top_left, bottom_left, bottom_right], [bottom_right, top_right, top_left]] {
+                    let p = triangle.map(|k| Vec3::from_array(self.vertices[k as usize].position()));
+                    if p[0] != p[1] && p[1] != p[2] && p[2] != p[0] {
+                        self.indices.extend(triangle);
+                    }
+                }
+            }
+        }
+    }
+
+    // Grid of a face seen from the outside, U to the right and V down. The ticks are the parameters (0 to 1)
+    // of the columns and rows, point maps the position on the face (-1 to 1 along its axes) to a vertex.
+    fn grid(
+        &mut self,
+        normal: Vec3,
+        right: Vec3,
+        up: Vec3,
+        columns: &[f32],
+        rows: &[f32],
+        point: impl Fn(Vec3) -> (Vec3, Vec3),
+    ) {
+        let first = self.vertices.len() as u32;
+        for &v in rows {
+            for &u in columns {
+                let (position, normal) = point(normal + right * (2.0 * u - 1.0) + up * (1.0 - 2.0 * v));
+                self.vertex(position, [u, v], normal);
+            }
+        }
+        self.quads(first, columns.len() as u32 - 1, rows.len() as u32 - 1);
+    }
+
+    fn subdivided_box(&mut self, size: Vec3, subdivisions: [u32; 3]) {
+        let ticks = subdivisions.map(uniform_ticks);
+        for (normal, right, up) in BOX_FACES {
+            self.grid(normal, right, up, &ticks[axis(right)], &ticks[axis(up)], |t| (t * size / 2.0, normal));
+        }
+    }
+
+    // Every face has extra grid lines where its edges start to bend, the points of the bent bands
+    // are pushed out onto the spheres (cylinders) around the inner box
+    fn rounded_box(&mut self, size: Vec3, radius: f32, segments: u32) {
+        let ticks = size.to_array().map(|extent| {
+            let band = radius / extent;
+            let mut ticks: Vec<f32> = (0..=segments).map(|i| band * i as f32 / segments as f32).collect();
+            if band < 0.5 {
+                ticks.extend((0..=segments).rev().map(|i| 1.0 - band * i as f32 / segments as f32));
+            } else {
+                ticks.extend((0..segments).rev().map(|i| 1.0 - band * i as f32 / segments as f32));
+            }
+            ticks
+        });
+        let inner = size / 2.0 - radius;
+
+        for (normal, right, up) in BOX_FACES {
+            self.grid(normal, right, up, &ticks[axis(right)], &ticks[axis(up)], |t| {
+                let p = t * size / 2.0;
+                let center = p.clamp(-inner, inner);
+                let direction = (p - center).normalize_or_zero();
+                let direction = match direction == Vec3::ZERO {
+                    true => normal,
+                    false => direction,
+                };
+                (center + direction * radius, direction)
+            });
+        }
+    }
+
+    // Surface of revolution around the Z axis. The profile (distance from the axis and Z with the normal
+    // in the same plane) goes from the top down, V follows its length.
+    fn revolve(&mut self, profile: Vec<(Vec2, Vec2)>, segments: u32) {
+        let mut lengths = vec![0.0];
+        for pair in profile.windows(2) {
+            lengths.push(lengths.last().unwrap() + pair[0].0.distance(pair[1].0));
+        }
+        let total = lengths.last().unwrap().max(f32::EPSILON);
+
+        let first = self.vertices.len() as u32;
+        for ((point, normal), length) in profile.iter().zip(&lengths) {
+            // Rounding errors of the profile (e.g. sin(PI)) would leave the poles open
+            let point = match point.x.abs() < 1e-6 {
+                true => Vec2::new(0.0, point.y),
+                false => *point,
+            };
+            for i in 0..=segments {
+                // Points on the axis are the apex of every column, in its middle
+                let u = match point.x == 0.0 {
+                    true => (i as f32 + 0.5).min(segments as f32) / segments as f32,
+                    false => i as f32 / segments as f32,
+                };
+                let (sin, cos) = (u * TAU).sin_cos();
+                let position = Vec3::new(point.x * cos, point.x * sin, point.y);
+                self.vertex(position, [u, length / total], Vec3::new(normal.x * cos, normal.x * sin, normal.y));
+            }
+        }
+        self.quads(first, segments, profile.len() as u32 - 1);
+    }
+
+    // Cap of a cylinder at z, facing up above 0 and down below, mapped from above
+    fn disc(&mut self, radius: f32, z: f32, segments: u32) {
+        let normal = Vec3::new(0.0, 0.0, z.signum());
+        let center = self.vertices.len() as u32;
+        self.vertex(Vec3::new(0.0, 0.0, z), [0.5, 0.5], normal);
+        for i in 0..segments {
+            let (sin, cos) = (i as f32 / segments as f32 * TAU).sin_cos();
+            self.vertex(Vec3::new(radius * cos, radius * sin, z), [0.5 + 0.5 * cos, 0.5 - 0.5 * sin], normal);
+        }
+        for i in 0..segments {
+            let (a, b) = (center + 1 + i, center + 1 + (i + 1) % segments);
+            match z < 0.0 {
+                true => self.indices.extend([a, center, b]),
+                false => self.indices.extend([a, b, center]),
+            }
+        }
+    }
+
+    fn icosphere(&mut self, radius: f32, subdivisions: u32) {
+        // Icosahedron from three golden rectangles
+        let t = (1.0 + 5f32.sqrt()) / 2.0;
+        let mut points: Vec<Vec3> = [
+            [-1.0, t, 0.0], [1.0, t, 0.0], [-1.0, -t, 0.0], [1.0, -t, 0.0],
+            [0.0, -1.0, t], [0.0, 1.0, t], [0.0, -1.0, -t], [0.0, 1.0, -t],
+            [t, 0.0, -1.0], [t, 0.0, 1.0], [-t, 0.0, -1.0], [-t, 0.0, 1.0],
+        ]
+        .iter()
+        .map(|&p| Vec3::from_array(p).normalize())
+        .collect();
+        let mut triangles: Vec<[usize; 3]> = vec![
+            [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+            [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+            [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+            [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+        ];
+
+        for _ in 0..subdivisions {
+            let mut midpoints = std::collections::HashMap::new();
+            let mut midpoint = |a: usize, b: usize| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    points.push((points[a] + points[b]).normalize());
+                    points.len() - 1
+                })
+            };
+            triangles = triangles
+                .iter()
+                .flat_map(|&[a, b, c]| {
+                    let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                    [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+                })
+                .collect();
+        }
+
+        // U around the Z axis, V from the top. Triangles crossing the seam get copies of their vertices
+        // with U above 1, so the texture has to repeat. Vertices with the same U are shared.
+        let mut shared: std::collections::HashMap<(usize, u32), u32> = std::collections::HashMap::new();
+        for triangle in triangles {
+            let mut us = triangle.map(|i| (points[i].y.atan2(points[i].x) / TAU).rem_euclid(1.0));
+            let max_u = us.iter().copied().fold(0.0, f32::max);
+            for u in &mut us {
+                if max_u - *u > 0.5 {
+                    *u += 1.0;
+                }
+            }
+
+            for (&i, u) in triangle.iter().zip(us) {
+                let index = *shared.entry((i, u.to_bits())).or_insert_with(|| {
+                    let v = points[i].z.clamp(-1.0, 1.0).acos() / PI;
+                    self.vertices.push(Vertex::with_normal((points[i] * radius).to_array(), [u, v], points[i].to_array()));
+                    self.vertices.len() as u32 - 1
+                });
+                self.indices.push(index);
+            }
+        }
+    }
+}
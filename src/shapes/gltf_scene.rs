@@ -0,0 +1,274 @@
+use super::{Mesh, MeshId, Object, TextureId, Vertex};
+use std::collections::HashMap;
+use std::path::Path;
+
+// Meshes, objects and textures imported from a glTF 2.0 file (.gltf or .glb).
+// The node hierarchy of the default scene is flattened, every triangle primitive is a mesh and every
+// material a texture (the base color texture multiplied by the base color factor, or only the factor).
+// Missing normals and tangents are generated.
+// glTF is Y up, the objects are rotated to Z up.
+pub struct GltfScene {
+    pub meshes: Vec<Mesh>, // Handles following first_mesh
+    pub objects: Vec<Object>,
+    pub textures: Vec<image::RgbaImage>, // Handles following first_texture
+}
+
+impl GltfScene {
+    // External buffers and images are relative to the file
+    pub fn load(path: &Path, first_mesh: MeshId, first_texture: TextureId) -> Result<Self, String> {
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        std::fs::read(path)
+            .map_err(|err| err.to_string())
+            .and_then(|data| Self::parse(&data, Some(dir), first_mesh, first_texture))
+            .map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    // glTF or GLB in memory, external files can't be referenced
+    pub fn from_slice(data: &[u8], first_mesh: MeshId, first_texture: TextureId) -> Result<Self, String> {
+        Self::parse(data, None, first_mesh, first_texture)
+    }
+
+    fn parse(data: &[u8], dir: Option<&Path>, first_mesh: MeshId, first_texture: TextureId) -> Result<Self, String> {
+        let gltf = gltf::Gltf::from_slice(data).map_err(|err| err.to_string())?;
+        let buffers = gltf
+            .buffers()
+            .map(|buffer| {
+                let data = match buffer.source() {
+                    gltf::buffer::Source::Bin => gltf.blob.clone().ok_or("Binary chunk is missing")?,
+                    gltf::buffer::Source::Uri(uri) => read_uri(uri, dir)?,
+                };
+                match data.len() >= buffer.length() {
+                    true => Ok(data),
+                    false => Err(format!("Buffer {} is shorter than {} bytes", buffer.index(), buffer.length())),
+                }
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Self::from_document(&gltf.document, &buffers, dir, first_mesh, first_texture)
+    }
+
+    fn from_document(
+        document: &gltf::Document,
+        buffers: &[Vec<u8>],
+        dir: Option<&Path>,
+        first_mesh: MeshId,
+        first_texture: TextureId,
+    ) -> Result<Self, String> {
+        let mut scene = GltfScene {
+            meshes: Vec::new(),
+            objects: Vec::new(),
+            textures: Vec::new(),
+        };
+        // Handles of the primitives (mesh and primitive index) and the materials already imported
+        let mut primitive_ids: HashMap<(usize, usize), MeshId> = HashMap::new();
+        let mut material_ids: HashMap<Option<usize>, TextureId> = HashMap::new();
+
+        let root = match document.default_scene().or_else(|| document.scenes().next()) {
+            Some(root) => root,
+            None => return Ok(scene),
+        };
+
+        let y_up_to_z_up = glam::Mat4::from_rotation_x(std::f32::consts::FRAC_PI_2);
+        // Nodes to visit with the transformation of their parent, in reverse order, so they are visited depth first
+        let mut nodes: Vec<(gltf::Node, glam::Mat4)> = root.nodes().map(|node| (node, y_up_to_z_up)).collect();
+        nodes.reverse();
+
+        while let Some((node, parent_m)) = nodes.pop() {
+            let transform_m = parent_m * glam::Mat4::from_cols_array_2d(&node.transform().matrix());
+            let first_child = nodes.len();
+            nodes.extend(node.children().map(|child| (child, transform_m)));
+            nodes[first_child..].reverse();
+
+            let Some(mesh) = node.mesh() else { continue };
+            for primitive in mesh.primitives() {
+                // Points and lines can't be drawn by the triangle pipelines
+                if primitive.mode() != gltf::mesh::Mode::Triangles {
+                    continue;
+                }
+
+                let key = (mesh.index(), primitive.index());
+                let m_type = match primitive_ids.get(&key) {
+                    Some(&m_type) => m_type,
+                    None => {
+                        let m_type = MeshId(first_mesh.0 + scene.meshes.len() as u32);
+                        let mesh = read_primitive(&primitive, buffers, m_type)
+                            .map_err(|err| format!("Mesh {} primitive {}: {}", key.0, key.1, err))?;
+                        scene.meshes.push(mesh);
+                        primitive_ids.insert(key, m_type);
+                        m_type
+                    }
+                };
+
+                let material = primitive.material();
+                let t_type = match material_ids.get(&material.index()) {
+                    Some(&t_type) => t_type,
+                    None => {
+                        let t_type = TextureId(first_texture.0 + scene.textures.len() as u32);
+                        scene.textures.push(read_base_color(&material, buffers, dir)?);
+                        material_ids.insert(material.index(), t_type);
+                        t_type
+                    }
+                };
+
+                scene.objects.push(Object { transform_m, m_type, t_type });
+            }
+        }
+
+        Ok(scene)
+    }
+}
+
+fn read_primitive(primitive: &gltf::Primitive, buffers: &[Vec<u8>], m_type: MeshId) -> Result<Mesh, String> {
+    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data[..]));
+
+    let positions: Vec<[f32; 3]> = reader.read_positions().ok_or("No positions")?.collect();
+    let tex_coords: Vec<[f32; 2]> = match reader.read_tex_coords(0) {
+        Some(tex_coords) => tex_coords.into_f32().collect(),
+        None => vec![[0.0, 0.0]; positions.len()],
+    };
+    let normals: Vec<[f32; 3]> = match reader.read_normals() {
+        Some(normals) => normals.collect(),
+        None => vec![[0.0; 3]; positions.len()],
+    };
+
+    if tex_coords.len() != positions.len() || normals.len() != positions.len() {
+        return Err("Attributes have different lengths".to_string());
+    }
+
+    let indices: Vec<u32> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..positions.len() as u32).collect(),
+    };
+    if indices.iter().any(|&index| index as usize >= positions.len()) {
+        return Err("Index out of range".to_string());
+    }
+
+    let vertices = (0..positions.len())
+        .map(|i| Vertex::with_normal(positions[i], tex_coords[i], normals[i]))
+        .collect();
+    let mut mesh = Mesh { m_type, vertices, indices, lods: Vec::new() };
+    mesh.generate_normals();
+
+    // Tangents of the file are kept, they belong to its normal maps
+    match reader.read_tangents() {
+        Some(tangents) => {
+            for (vertex, tangent) in mesh.vertices.iter_mut().zip(tangents) {
+                vertex._tangent = tangent;
+            }
+        }
+        None => mesh.generate_tangents(),
+    }
+
+    mesh.generate_lods()?;
+    Ok(mesh)
+}
+
+// Base color texture multiplied by the base color factor, 1x1 texture of the factor without a texture
+fn read_base_color(material: &gltf::Material, buffers: &[Vec<u8>], dir: Option<&Path>) -> Result<image::RgbaImage, String> {
+    let pbr = material.pbr_metallic_roughness();
+    let factor = pbr.base_color_factor();
+
+    let mut texture = match pbr.base_color_texture() {
+        Some(info) => read_image(&info.texture().source(), buffers, dir)?,
+        None => image::RgbaImage::from_pixel(1, 1, image::Rgba([255; 4])),
+    };
+
+    multiply_color(&mut texture, factor);
+    Ok(texture)
+}
+
+// Multiplies the sRGB texture by a linear color (and alpha), shared with the OBJ materials
+pub fn multiply_color(texture: &mut image::RgbaImage, factor: [f32; 4]) {
+    if factor == [1.0; 4] {
+        return;
+    }
+    for pixel in texture.pixels_mut() {
+        for c in 0..3 {
+            let linear = srgb_to_linear(pixel[c] as f32 / 255.0) * factor[c];
+            pixel[c] = (linear_to_srgb(linear) * 255.0).round() as u8;
+        }
+        pixel[3] = (pixel[3] as f32 * factor[3]).round() as u8;
+    }
+}
+
+// Encoded image (PNG, JPEG, ...) from a buffer view or a URI, the missing channels are filled
+// like in the glTF spec (gray or opaque)
+fn read_image(image: &gltf::Image, buffers: &[Vec<u8>], dir: Option<&Path>) -> Result<image::RgbaImage, String> {
+    let data = match image.source() {
+        gltf::image::Source::View { view, .. } => {
+            let buffer = &buffers[view.buffer().index()];
+            buffer.get(view.offset()..view.offset() + view.length()).ok_or("Image is out of its buffer")?.to_vec()
+        }
+        gltf::image::Source::Uri { uri, .. } => read_uri(uri, dir)?,
+    };
+    let image = image::load_from_memory(&data).map_err(|err| format!("Image {}: {}", image.index(), err))?;
+    Ok(image.to_rgba8())
+}
+
+// Base64 data URI or a (percent-encoded) path relative to the directory of the file
+fn read_uri(uri: &str, dir: Option<&Path>) -> Result<Vec<u8>, String> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (_, base64) = data.split_once(";base64,").ok_or("Data URI isn't base64 encoded")?;
+        return decode_base64(base64);
+    }
+
+    let dir = dir.ok_or_else(|| format!("External file {} can't be referenced", uri))?;
+    let path = dir.join(decode_percent(uri)?);
+    std::fs::read(&path).map_err(|err| format!("Can't read {}: {}", path.display(), err))
+}
+
+fn decode_base64(data: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(data.len() * 3 / 4);
+    let (mut bits, mut count) = (0u32, 0);
+    for c in data.bytes().take_while(|&c| c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return Err(format!("Invalid base64 character '{}'", c as char)),
+        };
+        bits = (bits << 6 | value as u32) & 0xFFFF;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+fn decode_percent(uri: &str) -> Result<String, String> {
+    let mut bytes = Vec::with_capacity(uri.len());
+    let mut rest = uri.as_bytes();
+    while let Some((&c, tail)) = rest.split_first() {
+        match c {
+            b'%' => {
+                let hex = tail.get(..2).and_then(|hex| std::str::from_utf8(hex).ok());
+                let byte = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                bytes.push(byte.ok_or_else(|| format!("Invalid URI {}", uri))?);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(c);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| format!("Invalid URI {}", uri))
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    match c <= 0.04045 {
+        true => c / 12.92,
+        false => ((c + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    match c <= 0.0031308 {
+        true => c * 12.92,
+        false => 1.055 * c.powf(1.0 / 2.4) - 0.055,
+    }
+}
@@ -0,0 +1,357 @@
+use super::{gltf_scene, tangents, Mesh, MeshId, Object, TextureId, Vertex};
+use std::collections::HashMap;
+use std::path::Path;
+
+// Wavefront OBJ model. Every group (g or o, split further by usemtl) has its own vertices,
+// faces are triangulated and the vertices with the same position, UV and normal are shared.
+// Coordinates are kept as they are in the file, V of the UVs is flipped for the top-left origin.
+// Vertices without a normal get the normal of their face, averaged over the faces of the same
+// smoothing group (s 1, s 2, ...) around them, tangents are generated for all.
+#[derive(Clone, Debug, Default)]
+pub struct ObjModel {
+    pub groups: Vec<ObjGroup>,
+    pub materials: Vec<ObjMaterial>,
+    pub material_libs: Vec<String>, // mtllib files, loaded by ObjModel::load
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ObjGroup {
+    pub name: String,
+    pub material: Option<String>,
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+// Diffuse color and texture of a material from an MTL file
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjMaterial {
+    pub name: String,
+    pub diffuse: [f32; 3],
+    pub diffuse_texture: Option<String>,
+}
+
+// Meshes, objects and textures of an OBJ file with its materials, like GltfScene.
+// Every group is a mesh drawn by an object at the origin and every material a texture
+// (map_Kd multiplied by Kd, or only Kd), groups without a known material are white.
+pub struct ObjScene {
+    pub meshes: Vec<Mesh>, // Handles following first_mesh
+    pub objects: Vec<Object>,
+    pub textures: Vec<image::RgbaImage>, // Handles following first_texture
+}
+
+// Key of a deduplicated vertex, indices of the position, UV and normal.
+// Vertices without a normal are shared only within their smoothing group.
+type VertexKey = (usize, Option<usize>, Option<usize>, Option<Smoothing>);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Smoothing {
+    Group(u32),
+    Face(usize), // Outside smoothing groups, every face has its own vertices
+}
+
+impl ObjGroup {
+    fn new(name: &str, material: Option<String>) -> Self {
+        ObjGroup {
+            name: name.to_string(),
+            material,
+            ..Default::default()
+        }
+    }
+
+    pub fn to_mesh(&self, m_type: MeshId) -> Mesh {
+        Mesh {
+            m_type,
+            vertices: self.vertices.clone(),
+            indices: self.indices.clone(),
+            lods: Vec::new(),
+        }
+    }
+}
+
+impl ObjModel {
+    // Reads the OBJ file with its MTL files, which are relative to it
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let read = |path: &Path| {
+            std::fs::read_to_string(path).map_err(|err| format!("Can't read {}: {}", path.display(), err))
+        };
+
+        let mut model = Self::parse(&read(path)?).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        for lib in &model.material_libs {
+            let lib_path = dir.join(lib);
+            let materials = parse_mtl(&read(&lib_path)?).map_err(|err| format!("{}: {}", lib_path.display(), err))?;
+            model.materials.extend(materials);
+        }
+
+        Ok(model)
+    }
+
+    // Parses the OBJ source, errors start with the line number
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut model = ObjModel::default();
+        let mut positions: Vec<[f32; 3]> = Vec::new();
+        let mut tex_coords: Vec<[f32; 2]> = Vec::new();
+        let mut normals: Vec<[f32; 3]> = Vec::new();
+
+        let mut group = ObjGroup::new("default", None);
+        let mut shared: HashMap<VertexKey, u32> = HashMap::new();
+        let mut smoothing_group: Option<u32> = None;
+        let mut face_count = 0;
+
+        for (i, line) in source.lines().enumerate() {
+            let error = |message: String| format!("line {}: {}", i + 1, message);
+
+            let line = line.split('#').next().unwrap().trim();
+            let mut tokens = line.split_whitespace();
+            let Some(keyword) = tokens.next() else { continue };
+            let args: Vec<&str> = tokens.collect();
+
+            match keyword {
+                "v" => positions.push(parse_floats::<3>(&args).map_err(error)?),
+                "vt" => {
+                    let [u, v] = parse_floats::<2>(&args).map_err(error)?;
+                    tex_coords.push([u, 1.0 - v]);
+                }
+                "vn" => normals.push(parse_floats::<3>(&args).map_err(error)?),
+                "f" => {
+                    if args.len() < 3 {
+                        return Err(error(format!("Face needs at least 3 vertices, it has {}", args.len())));
+                    }
+
+                    let smoothing = match smoothing_group {
+                        Some(number) => Smoothing::Group(number),
+                        None => Smoothing::Face(face_count),
+                    };
+                    face_count += 1;
+
+                    let mut face = Vec::with_capacity(args.len());
+                    for arg in &args {
+                        let (position, tex_coord, normal) =
+                            parse_face_vertex(arg, positions.len(), tex_coords.len(), normals.len()).map_err(error)?;
+                        let key = (position, tex_coord, normal, normal.is_none().then_some(smoothing));
+                        let index = match shared.get(&key) {
+                            Some(&index) => index,
+                            None => {
+                                let index = u32::try_from(group.vertices.len())
+                                    .map_err(|_| error(format!("Group {} has too many vertices", group.name)))?;
+                                group.vertices.push(Vertex::with_normal(
+                                    positions[position],
+                                    tex_coord.map_or([0.0, 0.0], |t| tex_coords[t]),
+                                    normal.map_or([0.0; 3], |n| normals[n]),
+                                ));
+                                shared.insert(key, index);
+                                index
+                            }
+                        };
+                        face.push(index);
+                    }
+
+                    // Triangle fan
+                    for j in 1..face.len() - 1 {
+                        group.indices.extend([face[0], face[j], face[j + 1]]);
+                    }
+                }
+                "g" | "o" | "usemtl" => {
+                    let (name, material) = match keyword {
+                        "usemtl" => (group.name.clone(), args.first().map(|name| name.to_string())),
+                        _ => (args.join(" "), group.material.clone()),
+                    };
+                    let previous = std::mem::replace(&mut group, ObjGroup::new(&name, material));
+                    if !previous.indices.is_empty() {
+                        model.groups.push(previous);
+                    }
+                    shared.clear();
+                }
+                "s" => {
+                    smoothing_group = match args.first() {
+                        None | Some(&"off") | Some(&"0") => None,
+                        Some(number) => Some(number.parse().map_err(|_| error(format!("Invalid smoothing group {:?}", number)))?),
+                    }
+                }
+                "mtllib" => model.material_libs.extend(args.iter().map(|lib| lib.to_string())),
+                // Lines, points, ...
+                _ => {}
+            }
+        }
+
+        if !group.indices.is_empty() {
+            model.groups.push(group);
+        }
+
+        for group in &mut model.groups {
+            tangents::generate_normals(&mut group.vertices, &group.indices);
+            tangents::generate_tangents(&mut group.vertices, &group.indices);
+        }
+
+        Ok(model)
+    }
+
+    // All groups in one mesh, with levels of detail made by simplification
+    pub fn to_mesh(&self, m_type: MeshId) -> Result<Mesh, String> {
+        let mut mesh = Mesh {
+            m_type,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            lods: Vec::new(),
+        };
+
+        for group in &self.groups {
+            let offset = mesh.vertices.len();
+            if offset + group.vertices.len() > u32::MAX as usize + 1 {
+                return Err(format!("Mesh has more than {} vertices", u32::MAX as usize + 1));
+            }
+            mesh.vertices.extend(&group.vertices);
+            mesh.indices.extend(group.indices.iter().map(|&index| index + offset as u32));
+        }
+
+        mesh.generate_lods()?;
+        Ok(mesh)
+    }
+
+    pub fn material(&self, name: &str) -> Option<&ObjMaterial> {
+        self.materials.iter().find(|material| material.name == name)
+    }
+}
+
+impl ObjScene {
+    // The OBJ file with its MTL files and textures, which are relative to it
+    pub fn load(path: &Path, first_mesh: MeshId, first_texture: TextureId) -> Result<Self, String> {
+        let model = ObjModel::load(path)?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut scene = ObjScene {
+            meshes: Vec::new(),
+            objects: Vec::new(),
+            textures: Vec::new(),
+        };
+        // Handles of the materials already imported
+        let mut material_ids: HashMap<Option<&str>, TextureId> = HashMap::new();
+
+        for group in &model.groups {
+            let m_type = MeshId(first_mesh.0 + scene.meshes.len() as u32);
+            let mut mesh = group.to_mesh(m_type);
+            mesh.generate_lods().map_err(|err| format!("{}: group {}: {}", path.display(), group.name, err))?;
+            scene.meshes.push(mesh);
+
+            let material = group.material.as_deref().and_then(|name| model.material(name));
+            let key = material.map(|material| material.name.as_str());
+            let t_type = match material_ids.get(&key) {
+                Some(&t_type) => t_type,
+                None => {
+                    let t_type = TextureId(first_texture.0 + scene.textures.len() as u32);
+                    let texture = match material {
+                        Some(material) => material.to_texture(dir)?,
+                        None => image::RgbaImage::from_pixel(1, 1, image::Rgba([255; 4])),
+                    };
+                    scene.textures.push(texture);
+                    material_ids.insert(key, t_type);
+                    t_type
+                }
+            };
+
+            scene.objects.push(Object {
+                transform_m: glam::Mat4::IDENTITY,
+                m_type,
+                t_type,
+            });
+        }
+
+        Ok(scene)
+    }
+}
+
+impl ObjMaterial {
+    // Diffuse texture (relative to the directory) multiplied by the diffuse color,
+    // 1x1 texture of the color without a texture
+    pub fn to_texture(&self, dir: &Path) -> Result<image::RgbaImage, String> {
+        let mut texture = match &self.diffuse_texture {
+            Some(file) => {
+                let path = dir.join(file);
+                image::open(&path).map_err(|err| format!("{}: {}", path.display(), err))?.to_rgba8()
+            }
+            None => image::RgbaImage::from_pixel(1, 1, image::Rgba([255; 4])),
+        };
+        let [r, g, b] = self.diffuse;
+        gltf_scene::multiply_color(&mut texture, [r, g, b, 1.0]);
+        Ok(texture)
+    }
+}
+
+// Parses the MTL source, only the diffuse color and texture are kept
+pub fn parse_mtl(source: &str) -> Result<Vec<ObjMaterial>, String> {
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let error = |message: String| format!("line {}: {}", i + 1, message);
+
+        let line = line.split('#').next().unwrap().trim();
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else { continue };
+        let args: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            materials.push(ObjMaterial {
+                name: args.join(" "),
+                diffuse: [1.0; 3],
+                diffuse_texture: None,
+            });
+            continue;
+        }
+
+        let material = match materials.last_mut() {
+            Some(material) => material,
+            None if matches!(keyword, "Kd" | "map_Kd") => return Err(error(format!("{} before newmtl", keyword))),
+            None => continue,
+        };
+        match keyword {
+            "Kd" => material.diffuse = parse_floats::<3>(&args).map_err(error)?,
+            // Options of the texture (-o, -s, ...) precede the file name
+            "map_Kd" => match args.last() {
+                Some(path) => material.diffuse_texture = Some(path.to_string()),
+                None => return Err(error("map_Kd without a file".to_string())),
+            },
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+// The first N numbers, further ones (e.g. w of the positions) are ignored
+fn parse_floats<const N: usize>(args: &[&str]) -> Result<[f32; N], String> {
+    if args.len() < N {
+        return Err(format!("Expected {} numbers, found {}", N, args.len()));
+    }
+
+    let mut values = [0.0; N];
+    for (value, arg) in values.iter_mut().zip(args) {
+        *value = arg.parse().map_err(|_| format!("Invalid number {:?}", arg))?;
+    }
+    Ok(values)
+}
+
+// v, v/vt, v//vn or v/vt/vn with 1-based or negative (relative to the end) indices
+fn parse_face_vertex(arg: &str, positions: usize, tex_coords: usize, normals: usize) -> Result<(usize, Option<usize>, Option<usize>), String> {
+    let mut parts = arg.split('/');
+    let index = |part: Option<&str>, count: usize, name: &str| -> Result<Option<usize>, String> {
+        let part = match part {
+            Some(part) if !part.is_empty() => part,
+            _ => return Ok(None),
+        };
+        let index: i64 = part.parse().map_err(|_| format!("Invalid {} index {:?}", name, part))?;
+        let resolved = match index {
+            i if i > 0 => i - 1,
+            i if i < 0 => count as i64 + i,
+            _ => -1,
+        };
+        match resolved >= 0 && resolved < count as i64 {
+            true => Ok(Some(resolved as usize)),
+            false => Err(format!("{} index {} is out of range", name, index)),
+        }
+    };
+
+    let position = index(parts.next(), positions, "Position")?.ok_or_else(|| format!("Face vertex {:?} has no position", arg))?;
+    let tex_coord = index(parts.next(), tex_coords, "UV")?;
+    let normal = index(parts.next(), normals, "Normal")?;
+
+    Ok((position, tex_coord, normal))
+}
@@ -0,0 +1,381 @@
+use super::Vertex;
+use glam::DVec3;
+use std::collections::HashMap;
+
+// Weight of the planes keeping the borders and seams in place, relative to the planes of the triangles
+const CONSTRAINT_WEIGHT: f64 = 10.0;
+// Largest distance of vertices welded into one point, relative to the largest coordinate of the mesh
+const WELD_DISTANCE: f64 = 1e-5;
+// Cosine of the largest angle between the edges of a border or a seam at a point which can move along it
+const BEND_LIMIT: f64 = 0.9;
+// Cosine of the largest angle between the normal of a changed triangle and its original normal
+const TURN_LIMIT: f64 = 0.25;
+
+// Level of detail made by Mesh::simplify, the indices refer to the vertices of the mesh
+#[derive(Clone, Debug, PartialEq)]
+pub struct Simplified {
+    pub indices: Vec<u32>,
+    // Largest root mean square distance of a moved point to the planes of the original triangles around it
+    pub error: f32,
+}
+
+// Sum of squared distances to planes as a symmetric 4x4 matrix (xx, xy, xz, xw, yy, yz, yw, zz, zw, ww)
+#[derive(Clone, Copy, Default)]
+struct Quadric {
+    q: [f64; 10],
+    planes: f64, // Number of the planes of triangles
+}
+
+impl Quadric {
+    fn plane(normal: DVec3, point: DVec3, weight: f64) -> Self {
+        let (a, b, c) = (normal.x, normal.y, normal.z);
+        let d = -normal.dot(point);
+        let q = [a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d].map(|v| v * weight);
+        Quadric { q, planes: 1.0 }
+    }
+
+    // Plane of a border or a seam, which doesn't count into the mean distance
+    fn constraint(normal: DVec3, point: DVec3) -> Self {
+        Quadric { planes: 0.0, ..Quadric::plane(normal, point, CONSTRAINT_WEIGHT) }
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for (a, b) in self.q.iter_mut().zip(other.q) {
+            *a += b;
+        }
+        self.planes += other.planes;
+    }
+
+    // Mean squared distance of the point to the planes
+    fn error(&self, p: DVec3) -> f64 {
+        let q = &self.q;
+        let error = q[0] * p.x * p.x + q[4] * p.y * p.y + q[7] * p.z * p.z
+            + 2.0 * (q[1] * p.x * p.y + q[2] * p.x * p.z + q[5] * p.y * p.z)
+            + 2.0 * (q[3] * p.x + q[6] * p.y + q[8] * p.z)
+            + q[9];
+        error.max(0.0) / self.planes.max(1.0)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum EdgeKind {
+    Interior,
+    Border,       // Has one triangle
+    Seam,         // Its two triangles use different vertices, e.g. of two UV islands
+    NonManifold,  // Has more than two triangles
+}
+
+// Edge between two points (groups of vertices at the same position)
+struct Edge {
+    triangles: u32,
+    pairs: Vec<(u32, u32)>, // Distinct pairs of vertices, the vertex of the lower group first
+}
+
+impl Edge {
+    fn kind(&self) -> EdgeKind {
+        match (self.triangles, self.pairs.len()) {
+            (1, _) => EdgeKind::Border,
+            (2, 1) => EdgeKind::Interior,
+            (2, _) => EdgeKind::Seam,
+            _ => EdgeKind::NonManifold,
+        }
+    }
+}
+
+// How a point can move
+#[derive(Clone, Copy, PartialEq)]
+enum PointKind {
+    Free,   // Onto any neighbour
+    Border, // Along the border
+    Seam,   // Along the seam, the vertices of both sides move together
+    Locked, // Corners, ends and junctions of seams and the other complex points
+}
+
+// Edges and points of the remaining triangles
+struct Topology {
+    edges: HashMap<(u32, u32), Edge>,
+    kinds: Vec<PointKind>,
+    vertices: Vec<Vec<u32>>, // Vertices of every point used by the triangles
+}
+
+impl Topology {
+    fn new(triangles: &[[u32; 3]], alive: &[bool], group: &[u32], points: &[DVec3]) -> Self {
+        let point_count = points.len();
+        let mut edges: HashMap<(u32, u32), Edge> = HashMap::new();
+        let mut vertices = vec![Vec::new(); point_count];
+        for (t, _) in triangles.iter().zip(alive).filter(|(_, &alive)| alive) {
+            for k in 0..3 {
+                let (a, b) = (t[k], t[(k + 1) % 3]);
+                let (a, b) = match group[a as usize] < group[b as usize] {
+                    true => (a, b),
+                    false => (b, a),
+                };
+                let edge = edges
+                    .entry((group[a as usize], group[b as usize]))
+                    .or_insert(Edge { triangles: 0, pairs: Vec::new() });
+                edge.triangles += 1;
+                if !edge.pairs.contains(&(a, b)) {
+                    edge.pairs.push((a, b));
+                }
+
+                let point = &mut vertices[group[t[k] as usize] as usize];
+                if !point.contains(&t[k]) {
+                    point.push(t[k]);
+                }
+            }
+        }
+
+        // Number of border, seam and non-manifold edges of every point and the other ends of its border or seam edges
+        let mut counts = vec![[0u32; 3]; point_count];
+        let mut ends = vec![Vec::new(); point_count];
+        for (&(a, b), edge) in &edges {
+            let slot = match edge.kind() {
+                EdgeKind::Interior => continue,
+                EdgeKind::Border => 0,
+                EdgeKind::Seam => 1,
+                EdgeKind::NonManifold => 2,
+            };
+            counts[a as usize][slot] += 1;
+            counts[b as usize][slot] += 1;
+            ends[a as usize].push(b);
+            ends[b as usize].push(a);
+        }
+
+        // Points on a border or a seam only move along a straight enough part of it
+        let straight = |point: usize| {
+            let p = points[point];
+            let (a, b) = (points[ends[point][0] as usize], points[ends[point][1] as usize]);
+            (p - a).normalize_or_zero().dot((b - p).normalize_or_zero()) >= BEND_LIMIT
+        };
+        let kinds = (0..point_count)
+            .map(|point| match (vertices[point].len(), counts[point]) {
+                (1, [0, 0, 0]) => PointKind::Free,
+                (1, [2, 0, 0]) if straight(point) => PointKind::Border,
+                (2, [0, 2, 0]) if straight(point) => PointKind::Seam,
+                _ => PointKind::Locked,
+            })
+            .collect();
+
+        Topology { edges, kinds, vertices }
+    }
+
+    fn edge(&self, a: u32, b: u32) -> &Edge {
+        &self.edges[&(a.min(b), a.max(b))]
+    }
+
+    // Vertex of the point `to` which every vertex of the point `from` moves to,
+    // None if the point can't move along the edge
+    fn collapse_map(&self, from: u32, to: u32) -> Option<Vec<(u32, u32)>> {
+        let edge = self.edge(from, to);
+        let allowed = match self.kinds[from as usize] {
+            PointKind::Free => edge.kind() != EdgeKind::NonManifold,
+            PointKind::Border => edge.kind() == EdgeKind::Border,
+            PointKind::Seam => edge.kind() == EdgeKind::Seam,
+            PointKind::Locked => false,
+        };
+        if !allowed {
+            return None;
+        }
+
+        // Every vertex has to be connected to exactly one vertex of the other point
+        self.vertices[from as usize]
+            .iter()
+            .map(|&vertex| {
+                let mut partners = edge.pairs.iter().filter_map(|&(a, b)| match (a == vertex, b == vertex) {
+                    (true, _) => Some(b),
+                    (_, true) => Some(a),
+                    _ => None,
+                });
+                match (partners.next(), partners.next()) {
+                    (Some(partner), None) => Some((vertex, partner)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+fn face_normal(p: [DVec3; 3]) -> DVec3 {
+    (p[1] - p[0]).cross(p[2] - p[0])
+}
+
+// Quadric error edge collapse. Points (vertices at the same position) are collapsed onto their neighbours
+// in the order of the error, until the number of triangles is at most target_ratio of the original one
+// or no collapse is possible. Borders stay in place and UV seams (and other attribute seams, like hard
+// edges) only collapse along themselves with the vertices of both sides, so they don't open.
+// No vertices are created, the indices refer to the given vertices.
+pub fn simplify(vertices: &[Vertex], indices: &[u32], target_ratio: f32) -> Result<Simplified, String> {
+    if !(target_ratio > 0.0 && target_ratio <= 1.0) {
+        return Err(format!("Target ratio has to be in the range (0, 1], not {}", target_ratio));
+    }
+    if indices.len() % 3 != 0 {
+        return Err(format!("Number of indices {} isn't a multiple of 3", indices.len()));
+    }
+    if let Some(&index) = indices.iter().find(|&&index| index as usize >= vertices.len()) {
+        return Err(format!("Index {} is out of range of {} vertices", index, vertices.len()));
+    }
+
+    // Identical vertices are merged, vertices closer than WELD_DISTANCE (relative to the size of the mesh)
+    // form a point of the surface, e.g. the ends of a seam computed with sin(2 * PI) != 0
+    let extent = vertices.iter().flat_map(|vertex| vertex.position()).fold(0.0f64, |max, v| max.max(v.abs() as f64));
+    let cell = WELD_DISTANCE * extent.max(f64::MIN_POSITIVE);
+    let mut identical: HashMap<[u32; 13], u32> = HashMap::new();
+    let mut cells: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+    let mut canonical = vec![0; vertices.len()];
+    let mut group = vec![0; vertices.len()];
+    let mut points: Vec<DVec3> = Vec::new();
+    for (i, vertex) in vertices.iter().enumerate() {
+        canonical[i] = *identical.entry(bytemuck::cast(*vertex)).or_insert(i as u32);
+
+        let position = DVec3::from(vertex.position().map(|v| v as f64));
+        let key = (position / cell).floor().to_array().map(|v| v as i64);
+        let nearby = (0..27).flat_map(|n| cells.get(&[key[0] + n % 3 - 1, key[1] + n / 3 % 3 - 1, key[2] + n / 9 - 1]));
+        group[i] = match nearby.flatten().find(|&&point| points[point as usize].distance(position) <= cell) {
+            Some(&point) => point,
+            None => {
+                points.push(position);
+                cells.entry(key).or_default().push(points.len() as u32 - 1);
+                points.len() as u32 - 1
+            }
+        };
+    }
+
+    // Triangles collapsed to a line or a point are dropped
+    let mut triangles: Vec<[u32; 3]> = indices
+        .chunks_exact(3)
+        .map(|t| [t[0], t[1], t[2]].map(|index| canonical[index as usize]))
+        .filter(|t| {
+            let g = t.map(|index| group[index as usize]);
+            g[0] != g[1] && g[1] != g[2] && g[2] != g[0]
+        })
+        .collect();
+    let target = (indices.len() / 3) as f64 * target_ratio as f64;
+    let mut alive = vec![true; triangles.len()];
+    let mut count = triangles.len();
+
+    // Planes of the triangles around every point, borders and seams get planes perpendicular to their triangles
+    let topology = Topology::new(&triangles, &alive, &group, &points);
+    let mut quadrics = vec![Quadric::default(); points.len()];
+    let mut point_triangles = vec![Vec::new(); points.len()];
+    let mut normals = Vec::with_capacity(triangles.len());
+    for (i, t) in triangles.iter().enumerate() {
+        let g = t.map(|index| group[index as usize]);
+        let p = g.map(|g| points[g as usize]);
+        let normal = face_normal(p).normalize_or_zero();
+        normals.push(normal);
+        for k in 0..3 {
+            quadrics[g[k] as usize].add(&Quadric::plane(normal, p[k], 1.0));
+            point_triangles[g[k] as usize].push(i);
+
+            let (a, b) = (g[k], g[(k + 1) % 3]);
+            if matches!(topology.edge(a, b).kind(), EdgeKind::Border | EdgeKind::Seam) {
+                let side = (p[(k + 1) % 3] - p[k]).cross(normal).normalize_or_zero();
+                let constraint = Quadric::constraint(side, p[k]);
+                quadrics[a as usize].add(&constraint);
+                quadrics[b as usize].add(&constraint);
+            }
+        }
+    }
+
+    // Passes over the cheapest collapses, the points around a collapse wait for the next pass
+    let mut error: f64 = 0.0;
+    while count as f64 > target {
+        let topology = Topology::new(&triangles, &alive, &group, &points);
+        let mut candidates: Vec<(f64, u32, u32)> = Vec::new();
+        for &(a, b) in topology.edges.keys() {
+            for (from, to) in [(a, b), (b, a)] {
+                if topology.collapse_map(from, to).is_some() {
+                    let mut quadric = quadrics[from as usize];
+                    quadric.add(&quadrics[to as usize]);
+                    candidates.push((quadric.error(points[to as usize]), from, to));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut touched = vec![false; points.len()];
+        let mut collapsed = false;
+        for (cost, from, to) in candidates {
+            if count as f64 <= target {
+                break;
+            }
+            if touched[from as usize] || touched[to as usize] {
+                continue;
+            }
+
+            let around: Vec<usize> = point_triangles[from as usize].iter().copied().filter(|&t| alive[t]).collect();
+            let neighbours = |point: u32| {
+                let mut neighbours: Vec<u32> = point_triangles[point as usize]
+                    .iter()
+                    .filter(|&&t| alive[t])
+                    .flat_map(|&t| triangles[t].map(|index| group[index as usize]))
+                    .filter(|&g| g != point)
+                    .collect();
+                neighbours.sort_unstable();
+                neighbours.dedup();
+                neighbours
+            };
+
+            // The points connected to both ends are only the third corners of the triangles of the edge,
+            // otherwise the collapse would make the surface non-manifold
+            let (from_neighbours, to_neighbours) = (neighbours(from), neighbours(to));
+            let common = from_neighbours.iter().filter(|g| to_neighbours.contains(g)).count();
+            let shared = around.iter().filter(|&&t| triangles[t].iter().any(|&index| group[index as usize] == to)).count();
+            if common != shared {
+                continue;
+            }
+
+            // The remaining triangles must not flip, become degenerate or turn too far from their original direction
+            let flips = around.iter().any(|&t| {
+                let g = triangles[t].map(|index| group[index as usize]);
+                if g.contains(&to) {
+                    return false;
+                }
+                let after = g.map(|g| points[if g == from { to } else { g } as usize]);
+                face_normal(after).normalize_or_zero().dot(normals[t]) < TURN_LIMIT
+            });
+            if flips {
+                continue;
+            }
+
+            let map = topology.collapse_map(from, to).unwrap();
+            for &t in &around {
+                for index in triangles[t].iter_mut() {
+                    if let Some(&(_, partner)) = map.iter().find(|&&(vertex, _)| vertex == *index) {
+                        *index = partner;
+                    }
+                }
+                if triangles[t].iter().filter(|&&index| group[index as usize] == to).count() > 1 {
+                    alive[t] = false;
+                    count -= 1;
+                } else {
+                    point_triangles[to as usize].push(t);
+                }
+            }
+            point_triangles[from as usize].clear();
+            let from_quadric = quadrics[from as usize];
+            quadrics[to as usize].add(&from_quadric);
+            error = error.max(cost);
+
+            touched[from as usize] = true;
+            touched[to as usize] = true;
+            for g in from_neighbours {
+                touched[g as usize] = true;
+            }
+            collapsed = true;
+        }
+
+        if !collapsed {
+            break;
+        }
+    }
+
+    let indices = triangles
+        .iter()
+        .zip(&alive)
+        .filter(|(_, &alive)| alive)
+        .flat_map(|(t, _)| *t)
+        .collect();
+
+    Ok(Simplified { indices, error: error.sqrt() as f32 })
+}
@@ -0,0 +1,83 @@
+use super::Vertex;
+use glam::{Vec2, Vec3};
+
+// Corners of the triangles with their positions
+fn triangles<'a>(vertices: &'a [Vertex], indices: &'a [u32]) -> impl Iterator<Item = ([usize; 3], [Vec3; 3])> + 'a {
+    indices.chunks_exact(3).map(move |t| {
+        let corners = [t[0] as usize, t[1] as usize, t[2] as usize];
+        (corners, corners.map(|i| Vec3::from_array(vertices[i].position())))
+    })
+}
+
+// Angle of the triangle at its first corner, weights the contribution of the triangle to the vertex
+fn corner_angle(p: [Vec3; 3]) -> f32 {
+    let (a, b) = ((p[1] - p[0]).normalize_or_zero(), (p[2] - p[0]).normalize_or_zero());
+    a.dot(b).clamp(-1.0, 1.0).acos()
+}
+
+// Any unit vector perpendicular to n
+fn perpendicular(n: Vec3) -> Vec3 {
+    let axis = match n.x.abs() < 0.9 {
+        true => Vec3::X,
+        false => Vec3::Y,
+    };
+    (axis - n * n.dot(axis)).normalize_or_zero()
+}
+
+// Normals of the vertices which have none (zero), the angle weighted normals of their triangles.
+// Vertices on hard edges have to be split, like the ones loaded from files.
+pub fn generate_normals(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut normals = vec![Vec3::ZERO; vertices.len()];
+    for (corners, p) in triangles(vertices, indices) {
+        let normal = (p[1] - p[0]).cross(p[2] - p[0]).normalize_or_zero();
+        for k in 0..3 {
+            normals[corners[k]] += normal * corner_angle([p[k], p[(k + 1) % 3], p[(k + 2) % 3]]);
+        }
+    }
+
+    for (vertex, normal) in vertices.iter_mut().zip(normals) {
+        if vertex.normal() == [0.0; 3] {
+            vertex._normal = normal.normalize_or_zero().to_array();
+        }
+    }
+}
+
+// Tangents in the MikkTSpace convention: the direction of increasing U orthogonalized against the normal,
+// w is the sign of the bitangent (direction of increasing V) = w * cross(normal, tangent).
+// Contributions of the triangles are weighted by their angle at the vertex, vertices without UVs get
+// an arbitrary tangent perpendicular to their normal.
+pub fn generate_tangents(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut tangents = vec![Vec3::ZERO; vertices.len()];
+    let mut bitangents = vec![Vec3::ZERO; vertices.len()];
+    for (corners, p) in triangles(vertices, indices) {
+        let uv = corners.map(|i| Vec2::from_array(vertices[i].tex_coord()));
+        let (e1, e2) = (p[1] - p[0], p[2] - p[0]);
+        let (d1, d2) = (uv[1] - uv[0], uv[2] - uv[0]);
+        let det = d1.x * d2.y - d2.x * d1.y;
+        if det.abs() < f32::EPSILON {
+            continue;
+        }
+
+        // Solves e = du * tangent + dv * bitangent for both edges
+        let tangent = ((e1 * d2.y - e2 * d1.y) / det).normalize_or_zero();
+        let bitangent = ((e2 * d1.x - e1 * d2.x) / det).normalize_or_zero();
+        for k in 0..3 {
+            let weight = corner_angle([p[k], p[(k + 1) % 3], p[(k + 2) % 3]]);
+            tangents[corners[k]] += tangent * weight;
+            bitangents[corners[k]] += bitangent * weight;
+        }
+    }
+
+    for (i, vertex) in vertices.iter_mut().enumerate() {
+        let normal = Vec3::from_array(vertex.normal());
+        let mut tangent = (tangents[i] - normal * normal.dot(tangents[i])).normalize_or_zero();
+        if tangent == Vec3::ZERO {
+            tangent = perpendicular(normal);
+        }
+        let w = match normal.cross(tangent).dot(bitangents[i]) < 0.0 {
+            true => -1.0,
+            false => 1.0,
+        };
+        vertex._tangent = tangent.extend(w).to_array();
+    }
+}
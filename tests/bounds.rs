@@ -0,0 +1,85 @@
+use paralelizace_3d_renderovani::shapes::{self, Aabb, Batch, BoundingSphere, Mesh, MeshId, Object, TextureId};
+use glam::{Mat4, Quat, Vec3};
+
+fn mesh(m_type: MeshId) -> Mesh {
+    let mut mesh = Mesh { m_type, vertices: Vec::new(), indices: Vec::new(), lods: Vec::new() };
+    mesh.generate_vertices();
+    mesh
+}
+
+#[test]
+fn mesh_bounds() {
+    let cube = mesh(MeshId::CUBE);
+    assert_eq!(cube.aabb(), Aabb { min: Vec3::splat(-1.0), max: Vec3::ONE });
+    assert_eq!(cube.bounding_sphere().center, Vec3::ZERO);
+    assert!((cube.bounding_sphere().radius - 3f32.sqrt()).abs() < 1e-6);
+
+    let empty = Mesh { m_type: MeshId::CUBE, vertices: Vec::new(), indices: Vec::new(), lods: Vec::new() };
+    assert!(empty.aabb().is_empty());
+    assert_eq!(empty.bounding_sphere(), BoundingSphere::EMPTY);
+}
+
+#[test]
+fn object_bounds_in_world_space() {
+    let cube = mesh(MeshId::CUBE);
+    let object = Object {
+        transform_m: Mat4::from_scale_rotation_translation(
+            Vec3::new(2.0, 1.0, 1.0),
+            Quat::from_rotation_z(std::f32::consts::FRAC_PI_2),
+            Vec3::new(10.0, 0.0, 0.0),
+        ),
+        m_type: MeshId::CUBE,
+        t_type: TextureId::BLUE,
+    };
+
+    // Scaled along x, then rotated onto the y axis
+    let bounds = object.bounds(&cube);
+    assert!(bounds.aabb.min.abs_diff_eq(Vec3::new(9.0, -2.0, -1.0), 1e-5));
+    assert!(bounds.aabb.max.abs_diff_eq(Vec3::new(11.0, 2.0, 1.0), 1e-5));
+    assert!(bounds.sphere.center.abs_diff_eq(Vec3::new(10.0, 0.0, 0.0), 1e-5));
+    assert!((bounds.sphere.radius - 2.0 * 3f32.sqrt()).abs() < 1e-5);
+}
+
+#[test]
+fn unions() {
+    let a = Aabb::from_points([Vec3::ZERO, Vec3::ONE]);
+    let b = Aabb::from_points([Vec3::splat(2.0), Vec3::splat(3.0)]);
+    let union = a.union(&b);
+    assert_eq!(union, Aabb { min: Vec3::ZERO, max: Vec3::splat(3.0) });
+    assert!(!a.intersects(&b) && union.intersects(&a));
+    assert_eq!(Aabb::EMPTY.union(&a), a);
+
+    let a = BoundingSphere { center: Vec3::ZERO, radius: 1.0 };
+    let b = BoundingSphere { center: Vec3::new(4.0, 0.0, 0.0), radius: 1.0 };
+    let union = a.union(&b);
+    assert_eq!(union, BoundingSphere { center: Vec3::new(2.0, 0.0, 0.0), radius: 3.0 });
+    assert!(union.contains(Vec3::new(-1.0, 0.0, 0.0)) && union.contains(Vec3::new(5.0, 0.0, 0.0)));
+
+    // Contained sphere
+    let inner = BoundingSphere { center: Vec3::new(0.5, 0.0, 0.0), radius: 0.2 };
+    assert_eq!(a.union(&inner), a);
+}
+
+#[test]
+fn merged_bounds_follow_matrices() {
+    let cube = mesh(MeshId::CUBE);
+    let sphere = mesh(MeshId::SPHERE);
+    let batches = vec![
+        Batch {
+            transform_m: vec![Mat4::IDENTITY, Mat4::from_translation(Vec3::X * 5.0)],
+            m_type: MeshId::CUBE,
+            t_type: TextureId::BLUE,
+        },
+        Batch {
+            transform_m: vec![Mat4::from_scale(Vec3::splat(2.0))],
+            m_type: MeshId::SPHERE,
+            t_type: TextureId::RED,
+        },
+    ];
+
+    let bounds = shapes::merge_bounds(&batches, &vec![&cube, &sphere]);
+    assert_eq!(bounds.len(), 3);
+    assert_eq!(bounds[1].aabb_min, [4.0, -1.0, -1.0, 0.0]);
+    assert_eq!(bounds[1].aabb_max, [6.0, 1.0, 1.0, 0.0]);
+    assert_eq!(bounds[2].sphere[3], 2.0 * sphere.bounding_sphere().radius);
+}
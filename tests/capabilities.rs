@@ -0,0 +1,29 @@
+use paralelizace_3d_renderovani::framework::Capabilities;
+
+#[test]
+fn capabilities_from_features() {
+    let capabilities = Capabilities::from_features(
+        wgpu::Features::MULTI_DRAW_INDIRECT_COUNT | wgpu::Features::TEXTURE_BINDING_ARRAY,
+    );
+    // Count needs the multi draw and the binding array needs the non-uniform indexing
+    assert!(!capabilities.multi_draw_indirect_count);
+    assert!(!capabilities.texture_binding_array);
+    assert_eq!(capabilities.missing().len(), 4);
+
+    let capabilities = Capabilities::from_features(wgpu::Features::all());
+    assert!(capabilities.missing().is_empty());
+}
+
+#[test]
+fn disable_capabilities() {
+    let mut capabilities = Capabilities::from_features(wgpu::Features::all());
+    capabilities.disable("multi-draw-indirect, texture-binding-array").unwrap();
+    assert_eq!(
+        capabilities.missing(),
+        ["multi-draw-indirect", "multi-draw-indirect-count", "texture-binding-array"]
+    );
+
+    capabilities.disable("all").unwrap();
+    assert_eq!(capabilities.missing().len(), 4);
+    assert!(capabilities.disable("compute").is_err());
+}
@@ -0,0 +1,24 @@
+use paralelizace_3d_renderovani::framework::{Args, CliOption};
+
+const ROWS: CliOption = CliOption { name: "rows", value_name: "<count>", help: "" };
+
+fn parse(args: &[&str]) -> Result<Args, String> {
+    Args::parse(args.iter().map(|arg| arg.to_string()), &[ROWS])
+}
+
+#[test]
+fn backends_are_checked() {
+    assert_eq!(parse(&["--backend", "vulkan,gl"]).unwrap().backend, Some(wgpu::Backends::VULKAN | wgpu::Backends::GL));
+    assert_eq!(parse(&["--backend=foo"]).unwrap_err(), "Unknown backend 'foo'");
+}
+
+#[test]
+fn invalid_option_values_are_errors() {
+    let args = parse(&["--rows", "3"]).unwrap();
+    assert_eq!(args.options.parse::<usize>("rows"), Ok(Some(3)));
+    assert_eq!(args.options.parse::<f32>("spin"), Ok(None));
+
+    let args = parse(&["--rows", "abc"]).unwrap();
+    let err = args.options.parse::<usize>("rows").unwrap_err();
+    assert!(err.starts_with("Invalid value 'abc' of --rows"), "{}", err);
+}
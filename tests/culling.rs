@@ -0,0 +1,238 @@
+mod common;
+
+use paralelizace_3d_renderovani::culling::{
+    self, CullInstance, DrawCommand, DrawCompaction, Frustum, OcclusionCulling, OcclusionStats,
+};
+use paralelizace_3d_renderovani::framework;
+use paralelizace_3d_renderovani::shapes::BoundingSphere;
+use glam::{Mat4, Vec3};
+use wgpu::util::DeviceExt;
+
+// Camera at the origin looking along +X, visible depth 1..20
+fn view_proj() -> Mat4 {
+    let projection = Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 1.0, 20.0);
+    projection * Mat4::look_at_rh(Vec3::ZERO, Vec3::X, Vec3::Z)
+}
+
+fn frustum() -> Frustum {
+    Frustum::from_matrix(&view_proj())
+}
+
+fn instance(x: f32, y: f32, radius: f32, batch: u32) -> CullInstance {
+    CullInstance::new(BoundingSphere { center: Vec3::new(x, y, 0.0), radius }, batch)
+}
+
+// Two batches with 3 and 2 instances
+fn scene() -> (Vec<CullInstance>, Vec<DrawCommand>) {
+    let instances = vec![
+        instance(5.0, 0.0, 1.0, 0),   // visible
+        instance(-5.0, 0.0, 1.0, 0),  // behind the camera
+        instance(10.0, 2.0, 1.0, 0),  // visible
+        instance(30.0, 0.0, 1.0, 1),  // beyond the far plane
+        instance(5.0, 5.5, 1.0, 1),   // intersects the left plane
+    ];
+    let commands = vec![
+        DrawCommand { index_count: 36, instance_count: 3, base_index: 0, vertex_offset: 0, base_instance: 0 },
+        DrawCommand { index_count: 12, instance_count: 2, base_index: 36, vertex_offset: 0, base_instance: 3 },
+    ];
+    (instances, commands)
+}
+
+#[test]
+fn frustum_planes() {
+    let frustum = frustum();
+    assert!(frustum.intersects_sphere(Vec3::new(5.0, 0.0, 0.0), 0.1));
+    assert!(!frustum.intersects_sphere(Vec3::new(-5.0, 0.0, 0.0), 1.0));
+    assert!(!frustum.intersects_sphere(Vec3::new(0.5, 0.0, 0.0), 0.1)); // in front of the near plane
+    assert!(frustum.intersects_sphere(Vec3::new(21.0, 0.0, 0.0), 1.5));
+    assert!(!frustum.intersects_sphere(Vec3::new(5.0, 0.0, 7.0), 1.0));
+    assert!(frustum.intersects_sphere(Vec3::new(5.0, 0.0, 5.5), 1.0));
+}
+
+#[test]
+fn cpu_culling_compacts_batches() {
+    let (instances, commands) = scene();
+    let (culled, visible) = culling::cull(&frustum(), &instances, &commands);
+
+    assert_eq!(culled[0], DrawCommand { instance_count: 2, ..commands[0] });
+    assert_eq!(culled[1], DrawCommand { instance_count: 1, ..commands[1] });
+    assert_eq!(&visible[0..2], &[0, 2]);
+    assert_eq!(visible[3], 4);
+}
+
+// Without the depth of a previous frame the first phase only culls by the frustum, like culling::cull
+#[test]
+fn gpu_culling_matches_cpu() {
+    let framework::HeadlessSetup { device, queue, .. } =
+        pollster::block_on(framework::setup_headless(&framework::Args::default()));
+
+    let (instances, commands) = scene();
+    let (expected_commands, expected_visible) = culling::cull(&frustum(), &instances, &commands);
+
+    let mut gpu_culling = OcclusionCulling::new(&device, &instances, &commands, (64, 64));
+    gpu_culling.update_camera(view_proj());
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    gpu_culling.cull_phase1(&queue, &mut encoder);
+    queue.submit(Some(encoder.finish()));
+
+    let indirect: Vec<DrawCommand> = bytemuck::cast_slice(&common::read_buffer(&device, &queue, gpu_culling.indirect_buffer())).to_vec();
+    let visible: Vec<u32> = bytemuck::cast_slice(&common::read_buffer(&device, &queue, gpu_culling.visible_buffer())).to_vec();
+    let commands = &indirect[..commands.len()];
+    assert_eq!(commands, expected_commands);
+
+    // The order within a batch depends on the scheduling of the GPU threads
+    for command in commands {
+        let range = command.base_instance as usize..(command.base_instance + command.instance_count) as usize;
+        let mut actual = visible[range.clone()].to_vec();
+        actual.sort();
+        assert_eq!(actual, expected_visible[range]);
+    }
+}
+
+// Occluder covering the whole frame at depth 0.5 and an object behind it
+#[test]
+fn gpu_occlusion_culling_phases() {
+    let framework::HeadlessSetup { device, queue, .. } =
+        pollster::block_on(framework::setup_headless(&framework::Args::default()));
+
+    let instances = vec![
+        instance(1.3, 0.0, 0.1, 0),  // in front of the occluder
+        instance(10.0, 0.0, 1.0, 0), // behind the occluder
+        instance(-5.0, 0.0, 1.0, 0), // behind the camera
+    ];
+    let commands = vec![DrawCommand { index_count: 36, instance_count: 3, base_index: 0, vertex_offset: 0, base_instance: 0 }];
+
+    let mut culling = OcclusionCulling::new(&device, &instances, &commands, (64, 64));
+    let projection = Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 1.0, 20.0);
+    culling.update_camera(projection * Mat4::look_at_rh(Vec3::ZERO, Vec3::X, Vec3::Z));
+
+    // Runs both phases with the depth buffer cleared to the given depth in place of the first draw
+    let mut frame = |depth: f32| {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        culling.cull_phase1(&queue, &mut encoder);
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: culling.depth_view(),
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(depth),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+        culling.build_pyramid(&mut encoder);
+        culling.cull_phase2(&mut encoder);
+        queue.submit(Some(encoder.finish()));
+
+        let commands: Vec<DrawCommand> = bytemuck::cast_slice(&common::read_buffer(&device, &queue, culling.indirect_buffer())).to_vec();
+        let visible: Vec<u32> = bytemuck::cast_slice(&common::read_buffer(&device, &queue, culling.visible_buffer())).to_vec();
+        let mut phase1 = visible[..commands[0].instance_count as usize].to_vec();
+        phase1.sort();
+        let phase2 = visible[commands[1].base_instance as usize..][..commands[1].instance_count as usize].to_vec();
+        (phase1, phase2, culling.read_stats(&device, &queue).unwrap())
+    };
+
+    // Without the previous frame only the frustum culling applies
+    let (phase1, phase2, stats) = frame(0.5);
+    assert_eq!((phase1, phase2), (vec![0, 1], vec![]));
+    assert_eq!(stats, OcclusionStats { frustum: 1, ..Default::default() });
+
+    let (phase1, phase2, stats) = frame(0.5);
+    assert_eq!((phase1, phase2), (vec![0], vec![]));
+    assert_eq!(stats, OcclusionStats { frustum: 1, phase1: 1, phase2: 1, disoccluded: 0 });
+
+    // The occluder disappears in the first phase of this frame
+    let (phase1, phase2, stats) = frame(1.0);
+    assert_eq!((phase1, phase2), (vec![0], vec![1]));
+    assert_eq!(stats, OcclusionStats { frustum: 1, phase1: 1, phase2: 0, disoccluded: 1 });
+}
+
+// Spheres of radius 1 along the view direction of the frustum's camera (f = 1)
+#[test]
+fn lod_follows_screen_size() {
+    let projection = Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 1.0, 20.0);
+    let view_proj = projection * Mat4::look_at_rh(Vec3::ZERO, Vec3::X, Vec3::Z);
+    let sphere = |x: f32| [x, 0.0, 0.0, 1.0];
+
+    assert!((culling::screen_size(&view_proj, sphere(5.0)) - 0.2).abs() < 1e-6);
+    assert_eq!(culling::screen_size(&view_proj, sphere(0.5)), f32::INFINITY);
+
+    let lods: Vec<u32> = [0.5, 2.0, 5.0, 10.0, 19.0].iter().map(|&x| culling::select_lod(&view_proj, sphere(x), 4, 0.25)).collect();
+    assert_eq!(lods, vec![0, 0, 1, 2, 3]);
+    assert_eq!(culling::select_lod(&view_proj, sphere(19.0), 2, 0.25), 1);
+    assert_eq!(culling::select_lod(&view_proj, sphere(19.0), 4, 0.0), 0);
+}
+
+#[test]
+fn gpu_occlusion_culling_selects_lods() {
+    let framework::HeadlessSetup { device, queue, .. } =
+        pollster::block_on(framework::setup_headless(&framework::Args::default()));
+
+    // Batch with 4 levels of detail followed by a batch without them, every command has a region of 4 instances
+    let sphere = |x: f32| BoundingSphere { center: Vec3::new(x, 0.0, 0.0), radius: 1.0 };
+    let mut instances: Vec<CullInstance> = [2.0, 5.0, 10.0, 19.0].iter().map(|&x| CullInstance::with_lods(sphere(x), 0, 4)).collect();
+    instances.push(CullInstance::new(sphere(19.0), 4));
+    let commands: Vec<DrawCommand> = (0..5)
+        .map(|c| DrawCommand { index_count: 36, instance_count: 4, base_index: 36 * c, vertex_offset: 0, base_instance: 4 * c })
+        .collect();
+
+    let mut culling = OcclusionCulling::new(&device, &instances, &commands, (64, 64));
+    let projection = Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 1.0, 20.0);
+    let view_proj = projection * Mat4::look_at_rh(Vec3::ZERO, Vec3::X, Vec3::Z);
+    culling.update_camera(view_proj);
+    culling.set_lod_size(0.25);
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    culling.cull_phase1(&queue, &mut encoder);
+    queue.submit(Some(encoder.finish()));
+
+    let culled: Vec<DrawCommand> = bytemuck::cast_slice(&common::read_buffer(&device, &queue, culling.indirect_buffer())).to_vec();
+    let visible: Vec<u32> = bytemuck::cast_slice(&common::read_buffer(&device, &queue, culling.visible_buffer())).to_vec();
+    for (i, instance) in instances.iter().enumerate() {
+        let command = instance.batch + culling::select_lod(&view_proj, instance.sphere, instance.lods, 0.25);
+        let command = culled[command as usize];
+        assert_eq!(command.instance_count, 1, "instance {}", i);
+        assert_eq!(visible[command.base_instance as usize], i as u32);
+    }
+    assert_eq!(culled[..5].iter().map(|command| command.instance_count).sum::<u32>(), 5);
+}
+
+#[test]
+fn gpu_draw_compaction_skips_empty_commands() {
+    let framework::HeadlessSetup { device, queue, .. } =
+        pollster::block_on(framework::setup_headless(&framework::Args::default()));
+
+    // The first command is outside of the compacted range
+    let commands: Vec<DrawCommand> = [5, 0, 2, 0, 0, 1, 3]
+        .iter()
+        .enumerate()
+        .map(|(i, &instance_count)| DrawCommand {
+            index_count: 36,
+            instance_count,
+            base_index: 0,
+            vertex_offset: 0,
+            base_instance: 10 * i as u32,
+        })
+        .collect();
+    let expected = culling::compact(&commands[1..]);
+
+    let commands_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: bytemuck::cast_slice(&commands),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+    let compaction = DrawCompaction::new(&device, &commands_buf, 1, commands.len() as u32 - 1);
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    compaction.compact(&mut encoder);
+    queue.submit(Some(encoder.finish()));
+
+    let count: u32 = *bytemuck::from_bytes(&common::read_buffer(&device, &queue, compaction.count_buffer()));
+    let mut compacted: Vec<DrawCommand> = bytemuck::cast_slice(&common::read_buffer(&device, &queue, compaction.indirect_buffer())).to_vec();
+    compacted.truncate(count as usize);
+    compacted.sort_by_key(|command| command.base_instance);
+    assert_eq!(compacted, expected);
+}
@@ -0,0 +1,22 @@
+use paralelizace_3d_renderovani::framework::FormatPolicy;
+use wgpu::TextureFormat;
+
+#[test]
+fn prefers_listed_format() {
+    let supported = [TextureFormat::Bgra8UnormSrgb, TextureFormat::Bgra8Unorm];
+    assert_eq!(FormatPolicy::default().select(&supported), Some(TextureFormat::Bgra8Unorm));
+}
+
+#[test]
+fn falls_back_to_srgb_choice() {
+    let supported = [TextureFormat::Rgba16Float, TextureFormat::Rgba8UnormSrgb];
+    let policy = FormatPolicy { preferred: Vec::new(), srgb: true };
+    assert_eq!(policy.select(&supported), Some(TextureFormat::Rgba8UnormSrgb));
+}
+
+#[test]
+fn falls_back_to_first_format() {
+    let supported = [TextureFormat::Rgba16Float, TextureFormat::Rgb10a2Unorm];
+    assert_eq!(FormatPolicy::default().select(&supported), Some(TextureFormat::Rgba16Float));
+    assert_eq!(FormatPolicy::default().select(&[]), None);
+}
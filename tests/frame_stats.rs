@@ -0,0 +1,46 @@
+use paralelizace_3d_renderovani::framework::{FrameSample, FrameStats, Summary};
+
+fn sample(frame_ms: f64) -> FrameSample {
+    FrameSample { frame_ms, encode_ms: 1.0, submit_ms: 0.5 }
+}
+
+#[test]
+fn summary_percentiles() {
+    let values: Vec<f64> = (1..=100).map(f64::from).collect();
+    let summary = Summary::from_values(&values).unwrap();
+    assert_eq!((summary.min, summary.max), (1.0, 100.0));
+    assert_eq!(summary.mean, 50.5);
+    assert_eq!((summary.p50, summary.p95, summary.p99), (50.0, 95.0, 99.0));
+    assert_eq!(Summary::from_values(&[]), None);
+}
+
+#[test]
+fn summary_of_latest_frames() {
+    let mut stats = FrameStats::new();
+    for frame_ms in [100.0, 100.0, 10.0, 20.0] {
+        stats.push(sample(frame_ms));
+    }
+    let summary = stats.summary(2, |s| s.frame_ms).unwrap();
+    assert_eq!((summary.min, summary.max), (10.0, 20.0));
+    assert_eq!(stats.summary(10, |s| s.frame_ms).unwrap().max, 100.0);
+}
+
+#[test]
+fn exports_csv_and_json() {
+    let mut stats = FrameStats::new();
+    stats.push(sample(16.0));
+    stats.push(sample(17.0));
+
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let csv = dir.join("frame_stats.csv");
+    let json = dir.join("frame_stats.json");
+    stats.export(&csv).unwrap();
+    stats.export(&json).unwrap();
+
+    let csv = std::fs::read_to_string(csv).unwrap();
+    assert_eq!(csv.lines().collect::<Vec<_>>(), ["frame,frame_ms,encode_ms,submit_ms", "0,16,1,0.5", "1,17,1,0.5"]);
+
+    let json = std::fs::read_to_string(json).unwrap();
+    assert!(json.starts_with('[') && json.trim_end().ends_with(']'));
+    assert!(json.contains("{\"frame\": 1, \"frame_ms\": 17, \"encode_ms\": 1, \"submit_ms\": 0.5}\n"));
+}
@@ -0,0 +1,173 @@
+use paralelizace_3d_renderovani::shapes::{self, Aabb, Mesh, MeshId, Shape, Vertex, MAX_LODS};
+use glam::Vec3;
+
+// Every triangle faces outwards (counter-clockwise seen along -normal) like its vertex normals
+fn assert_outward(name: &str, vertices: &[Vertex], indices: &[u32]) {
+    for t in indices.chunks_exact(3) {
+        let p = [t[0], t[1], t[2]].map(|i| Vec3::from_array(vertices[i as usize].position()));
+        let face = (p[1] - p[0]).cross(p[2] - p[0]);
+        if face.length() < 1e-6 {
+            continue;
+        }
+        for &i in t {
+            let normal = Vec3::from_array(vertices[i as usize].normal());
+            assert!(face.dot(normal) > 0.0, "{}: triangle {:?} faces against its normals", name, t);
+        }
+    }
+}
+
+#[test]
+fn cube_has_a_texture_on_every_face() {
+    let mut cube = Mesh { m_type: MeshId::CUBE, vertices: Vec::new(), indices: Vec::new(), lods: Vec::new() };
+    cube.generate_vertices();
+    assert_eq!((cube.vertices.len(), cube.indices.len()), (24, 36));
+    assert_eq!(cube.aabb(), Aabb { min: Vec3::splat(-1.0), max: Vec3::ONE });
+    assert_outward("cube", &cube.vertices, &cube.indices);
+
+    for face in cube.vertices.chunks_exact(4) {
+        // Flat faces with the corners of the texture
+        assert!(face.iter().all(|v| v.normal() == face[0].normal()));
+        let mut uvs: Vec<[f32; 2]> = face.iter().map(|v| v.tex_coord()).collect();
+        uvs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(uvs, vec![[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]]);
+    }
+
+    // Side faces are upright, V (top-left origin) grows downwards
+    let front = cube.vertices.iter().find(|v| v.normal() == [0.0, -1.0, 0.0] && v.tex_coord() == [0.0, 0.0]).unwrap();
+    assert_eq!(front.position(), [-1.0, -1.0, 1.0]);
+}
+
+#[test]
+fn boxes_have_the_given_size() {
+    let shape = Shape::Box { size: [4.0, 0.5, 0.2] };
+    let mesh = shape.to_mesh(MeshId::FIRST_CUSTOM);
+    assert_eq!(mesh.vertices.len(), 24);
+    assert_eq!(mesh.aabb(), Aabb { min: Vec3::new(-2.0, -0.25, -0.1), max: Vec3::new(2.0, 0.25, 0.1) });
+
+    let shape = Shape::SubdividedBox { size: [2.0, 2.0, 2.0], subdivisions: [3, 2, 1] };
+    let (vertices, indices) = shape.generate();
+    // Faces along X and Y (top, bottom), X and Z (front, back), Y and Z (sides)
+    assert_eq!(vertices.len(), 2 * (4 * 3 + 4 * 2 + 3 * 2));
+    assert_eq!(indices.len(), 6 * 2 * (3 * 2 + 3 + 2));
+    assert_outward("subdivided box", &vertices, &indices);
+    let xs: Vec<f32> = vertices.iter().filter(|v| v.normal() == [0.0, 0.0, 1.0]).map(|v| v.position()[0]).collect();
+    assert!(xs.iter().any(|&x| (x - 1.0 / 3.0).abs() < 1e-6));
+
+    assert!(Shape::Box { size: [1.0, -1.0, 1.0] }.validate().is_err());
+    assert!(Shape::SubdividedBox { size: [1.0; 3], subdivisions: [1, 0, 1] }.validate().is_err());
+}
+
+#[test]
+fn procedural_meshes_face_outwards() {
+    for m_type in [MeshId::CYLINDER, MeshId::SPHERE] {
+        let mut mesh = Mesh { m_type, vertices: Vec::new(), indices: Vec::new(), lods: Vec::new() };
+        mesh.generate_vertices();
+        assert_outward(&format!("{:?}", m_type), &mesh.vertices, &mesh.indices);
+    }
+}
+
+fn shapes() -> Vec<(Shape, Aabb)> {
+    let aabb = |min: [f32; 3], max: [f32; 3]| Aabb { min: Vec3::from(min), max: Vec3::from(max) };
+    vec![
+        (Shape::RoundedBox { size: [2.0, 1.0, 0.5], radius: 0.2, segments: 4 }, aabb([-1.0, -0.5, -0.25], [1.0, 0.5, 0.25])),
+        (Shape::RoundedBox { size: [1.0, 1.0, 1.0], radius: 0.5, segments: 3 }, aabb([-0.5; 3], [0.5; 3])),
+        (Shape::Plane { size: [4.0, 2.0], subdivisions: [4, 2] }, aabb([-2.0, -1.0, 0.0], [2.0, 1.0, 0.0])),
+        (Shape::Sphere { radius: 2.0, segments: 8, rings: 6 }, aabb([-2.0; 3], [2.0; 3])),
+        (Shape::Icosphere { radius: 1.0, subdivisions: 2 }, aabb([-1.0; 3], [1.0; 3])),
+        (Shape::Cylinder { radius: 0.5, height: 3.0, segments: 8 }, aabb([-0.5, -0.5, -1.5], [0.5, 0.5, 1.5])),
+        (Shape::Cone { radius: 1.0, height: 2.0, segments: 8 }, aabb([-1.0; 3], [1.0; 3])),
+        (Shape::Capsule { radius: 0.5, height: 3.0, segments: 8, rings: 4 }, aabb([-0.5, -0.5, -1.5], [0.5, 0.5, 1.5])),
+        (Shape::Torus { radius: 1.0, tube_radius: 0.25, segments: 16, tube_segments: 8 }, aabb([-1.25, -1.25, -0.25], [1.25, 1.25, 0.25])),
+    ]
+}
+
+#[test]
+fn primitives_have_normals_and_uvs() {
+    for (shape, expected) in shapes() {
+        shape.validate().unwrap();
+        let mesh = shape.to_mesh(MeshId::FIRST_CUSTOM);
+        let name = format!("{:?}", shape);
+        assert!(mesh.aabb().min.abs_diff_eq(expected.min, 1e-5) && mesh.aabb().max.abs_diff_eq(expected.max, 1e-5), "{}: {:?}", name, mesh.aabb());
+        assert_outward(&name, &mesh.vertices, &mesh.indices);
+
+        for vertex in &mesh.vertices {
+            assert!((Vec3::from_array(vertex.normal()).length() - 1.0).abs() < 1e-5, "{}: {:?}", name, vertex);
+            let [u, v] = vertex.tex_coord();
+            // The icosphere's triangles on the seam continue behind U = 1
+            assert!((0.0..=1.1).contains(&u) && (0.0..=1.0).contains(&v), "{}: {:?}", name, vertex);
+        }
+    }
+
+    // Every vertex of a round shape lies on its surface in the direction of its normal
+    let (vertices, indices) = Shape::Icosphere { radius: 2.0, subdivisions: 1 }.generate();
+    // 42 points shared by the triangles, the ones on the seam twice
+    assert!(vertices.len() >= 42 && vertices.len() < indices.len() / 2);
+    for vertex in &vertices {
+        assert!(Vec3::from_array(vertex.position()).abs_diff_eq(Vec3::from_array(vertex.normal()) * 2.0, 1e-5));
+    }
+    let (vertices, _) = Shape::Torus { radius: 1.0, tube_radius: 0.25, segments: 16, tube_segments: 8 }.generate();
+    for vertex in &vertices {
+        let p = Vec3::from_array(vertex.position());
+        let center = (p * Vec3::new(1.0, 1.0, 0.0)).normalize();
+        assert!(p.abs_diff_eq(center + Vec3::from_array(vertex.normal()) * 0.25, 1e-5));
+    }
+}
+
+#[test]
+fn invalid_primitives_are_reported() {
+    let invalid = [
+        Shape::RoundedBox { size: [1.0, 1.0, 1.0], radius: 0.6, segments: 2 },
+        Shape::Plane { size: [1.0, 1.0], subdivisions: [0, 1] },
+        Shape::Sphere { radius: 1.0, segments: 2, rings: 4 },
+        Shape::Icosphere { radius: 1.0, subdivisions: 9 },
+        Shape::Cone { radius: 1.0, height: 0.0, segments: 8 },
+        Shape::Capsule { radius: 1.0, height: 1.0, segments: 8, rings: 2 },
+        Shape::Torus { radius: 1.0, tube_radius: f32::NAN, segments: 8, tube_segments: 8 },
+    ];
+    for shape in invalid {
+        assert!(shape.validate().is_err(), "{:?}", shape);
+    }
+    assert_eq!(
+        Shape::Capsule { radius: 1.0, height: 1.0, segments: 8, rings: 2 }.validate().unwrap_err(),
+        "Height has to be at least the diameter"
+    );
+}
+
+#[test]
+fn shapes_have_coarser_levels_of_detail() {
+    let mut sphere = Mesh { m_type: MeshId::SPHERE, vertices: Vec::new(), indices: Vec::new(), lods: Vec::new() };
+    sphere.generate_vertices();
+    assert_eq!(sphere.lod_count(), MAX_LODS);
+    let counts: Vec<usize> = (0..sphere.lod_count()).map(|lod| sphere.lod_indices(lod).len()).collect();
+    assert!(counts.windows(2).all(|pair| pair[0] > pair[1]), "{:?}", counts);
+    for lod in 0..sphere.lod_count() {
+        assert_outward(&format!("sphere LOD {}", lod), &sphere.vertices, sphere.lod_indices(lod));
+    }
+    assert_eq!(sphere.aabb(), Shape::Sphere { radius: 1.0, segments: 20, rings: 20 }.to_mesh(MeshId::SPHERE).aabb());
+
+    // Halved resolution down to the smallest valid one
+    assert_eq!(
+        Shape::Sphere { radius: 1.0, segments: 20, rings: 20 }.coarser(),
+        Some(Shape::Sphere { radius: 1.0, segments: 10, rings: 10 })
+    );
+    assert_eq!(
+        Shape::Sphere { radius: 1.0, segments: 5, rings: 5 }.coarser(),
+        Some(Shape::Sphere { radius: 1.0, segments: 3, rings: 2 })
+    );
+    assert_eq!(Shape::Torus { radius: 1.0, tube_radius: 0.5, segments: 3, tube_segments: 3 }.coarser(), None);
+    assert_eq!(Shape::Icosphere { radius: 1.0, subdivisions: 0 }.coarser(), None);
+    assert_eq!(Shape::Box { size: [1.0; 3] }.to_mesh(MeshId::CUBE).lod_count(), 1);
+    assert_eq!(Shape::Icosphere { radius: 1.0, subdivisions: 1 }.to_mesh(MeshId::FIRST_CUSTOM).lod_count(), 2);
+
+    // The levels of detail follow each other in the merged index buffer
+    let cube = Shape::Box { size: [2.0; 3] }.to_mesh(MeshId::CUBE);
+    let (_, indices) = shapes::merge_index_vertex_data(&vec![&cube, &sphere]).unwrap();
+    let ranges = shapes::lod_ranges(&[&cube, &sphere]);
+    assert_eq!(ranges[0], vec![(36, 0)]);
+    assert_eq!(ranges[1][0], (counts[0] as u32, 36));
+    assert_eq!(ranges[1][3].0 + ranges[1][3].1, indices.len() as u32);
+    for (lod, &(count, first)) in ranges[1].iter().enumerate() {
+        assert_eq!(count as usize, counts[lod]);
+        assert_eq!(indices.get(first as usize), Some(sphere.lod_indices(lod)[0] + 24));
+    }
+}
@@ -0,0 +1,175 @@
+use paralelizace_3d_renderovani::shapes::{GltfScene, MeshId, TextureId};
+use glam::Vec3;
+
+// Binary glTF with the JSON chunk and the binary chunk
+fn glb(json: &str, bin: &[u8]) -> Vec<u8> {
+    let mut json = json.as_bytes().to_vec();
+    json.resize(json.len().next_multiple_of(4), b' ');
+    let mut bin = bin.to_vec();
+    bin.resize(bin.len().next_multiple_of(4), 0);
+
+    let mut data = Vec::new();
+    data.extend(b"glTF");
+    data.extend(2u32.to_le_bytes());
+    data.extend((12 + 8 + json.len() as u32 + 8 + bin.len() as u32).to_le_bytes());
+    data.extend((json.len() as u32).to_le_bytes());
+    data.extend(0x4E4F534Au32.to_le_bytes());
+    data.extend(json);
+    data.extend((bin.len() as u32).to_le_bytes());
+    data.extend(0x004E4942u32.to_le_bytes());
+    data.extend(bin);
+    data
+}
+
+// A triangle drawn by a node and its scaled child with a textured material and by another node
+// with a red material, a point primitive is skipped
+fn scene() -> Vec<u8> {
+    let mut png = Vec::new();
+    let image = image::RgbaImage::from_fn(2, 1, |x, _| image::Rgba([0, 255 * x as u8, 0, 255]));
+    image.write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png).unwrap();
+
+    let mut bin: Vec<u8> = Vec::new();
+    let positions: [f32; 9] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+    let tex_coords: [f32; 6] = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0];
+    let indices: [u16; 4] = [0, 1, 2, 0]; // Padded
+    bin.extend(bytemuck::cast_slice(&positions));
+    bin.extend(bytemuck::cast_slice(&tex_coords));
+    bin.extend(bytemuck::cast_slice(&indices));
+    let png_offset = bin.len();
+    bin.extend(&png);
+
+    let json = format!(
+        r#"{{
+            "asset": {{"version": "2.0"}},
+            "scene": 0,
+            "scenes": [{{"nodes": [0, 2]}}],
+            "nodes": [
+                {{"translation": [1, 0, 0], "children": [1], "mesh": 0}},
+                {{"scale": [2, 2, 2], "mesh": 0}},
+                {{"mesh": 1}}
+            ],
+            "meshes": [
+                {{"primitives": [{{"attributes": {{"POSITION": 0, "TEXCOORD_0": 1}}, "indices": 2, "material": 0}}]}},
+                {{"primitives": [
+                    {{"attributes": {{"POSITION": 0}}, "material": 1}},
+                    {{"attributes": {{"POSITION": 0}}, "mode": 0}}
+                ]}}
+            ],
+            "materials": [
+                {{"pbrMetallicRoughness": {{"baseColorTexture": {{"index": 0}}}}}},
+                {{"pbrMetallicRoughness": {{"baseColorFactor": [1, 0, 0, 1]}}}}
+            ],
+            "textures": [{{"source": 0}}],
+            "images": [{{"bufferView": 3, "mimeType": "image/png"}}],
+            "buffers": [{{"byteLength": {}}}],
+            "bufferViews": [
+                {{"buffer": 0, "byteOffset": 0, "byteLength": 36}},
+                {{"buffer": 0, "byteOffset": 36, "byteLength": 24}},
+                {{"buffer": 0, "byteOffset": 60, "byteLength": 6}},
+                {{"buffer": 0, "byteOffset": {}, "byteLength": {}}}
+            ],
+            "accessors": [
+                {{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0]}},
+                {{"bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC2"}},
+                {{"bufferView": 2, "componentType": 5123, "count": 3, "type": "SCALAR"}}
+            ]
+        }}"#,
+        bin.len(),
+        png_offset,
+        png.len(),
+    );
+
+    glb(&json, &bin)
+}
+
+#[test]
+fn nodes_are_flattened() {
+    let scene = GltfScene::from_slice(&scene(), MeshId::FIRST_CUSTOM, TextureId(3)).unwrap();
+
+    // The triangle primitive of every mesh, the point primitive is skipped
+    assert_eq!(scene.meshes.len(), 2);
+    assert_eq!(scene.meshes[0].m_type, MeshId::FIRST_CUSTOM);
+    assert_eq!(scene.meshes[0].indices, vec![0, 1, 2]);
+    assert_eq!(scene.meshes[0].vertices[1].tex_coord(), [1.0, 0.0]);
+    // Generated without NORMAL and TANGENT
+    assert_eq!(scene.meshes[0].vertices[1].normal(), [0.0, 0.0, 1.0]);
+    assert_eq!(scene.meshes[0].vertices[1].tangent(), [1.0, 0.0, 0.0, 1.0]);
+    assert_eq!(scene.meshes[1].indices, vec![0, 1, 2]);
+
+    let ids: Vec<_> = scene.objects.iter().map(|object| (object.m_type.0, object.t_type.0)).collect();
+    assert_eq!(ids, vec![(3, 3), (3, 3), (4, 4)]);
+
+    // Parent's translation, child's scale and Y up rotated to Z up
+    let point = scene.objects[1].transform_m.transform_point3(Vec3::Y);
+    assert!(point.abs_diff_eq(Vec3::new(1.0, 0.0, 2.0), 1e-5), "{}", point);
+    let point = scene.objects[0].transform_m.transform_point3(Vec3::Y);
+    assert!(point.abs_diff_eq(Vec3::new(1.0, 0.0, 1.0), 1e-5), "{}", point);
+}
+
+#[test]
+fn materials_become_textures() {
+    let scene = GltfScene::from_slice(&scene(), MeshId::FIRST_CUSTOM, TextureId(0)).unwrap();
+    assert_eq!(scene.textures.len(), 2);
+    assert_eq!(scene.textures[0].dimensions(), (2, 1));
+    assert_eq!(scene.textures[0].get_pixel(1, 0).0, [0, 255, 0, 255]);
+    assert_eq!(scene.textures[1].dimensions(), (1, 1));
+    assert_eq!(scene.textures[1].get_pixel(0, 0).0, [255, 0, 0, 255]);
+}
+
+#[test]
+fn invalid_files_are_reported() {
+    assert!(GltfScene::from_slice(b"not a gltf", MeshId::FIRST_CUSTOM, TextureId(0)).is_err());
+    let err = GltfScene::load(std::path::Path::new("missing.glb"), MeshId::FIRST_CUSTOM, TextureId(0)).err().unwrap();
+    assert!(err.starts_with("missing.glb"), "{}", err);
+}
+
+fn base64(data: &[u8]) -> String {
+    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::new();
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &b)| bits | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            text.push(match i <= chunk.len() {
+                true => CHARS[(bits >> (18 - 6 * i) & 63) as usize] as char,
+                false => '=',
+            });
+        }
+    }
+    text
+}
+
+#[test]
+fn data_uris_and_external_images_are_loaded() {
+    let dir = std::path::PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("gltf");
+    std::fs::create_dir_all(&dir).unwrap();
+    image::RgbaImage::from_pixel(1, 1, image::Rgba([0, 0, 255, 255])).save(dir.join("blue texture.png")).unwrap();
+
+    let positions: [f32; 9] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+    let json = format!(
+        r#"{{
+            "asset": {{"version": "2.0"}},
+            "scenes": [{{"nodes": [0]}}],
+            "nodes": [{{"mesh": 0}}],
+            "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}, "material": 0}}]}}],
+            "materials": [{{"pbrMetallicRoughness": {{"baseColorTexture": {{"index": 0}}}}}}],
+            "textures": [{{"source": 0}}],
+            "images": [{{"uri": "blue%20texture.png"}}],
+            "buffers": [{{"byteLength": 36, "uri": "data:application/octet-stream;base64,{}"}}],
+            "bufferViews": [{{"buffer": 0, "byteLength": 36}}],
+            "accessors": [
+                {{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0]}}
+            ]
+        }}"#,
+        base64(bytemuck::cast_slice(&positions)),
+    );
+    let path = dir.join("triangle.gltf");
+    std::fs::write(&path, &json).unwrap();
+
+    let scene = GltfScene::load(&path, MeshId::FIRST_CUSTOM, TextureId(0)).unwrap();
+    assert_eq!(scene.meshes[0].vertices[1].position(), [1.0, 0.0, 0.0]);
+    assert_eq!(scene.textures[0].get_pixel(0, 0).0, [0, 0, 255, 255]);
+
+    // Files next to a glTF in memory can't be found
+    let err = GltfScene::from_slice(json.as_bytes(), MeshId::FIRST_CUSTOM, TextureId(0)).err().unwrap();
+    assert!(err.contains("can't be referenced"), "{}", err);
+}
@@ -0,0 +1,157 @@
+// Golden image tests
+// Every example is rendered offscreen and compared with its reference image in tests/golden.
+// Set UPDATE_GOLDEN=1 to (re)generate the reference images instead of comparing against them.
+
+#[path = "../examples/triangle/main.rs"]
+#[allow(dead_code)]
+mod triangle;
+#[path = "../examples/cube/main.rs"]
+#[allow(dead_code)]
+mod cube;
+#[path = "../examples/gpu-driven-rendering/main.rs"]
+#[allow(dead_code)]
+mod gpu_driven_rendering;
+#[path = "../examples/post-processing/main.rs"]
+#[allow(dead_code)]
+mod post_processing;
+
+use paralelizace_3d_renderovani::framework;
+use std::path::PathBuf;
+
+const WIDTH: u32 = 256;
+const HEIGHT: u32 = 256;
+const FRAMES: u32 = 3;
+
+// Maximum difference of a single channel for two pixels to be considered equal
+const CHANNEL_TOLERANCE: u8 = 8;
+// Maximum ratio of pixels which can exceed the channel tolerance
+const MAX_MISMATCH_RATIO: f32 = 0.005;
+// Maximum mean perceptual difference (CIE76 delta E) over the whole image
+const MAX_MEAN_DELTA_E: f32 = 1.0;
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.png", name))
+}
+
+fn diff_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{}_diff.png", name))
+}
+
+// Converts sRGB pixel into CIE L*a*b* color space
+fn to_lab(pixel: &image::Rgba<u8>) -> [f32; 3] {
+    let linear = |c: u8| {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+    };
+    let (r, g, b) = (linear(pixel[0]), linear(pixel[1]), linear(pixel[2]));
+
+    // XYZ relative to the D65 white point
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+    let f = |t: f32| {
+        if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn delta_e(a: &image::Rgba<u8>, b: &image::Rgba<u8>) -> f32 {
+    let (a, b) = (to_lab(a), to_lab(b));
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+// Compares the frame with the reference image, writes the diff image on failure
+fn check(name: &str, frame: image::RgbaImage) {
+    let golden = golden_path(name);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(golden.parent().unwrap()).unwrap();
+        frame.save(&golden).unwrap();
+        return;
+    }
+
+    let reference = image::open(&golden)
+        .unwrap_or_else(|err| panic!("Unable to open {}: {}", golden.display(), err))
+        .to_rgba8();
+    assert_eq!(
+        reference.dimensions(),
+        frame.dimensions(),
+        "{}: frame size differs from the reference",
+        name
+    );
+
+    let mut diff = image::RgbaImage::new(frame.width(), frame.height());
+    let mut mismatched = 0;
+    let mut delta_e_sum = 0.0;
+
+    for ((expected, actual), out) in reference.pixels().zip(frame.pixels()).zip(diff.pixels_mut()) {
+        let max_channel_diff = (0..4)
+            .map(|c| expected[c].abs_diff(actual[c]))
+            .max()
+            .unwrap();
+        if max_channel_diff > CHANNEL_TOLERANCE {
+            mismatched += 1;
+        }
+        delta_e_sum += delta_e(expected, actual);
+
+        // Mismatched pixels are red, the rest is a dimmed copy of the reference
+        *out = if max_channel_diff > CHANNEL_TOLERANCE {
+            image::Rgba([255, 0, 0, 255])
+        } else {
+            image::Rgba([expected[0] / 4, expected[1] / 4, expected[2] / 4, 255])
+        };
+    }
+
+    let pixel_count = (frame.width() * frame.height()) as f32;
+    let mismatch_ratio = mismatched as f32 / pixel_count;
+    let mean_delta_e = delta_e_sum / pixel_count;
+
+    if mismatch_ratio > MAX_MISMATCH_RATIO || mean_delta_e > MAX_MEAN_DELTA_E {
+        let diff_file = diff_path(name);
+        diff.save(&diff_file).unwrap();
+        frame.save(diff_file.with_file_name(format!("{}_actual.png", name))).unwrap();
+        panic!(
+            "{}: {:.2}% of pixels differ, mean delta E {:.3} (diff image: {})",
+            name,
+            mismatch_ratio * 100.0,
+            mean_delta_e,
+            diff_file.display()
+        );
+    }
+}
+
+#[test]
+fn triangle() {
+    let frame = framework::run_headless::<triangle::Example>(WIDTH, HEIGHT, FRAMES);
+    check("triangle", frame);
+}
+
+#[test]
+fn cube() {
+    let frame = framework::run_headless::<cube::Example>(WIDTH, HEIGHT, FRAMES);
+    check("cube", frame);
+}
+
+#[test]
+fn gpu_driven_rendering() {
+    let frame = framework::run_headless::<gpu_driven_rendering::Example>(WIDTH, HEIGHT, FRAMES);
+    check("gpu-driven-rendering", frame);
+}
+
+#[test]
+fn post_processing() {
+    let frame = framework::run_headless::<post_processing::Example>(WIDTH, HEIGHT, FRAMES);
+    check("post-processing", frame);
+}
+
+// The frame buffers of other formats get the result through the blit, the sRGB ones decode it first
+#[test]
+fn post_processing_blit() {
+    for format in [wgpu::TextureFormat::Bgra8Unorm, wgpu::TextureFormat::Bgra8UnormSrgb] {
+        let frame = framework::run_headless_with_format::<post_processing::Example>(WIDTH, HEIGHT, FRAMES, Some(format));
+        check("post-processing", frame);
+    }
+}
@@ -0,0 +1,46 @@
+use paralelizace_3d_renderovani::shapes::{self, Indices, Mesh, MeshId, Vertex};
+
+// Mesh of separate triangles with the given number of vertices
+fn triangles(m_type: MeshId, vertex_count: u32) -> Mesh {
+    Mesh {
+        m_type,
+        vertices: vec![Vertex::new([0.0; 3], [0.0; 2]); vertex_count as usize],
+        indices: (0..vertex_count / 3 * 3).collect(),
+        lods: Vec::new(),
+    }
+}
+
+#[test]
+fn index_format_follows_vertex_count() {
+    let a = triangles(MeshId(10), 30000);
+    let b = triangles(MeshId(11), 35536);
+    let (vertices, indices) = shapes::merge_index_vertex_data(&vec![&a, &b]).unwrap();
+    assert_eq!(vertices.len(), 65536);
+    assert_eq!(indices.format(), wgpu::IndexFormat::Uint16);
+    assert_eq!(indices.get(indices.len() - 1), Some(65535 - 35536 % 3));
+    assert_eq!(indices.as_bytes().len(), indices.len() * 2);
+
+    // One more vertex doesn't fit 16 bits, the offsets of the meshes must not wrap around
+    let c = triangles(MeshId(12), 3);
+    let (vertices, indices) = shapes::merge_index_vertex_data(&vec![&a, &b, &c]).unwrap();
+    assert_eq!(vertices.len(), 65539);
+    assert_eq!(indices.format(), wgpu::IndexFormat::Uint32);
+    assert_eq!(indices.get(indices.len() - 3), Some(65536));
+    assert_eq!(indices.as_bytes().len(), indices.len() * 4);
+    match indices {
+        Indices::U32(indices) => assert_eq!(indices[..3], [0, 1, 2]),
+        Indices::U16(_) => unreachable!(),
+    }
+}
+
+#[test]
+fn index_out_of_range_is_an_error() {
+    let mut mesh = triangles(MeshId(10), 3);
+    mesh.indices[2] = 3;
+    let err = shapes::merge_index_vertex_data(&vec![&mesh]).unwrap_err();
+    assert!(err.contains("index 3"), "{}", err);
+
+    let (vertices, indices) = shapes::merge_index_vertex_data(&vec![]).unwrap();
+    assert!(vertices.is_empty() && indices.is_empty());
+    assert_eq!(indices.format(), wgpu::IndexFormat::Uint16);
+}
@@ -0,0 +1,160 @@
+use paralelizace_3d_renderovani::shapes::{self, MeshId, ObjModel, ObjScene, TextureId};
+
+// Quad made of two triangles and a triangle in another group with its own material
+const OBJ: &str = "\
+# Test model
+mtllib test.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0 1.0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+g quad
+usemtl red
+f 1/1/1 2/2/1 3/3/1 4/4/1
+g triangle
+usemtl blue
+f -4//1 -3//1 -2//1
+";
+
+const MTL: &str = "\
+newmtl red
+Kd 1 0 0
+map_Kd -s 1 1 1 textures/red.png
+newmtl blue
+Kd 0 0 1
+";
+
+#[test]
+fn groups_share_vertices() {
+    let model = ObjModel::parse(OBJ).unwrap();
+    assert_eq!(model.material_libs, vec!["test.mtl"]);
+    assert_eq!(model.groups.len(), 2);
+
+    let quad = &model.groups[0];
+    assert_eq!((quad.name.as_str(), quad.material.as_deref()), ("quad", Some("red")));
+    assert_eq!(quad.vertices.len(), 4);
+    assert_eq!(quad.indices, vec![0, 1, 2, 0, 2, 3]);
+    assert_eq!(quad.vertices[2].position(), [1.0, 1.0, 0.0]);
+    assert_eq!(quad.vertices[3].tex_coord(), [0.0, 0.0]); // V is flipped
+    assert_eq!(quad.vertices[0].normal(), [0.0, 0.0, 1.0]);
+
+    // Negative indices, no UVs
+    let triangle = &model.groups[1];
+    assert_eq!((triangle.name.as_str(), triangle.material.as_deref()), ("triangle", Some("blue")));
+    assert_eq!(triangle.vertices[0].position(), [0.0, 0.0, 0.0]);
+    assert_eq!(triangle.vertices[0].tex_coord(), [0.0, 0.0]);
+    assert_eq!(triangle.indices, vec![0, 1, 2]);
+
+    let mesh = model.to_mesh(MeshId::FIRST_CUSTOM).unwrap();
+    assert_eq!(mesh.m_type, MeshId::FIRST_CUSTOM);
+    assert_eq!(mesh.vertices.len(), 7);
+    assert_eq!(&mesh.indices[6..], &[4, 5, 6]);
+}
+
+#[test]
+fn vertices_differing_in_uv_are_split() {
+    let model = ObjModel::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 1\ns 1\nf 1/1 2/1 3/1\nf 1/2 3/1 2/1\n").unwrap();
+    assert_eq!(model.groups[0].vertices.len(), 4);
+    assert_eq!(model.groups[0].indices, vec![0, 1, 2, 3, 2, 1]);
+}
+
+#[test]
+fn faces_outside_smoothing_groups_are_faceted() {
+    // Two faces of a roof meeting at the ridge (vertices 2 and 3)
+    let roof = "v 0 0 0\nv 1 0 0\nv 1 0 1\nv 0 0 1\nv 0 1 0\nv 1 1 0\n";
+    let faces = "f 1 2 3 4\nf 4 3 6 5\n";
+
+    let flat = ObjModel::parse(&format!("{}{}", roof, faces)).unwrap();
+    assert_eq!(flat.groups[0].vertices.len(), 8);
+    assert_eq!(flat.groups[0].vertices[0].normal(), [0.0, -1.0, 0.0]);
+
+    let smooth = ObjModel::parse(&format!("{}s 1\n{}", roof, faces)).unwrap();
+    let vertices = &smooth.groups[0].vertices;
+    assert_eq!(vertices.len(), 6);
+    let ridge = vertices[2].normal();
+    assert!(ridge[1] < 0.5 && ridge[2] > 0.0, "{:?}", ridge); // Between the normals of the faces
+
+    // s off ends the group
+    let off = ObjModel::parse(&format!("{}s 1\ns off\n{}", roof, faces)).unwrap();
+    assert_eq!(off.groups[0].vertices.len(), 8);
+}
+
+#[test]
+fn errors_have_line_numbers() {
+    let err = ObjModel::parse("v 0 0 0\nv 1 0\n").unwrap_err();
+    assert_eq!(err, "line 2: Expected 3 numbers, found 2");
+
+    let err = ObjModel::parse("v 0 0 0\nv 1 0 0\nv 0 x 0\n").unwrap_err();
+    assert!(err.starts_with("line 3: Invalid number"), "{}", err);
+
+    let err = ObjModel::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n").unwrap_err();
+    assert_eq!(err, "line 4: Position index 4 is out of range");
+
+    let err = ObjModel::parse("v 0 0 0\nf 1 1\n").unwrap_err();
+    assert!(err.starts_with("line 2: Face needs at least 3 vertices"), "{}", err);
+
+    let err = ObjModel::parse("s x\n").unwrap_err();
+    assert_eq!(err, "line 1: Invalid smoothing group \"x\"");
+
+    let err = shapes::parse_mtl("Kd 1 0 0\n").unwrap_err();
+    assert_eq!(err, "line 1: Kd before newmtl");
+}
+
+#[test]
+fn load_reads_materials() {
+    let dir = std::env::temp_dir().join("paralelizace_obj");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("test.obj"), OBJ).unwrap();
+    std::fs::write(dir.join("test.mtl"), MTL).unwrap();
+
+    let model = ObjModel::load(&dir.join("test.obj")).unwrap();
+    let red = model.material("red").unwrap();
+    assert_eq!(red.diffuse, [1.0, 0.0, 0.0]);
+    assert_eq!(red.diffuse_texture.as_deref(), Some("textures/red.png"));
+    assert_eq!(model.material("blue").unwrap().diffuse_texture, None);
+
+    // Batched and merged with the procedural meshes
+    let mut cube = shapes::Mesh { m_type: MeshId::CUBE, vertices: Vec::new(), indices: Vec::new(), lods: Vec::new() };
+    cube.generate_vertices();
+    let loaded = model.to_mesh(MeshId::FIRST_CUSTOM).unwrap();
+    let (vertices, indices) = shapes::merge_index_vertex_data(&vec![&cube, &loaded]).unwrap();
+    assert_eq!(vertices.len(), cube.vertices.len() + 7);
+    assert_eq!(indices.get(cube.indices.len()), Some(cube.vertices.len() as u32));
+
+    assert!(ObjModel::load(&dir.join("missing.obj")).unwrap_err().starts_with("Can't read"));
+}
+
+#[test]
+fn scene_turns_materials_into_textures() {
+    let dir = std::env::temp_dir().join("paralelizace_obj_scene");
+    std::fs::create_dir_all(dir.join("textures")).unwrap();
+    std::fs::write(dir.join("test.obj"), OBJ).unwrap();
+    std::fs::write(dir.join("test.mtl"), MTL).unwrap();
+    // Gray texture, multiplied by the red color
+    image::RgbaImage::from_pixel(2, 2, image::Rgba([128, 128, 128, 255])).save(dir.join("textures/red.png")).unwrap();
+
+    let scene = ObjScene::load(&dir.join("test.obj"), MeshId::FIRST_CUSTOM, TextureId(3)).unwrap();
+    assert_eq!(scene.meshes.len(), 2);
+    assert_eq!(scene.meshes[1].m_type, MeshId(MeshId::FIRST_CUSTOM.0 + 1));
+    assert!(scene.meshes.iter().all(|mesh| mesh.lod_count() >= 1));
+
+    assert_eq!(scene.textures.len(), 2);
+    assert_eq!(scene.textures[0].dimensions(), (2, 2));
+    assert_eq!(scene.textures[0].get_pixel(1, 1).0, [128, 0, 0, 255]);
+    assert_eq!(scene.textures[1].dimensions(), (1, 1));
+    assert_eq!(scene.textures[1].get_pixel(0, 0).0, [0, 0, 255, 255]);
+
+    let types: Vec<_> = scene.objects.iter().map(|object| (object.m_type, object.t_type)).collect();
+    assert_eq!(types, vec![(MeshId::FIRST_CUSTOM, TextureId(3)), (MeshId(MeshId::FIRST_CUSTOM.0 + 1), TextureId(4))]);
+    assert_eq!(scene.objects[0].transform_m, glam::Mat4::IDENTITY);
+
+    // Missing textures are reported with their path
+    std::fs::remove_file(dir.join("textures/red.png")).unwrap();
+    let err = ObjScene::load(&dir.join("test.obj"), MeshId::FIRST_CUSTOM, TextureId(0)).err().unwrap();
+    assert!(err.contains("red.png"), "{}", err);
+}
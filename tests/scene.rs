@@ -0,0 +1,186 @@
+mod common;
+
+use paralelizace_3d_renderovani::culling::{CullInstance, DrawCommand};
+use paralelizace_3d_renderovani::framework;
+use paralelizace_3d_renderovani::scene::{Changes, GpuObject, Scene, SceneBuffers};
+use paralelizace_3d_renderovani::shapes::{Mesh, MeshId, Object, TextureId};
+use glam::{Mat4, Vec3};
+use std::ops::Range;
+
+fn meshes() -> (Mesh, Mesh) {
+    let mut cube = Mesh { m_type: MeshId::CUBE, vertices: Vec::new(), indices: Vec::new(), lods: Vec::new() };
+    let mut sphere = Mesh { m_type: MeshId::SPHERE, vertices: Vec::new(), indices: Vec::new(), lods: Vec::new() };
+    cube.generate_vertices();
+    sphere.generate_vertices();
+    (cube, sphere)
+}
+
+fn object(x: f32, m_type: MeshId, t_type: TextureId) -> Object {
+    Object { transform_m: Mat4::from_translation(Vec3::new(x, 0.0, 0.0)), m_type, t_type }
+}
+
+// 36 indices of the cube followed by the levels of detail of the sphere, 100 indices each
+fn index_range(m_type: MeshId, lod: u32) -> (u32, u32) {
+    match m_type {
+        MeshId::SPHERE => (100, 36 + 100 * lod),
+        _ => (36, 0),
+    }
+}
+
+#[test]
+fn slots_are_reused() {
+    let (cube, sphere) = meshes();
+    let mut scene = Scene::new(&[&cube, &sphere]);
+
+    let a = scene.insert(object(0.0, MeshId::CUBE, TextureId::BLUE));
+    let b = scene.insert(object(1.0, MeshId::CUBE, TextureId::BLUE));
+    let c = scene.insert(object(2.0, MeshId::SPHERE, TextureId::RED));
+    assert_eq!((a.slot(), b.slot(), c.slot()), (0, 1, 2));
+    assert_eq!(scene.len(), 3);
+
+    assert_eq!(scene.remove(b).map(|o| o.transform_m.w_axis.x), Some(1.0));
+    assert_eq!(scene.remove(b), None);
+    assert_eq!(scene.len(), 2);
+    assert_eq!(scene.slot_count(), 3);
+
+    // The freed slot is reused, the old handle stays invalid
+    let d = scene.insert(object(3.0, MeshId::SPHERE, TextureId::RED));
+    assert_eq!(d.slot(), 1);
+    assert_ne!(d, b);
+    assert!(scene.get(b).is_none());
+    assert!(!scene.set_transform(b, Mat4::IDENTITY));
+    assert_eq!(scene.get(d).unwrap().transform_m.w_axis.x, 3.0);
+    assert_eq!(scene.iter().map(|(id, _)| id).collect::<Vec<_>>(), vec![a, d, c]);
+}
+
+// Changes of a single range of slots
+fn changed(slots: Range<u32>, counts: bool, batches: bool) -> Changes {
+    Changes { slots: vec![slots], counts, batches }
+}
+
+#[test]
+fn changes_cover_modified_slots() {
+    let (cube, sphere) = meshes();
+    let mut scene = Scene::new(&[&cube, &sphere]);
+
+    let ids: Vec<_> = (0..5).map(|i| scene.insert(object(i as f32, MeshId::CUBE, TextureId::BLUE))).collect();
+    assert_eq!(scene.take_changes(), changed(0..5, true, true));
+    assert_eq!(scene.take_changes(), Changes::default());
+
+    // Moving objects doesn't change the draw commands
+    scene.set_transform(ids[3], Mat4::IDENTITY);
+    scene.set_transform(ids[1], Mat4::IDENTITY);
+    assert_eq!(scene.take_changes(), changed(1..4, false, false));
+
+    scene.remove(ids[4]);
+    assert_eq!(scene.take_changes(), changed(4..5, true, false));
+
+    scene.insert(object(0.0, MeshId::SPHERE, TextureId::YELLOW));
+    assert_eq!(scene.take_changes(), changed(4..5, true, true));
+}
+
+#[test]
+fn distant_changes_are_separate_ranges() {
+    let (cube, sphere) = meshes();
+    let mut scene = Scene::new(&[&cube, &sphere]);
+    let ids: Vec<_> = (0..100).map(|i| scene.insert(object(i as f32, MeshId::CUBE, TextureId::BLUE))).collect();
+    scene.take_changes();
+
+    // The first and the last slot don't upload the slots between them
+    scene.set_transform(ids[99], Mat4::IDENTITY);
+    scene.set_transform(ids[0], Mat4::IDENTITY);
+    assert_eq!(scene.take_changes().slots, vec![0..1, 99..100]);
+
+    // Near neighbours are merged, also when a slot joins two ranges
+    for i in [50, 10, 12, 20, 80, 16] {
+        scene.set_transform(ids[i], Mat4::IDENTITY);
+    }
+    assert_eq!(scene.take_changes().slots, vec![10..21, 50..51, 80..81]);
+}
+
+#[test]
+fn commands_follow_batch_counts() {
+    let (cube, sphere) = meshes();
+    let mut scene = Scene::new(&[&cube, &sphere]);
+
+    let a = scene.insert(object(0.0, MeshId::CUBE, TextureId::BLUE));
+    scene.insert(object(1.0, MeshId::SPHERE, TextureId::RED));
+    scene.insert(object(2.0, MeshId::CUBE, TextureId::BLUE));
+    scene.insert(object(3.0, MeshId::CUBE, TextureId::YELLOW));
+    scene.remove(a);
+
+    // The sphere has a command for each of its 4 levels of detail
+    assert_eq!((scene.lod_count(MeshId::CUBE), scene.lod_count(MeshId::SPHERE)), (1, 4));
+    let sphere = |lod: u32| DrawCommand {
+        index_count: 100,
+        instance_count: 1,
+        base_index: 36 + 100 * lod,
+        vertex_offset: 0,
+        base_instance: 1 + lod,
+    };
+    assert_eq!(
+        scene.commands(index_range),
+        vec![
+            DrawCommand { index_count: 36, instance_count: 1, base_index: 0, vertex_offset: 0, base_instance: 0 },
+            sphere(0),
+            sphere(1),
+            sphere(2),
+            sphere(3),
+            DrawCommand { index_count: 36, instance_count: 1, base_index: 0, vertex_offset: 0, base_instance: 5 },
+        ]
+    );
+
+    // Free slots are skipped by the culling, the others refer to the first command of their batch
+    let instances = scene.cull_instances(0..scene.slot_count());
+    assert_eq!(instances[0], CullInstance::EMPTY);
+    assert_eq!((instances[1].batch, instances[1].lods), (1, 4));
+    assert_eq!((instances[3].batch, instances[3].lods), (5, 1));
+    assert_eq!(scene.objects(1..2), vec![GpuObject { transform_id: 1, texture_id: TextureId::RED.index() }]);
+}
+
+#[test]
+#[should_panic]
+fn unknown_mesh_is_rejected() {
+    let (cube, _) = meshes();
+    let mut scene = Scene::new(&[&cube]);
+    scene.insert(object(0.0, MeshId::CYLINDER, TextureId::BLUE));
+}
+
+#[test]
+fn gpu_buffers_grow_and_upload_changes() {
+    let framework::HeadlessSetup { device, queue, .. } =
+        pollster::block_on(framework::setup_headless(&framework::Args::default()));
+
+    let (cube, sphere) = meshes();
+    let mut scene = Scene::new(&[&cube, &sphere]);
+    let a = scene.insert(object(0.0, MeshId::CUBE, TextureId::BLUE));
+    scene.insert(object(1.0, MeshId::SPHERE, TextureId::RED));
+    scene.take_changes();
+
+    let mut buffers = SceneBuffers::new(&device, &scene);
+    assert_eq!(buffers.capacity(), 2);
+
+    // Only the changed slot is written
+    scene.set_transform(a, Mat4::from_translation(Vec3::new(7.0, 0.0, 0.0)));
+    let slots = scene.take_changes().slots;
+    assert!(!buffers.upload(&device, &queue, &scene, &slots));
+    let matrices: Vec<[f32; 16]> = bytemuck::cast_slice(&common::read_buffer(&device, &queue, buffers.matrices_buffer())).to_vec();
+    assert_eq!(matrices, scene.matrices(0..2));
+    assert_eq!(matrices[0][12], 7.0);
+
+    // The capacity doubles
+    scene.insert(object(2.0, MeshId::CUBE, TextureId::BLUE));
+    let slots = scene.take_changes().slots;
+    assert!(buffers.upload(&device, &queue, &scene, &slots));
+    assert_eq!(buffers.capacity(), 4);
+
+    let objects: Vec<GpuObject> = bytemuck::cast_slice(&common::read_buffer(&device, &queue, buffers.objects_buffer())).to_vec();
+    assert_eq!(objects[..3], scene.objects(0..3)[..]);
+    assert_eq!(objects[3], GpuObject::default());
+    let bounds = common::read_buffer(&device, &queue, buffers.bounds_buffer());
+    assert_eq!(bounds, bytemuck::cast_slice::<_, u8>(&{
+        let mut bounds = scene.gpu_bounds(0..3);
+        bounds.resize(4, Default::default());
+        bounds
+    }).to_vec());
+}
@@ -0,0 +1,160 @@
+use paralelizace_3d_renderovani::scene::{SceneDescription, SceneError};
+use paralelizace_3d_renderovani::shapes::{MeshId, Object, Shape, TextureId};
+use glam::{Mat4, Quat, Vec3};
+use std::path::Path;
+
+const SCENE: &str = r#"// Comment with (brackets, "quotes"
+(
+    textures: [
+        (name: "blue", path: "blue.png"),
+        (name: "red", path: "red.png"),
+    ],
+    objects: [
+        (mesh: "cube", texture: "blue"),
+        /* Rotated
+           cylinder */ (
+            mesh: "cylinder",
+            texture: "red",
+            position: (1.0, 2.0, 3.0),
+            rotation: (0.0, 0.0, 90.0),
+            scale: (2.0, 2.0, 2.0),
+        ),
+    ],
+)
+"#;
+
+fn error(source: &str) -> SceneError {
+    SceneDescription::parse(source).unwrap_err()
+}
+
+#[test]
+fn parse_objects() {
+    let scene = SceneDescription::parse(SCENE).unwrap();
+    assert_eq!(scene.texture_paths(), vec![std::path::PathBuf::from("blue.png"), "red.png".into()]);
+
+    let objects = scene.objects().unwrap();
+    assert_eq!(objects[0], Object { transform_m: Mat4::IDENTITY, m_type: MeshId::CUBE, t_type: TextureId::BLUE });
+    assert_eq!(objects[1].m_type, MeshId::CYLINDER);
+    let point = objects[1].transform_m.transform_point3(Vec3::X);
+    assert!(point.abs_diff_eq(Vec3::new(1.0, 4.0, 3.0), 1e-5));
+}
+
+#[test]
+fn errors_have_line_numbers() {
+    // Syntax error
+    let err = error(&SCENE.replace("texture: \"red\",\n", "texture: \"red\"\n"));
+    assert_eq!(err.line, 13);
+
+    let err = error(&SCENE.replace("mesh: \"cube\"", "mesh: Cube"));
+    assert_eq!(err.line, 8);
+
+    // Validation errors point at the element
+    let err = error(&SCENE.replace("texture: \"red\",\n", "texture: \"yellow\",\n"));
+    assert_eq!(err.line, 10);
+    assert!(err.to_string().starts_with("line 10: Texture \"yellow\""), "{}", err);
+
+    let err = error(&SCENE.replace("(name: \"red\"", "(name: \"blue\""));
+    assert_eq!((err.line, err.message.as_str()), (5, "Texture \"blue\" is listed twice"));
+
+    let err = error(&SCENE.replace("scale: (2.0, 2.0, 2.0)", "scale: (2.0, 0.0, 2.0)"));
+    assert_eq!(err.line, 10);
+
+    let err = error(&SCENE.replace("\"cylinder\"", "\"teapot\""));
+    assert_eq!(err.line, 10);
+
+    let meshes = "(\n    meshes: [\n        (name: \"a\", path: \"a.obj\"),\n        (name: \"sphere\", path: \"b.obj\"),\n    ],\n)";
+    let err = error(meshes);
+    assert_eq!((err.line, err.message.as_str()), (4, "Mesh name \"sphere\" is used by a procedural mesh"));
+}
+
+#[test]
+fn shapes_follow_meshes() {
+    let source = r#"(
+        meshes: [(name: "teapot", path: "teapot.obj")],
+        shapes: [
+            (name: "plank", shape: Box(size: (4.0, 0.5, 0.2))),
+            (name: "tiles", shape: SubdividedBox(size: (1.0, 1.0, 1.0), subdivisions: (2, 2, 1))),
+        ],
+        textures: [(name: "blue", path: "blue.png")],
+        objects: [(mesh: "tiles", texture: "blue"), (mesh: "plank", texture: "blue")],
+    )"#;
+    let scene = SceneDescription::parse(source).unwrap();
+    let plank = Shape::Box { size: [4.0, 0.5, 0.2] };
+    assert_eq!(scene.shape_meshes()[0], (MeshId(MeshId::FIRST_CUSTOM.0 + 1), plank));
+    let ids: Vec<MeshId> = scene.objects().unwrap().iter().map(|object| object.m_type).collect();
+    assert_eq!(ids, vec![MeshId(MeshId::FIRST_CUSTOM.0 + 2), MeshId(MeshId::FIRST_CUSTOM.0 + 1)]);
+
+    let err = error(&source.replace("(2, 2, 1)", "(2, 0, 1)"));
+    assert_eq!(err.to_string(), "line 5: Shape \"tiles\": Subdivisions have to be at least 1");
+    let err = error(&source.replace("\"plank\", shape", "\"teapot\", shape"));
+    assert_eq!((err.line, err.message.as_str()), (4, "Mesh \"teapot\" is listed twice"));
+
+    let example = SceneDescription::load(Path::new("examples/gpu-driven-rendering/shapes.ron")).unwrap();
+    assert_eq!(example.shape_meshes().len(), 8);
+}
+
+#[test]
+fn saved_scene_can_be_parsed() {
+    let objects = vec![
+        Object {
+            transform_m: Mat4::from_scale_rotation_translation(
+                Vec3::new(1.0, 2.0, 0.5),
+                Quat::from_rotation_z(0.5) * Quat::from_rotation_x(0.25),
+                Vec3::new(-3.0, 1.0, 0.0),
+            ),
+            m_type: MeshId::SPHERE,
+            t_type: TextureId(1),
+        },
+        Object { transform_m: Mat4::IDENTITY, m_type: MeshId::FIRST_CUSTOM, t_type: TextureId::BLUE },
+        Object { transform_m: Mat4::IDENTITY, m_type: MeshId(MeshId::FIRST_CUSTOM.0 + 1), t_type: TextureId(1) },
+    ];
+    let meshes = vec![("teapot".to_string(), "teapot.obj".to_string())];
+    let shapes = vec![("plank".to_string(), Shape::SubdividedBox { size: [4.0, 0.5, 0.2], subdivisions: [8, 1, 1] })];
+    let textures = vec![("blue".to_string(), "blue.png".to_string()), ("yellow".to_string(), "yellow.png".to_string())];
+    let scene = SceneDescription::from_objects(&objects, &meshes, &shapes, &textures).unwrap();
+
+    let parsed = SceneDescription::parse(&scene.to_ron()).unwrap();
+    assert_eq!(parsed.meshes, scene.meshes);
+    assert_eq!(parsed.shapes, scene.shapes);
+    assert_eq!(parsed.textures, scene.textures);
+    assert_eq!(parsed.mesh_paths(), vec![(MeshId::FIRST_CUSTOM, "teapot.obj".into())]);
+    for (parsed, object) in parsed.objects().unwrap().iter().zip(&objects) {
+        assert_eq!((parsed.m_type, parsed.t_type), (object.m_type, object.t_type));
+        assert!(parsed.transform_m.abs_diff_eq(object.transform_m, 1e-5));
+    }
+}
+
+#[test]
+fn scenes_built_in_code_are_checked() {
+    let object = Object { transform_m: Mat4::IDENTITY, m_type: MeshId(MeshId::FIRST_CUSTOM.0 + 1), t_type: TextureId::BLUE };
+    let meshes = vec![("teapot".to_string(), "teapot.obj".to_string())];
+    let textures = vec![("blue".to_string(), "blue.png".to_string())];
+    let err = SceneDescription::from_objects(&[object], &meshes, &[], &textures).unwrap_err();
+    assert_eq!(err.to_string(), format!("Mesh {:?} of object 0 has no name", object.m_type));
+    let err = SceneDescription::from_objects(&[Object { m_type: MeshId::CUBE, ..object }], &meshes, &[], &[]).unwrap_err();
+    assert_eq!(err.to_string(), "Texture TextureId(0) of object 0 has no name");
+
+    // The fields can be changed after parsing
+    let mut scene = SceneDescription::parse(SCENE).unwrap();
+    scene.objects[0].mesh = "teapot".to_string();
+    assert!(scene.objects().unwrap_err().message.starts_with("Mesh \"teapot\" of object 0"));
+}
+
+#[test]
+fn load_resolves_texture_paths() {
+    let scene = SceneDescription::load(Path::new("examples/gpu-driven-rendering/scene.ron")).unwrap();
+    assert_eq!(scene.objects().unwrap().len(), 4);
+    for path in scene.texture_paths() {
+        assert!(path.is_file(), "{}", path.display());
+    }
+
+    let dir = std::env::temp_dir().join("paralelizace_scene_file");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("scene.ron");
+    std::fs::write(&path, SCENE).unwrap();
+    let err = SceneDescription::load(&path).unwrap_err();
+    assert_eq!(err.line, 4);
+
+    let err = SceneDescription::load(&dir.join("missing.ron")).unwrap_err();
+    assert_eq!(err.line, 0);
+}
@@ -0,0 +1,152 @@
+use paralelizace_3d_renderovani::shapes::{Mesh, MeshId, ObjModel, Shape, Vertex};
+use glam::Vec3;
+use std::collections::HashMap;
+
+// Number of triangles of every edge, the ends of the edges are compared by their rounded positions
+fn edges(vertices: &[Vertex], indices: &[u32]) -> HashMap<([i32; 3], [i32; 3]), u32> {
+    let mut edges = HashMap::new();
+    for t in indices.chunks_exact(3) {
+        for k in 0..3 {
+            let [a, b] = [t[k], t[(k + 1) % 3]].map(|i| vertices[i as usize].position().map(|v| (v * 1e5).round() as i32));
+            *edges.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+        }
+    }
+    edges
+}
+
+// The triangles keep facing the direction of their vertex normals
+fn assert_not_flipped(vertices: &[Vertex], indices: &[u32]) {
+    for t in indices.chunks_exact(3) {
+        let p = [t[0], t[1], t[2]].map(|i| Vec3::from_array(vertices[i as usize].position()));
+        let face = (p[1] - p[0]).cross(p[2] - p[0]);
+        let normal: Vec3 = t.iter().map(|&i| Vec3::from_array(vertices[i as usize].normal())).sum();
+        assert!(face.dot(normal) > 0.0, "triangle {:?} is flipped", t);
+    }
+}
+
+#[test]
+fn sphere_stays_closed() {
+    let mut sphere = Mesh { m_type: MeshId::SPHERE, vertices: Vec::new(), indices: Vec::new(), lods: Vec::new() };
+    sphere.generate_vertices();
+    let triangles = sphere.indices.len() / 3;
+
+    let simplified = sphere.simplify(0.25).unwrap();
+    assert!(simplified.indices.len() / 3 <= triangles / 4, "{} triangles left", simplified.indices.len() / 3);
+    assert!(simplified.error > 0.0 && simplified.error < 0.2, "error {}", simplified.error);
+    assert_not_flipped(&sphere.vertices, &simplified.indices);
+
+    // Every edge still has two triangles, the seam didn't open
+    assert!(edges(&sphere.vertices, &simplified.indices).values().all(|&count| count == 2));
+
+    // No triangle spans the seam, which would stretch the whole texture over it
+    for t in simplified.indices.chunks_exact(3) {
+        let u = t.iter().map(|&i| sphere.vertices[i as usize].tex_coord()[0]);
+        let (min, max) = u.fold((f32::MAX, f32::MIN), |(min, max), u| (min.min(u), max.max(u)));
+        assert!(max - min < 0.5, "triangle {:?} spans U {}..{}", t, min, max);
+    }
+
+    // Keeping all triangles changes nothing
+    let full = sphere.simplify(1.0).unwrap();
+    assert_eq!((full.indices.len(), full.error), (sphere.indices.len(), 0.0));
+}
+
+#[test]
+fn borders_stay_in_place() {
+    let plane = Shape::Plane { size: [2.0, 2.0], subdivisions: [8, 8] }.to_mesh(MeshId::FIRST_CUSTOM);
+    let simplified = plane.simplify(0.1).unwrap();
+    assert!(simplified.indices.len() / 3 <= 12, "{} triangles left", simplified.indices.len() / 3);
+    assert!(simplified.error < 1e-4, "error {}", simplified.error);
+    assert_not_flipped(&plane.vertices, &simplified.indices);
+
+    // The border edges lie on the sides of the plane and cover all of them, the corners are kept
+    let mut length = 0.0;
+    for ((a, b), count) in edges(&plane.vertices, &simplified.indices) {
+        let [a, b] = [a, b].map(|p| Vec3::from_array(p.map(|v| v as f32 / 1e5)));
+        if count == 1 {
+            assert!((a.x == b.x && a.x.abs() == 1.0) || (a.y == b.y && a.y.abs() == 1.0), "{} {}", a, b);
+            length += a.distance(b);
+        }
+    }
+    assert!((length - 8.0).abs() < 1e-5);
+}
+
+// Wavy terrain of 12x12 quads in the XY plane, the left and the right half have their own UVs
+fn terrain() -> String {
+    let mut obj = String::from("s 1\n");
+    for y in 0..=12 {
+        for x in 0..=12 {
+            let z = 0.1 * (x as f32 * 0.5).sin() * (y as f32 * 0.5).cos();
+            obj += &format!("v {} {} {}\n", x, y, z);
+        }
+    }
+    for half in 0..2 {
+        for y in 0..=12 {
+            for x in 0..=12 {
+                obj += &format!("vt {} {}\n", x as f32 / 12.0 + half as f32, y as f32 / 12.0);
+            }
+        }
+    }
+
+    // The quads of the right half use the second UVs, so the vertices at x = 6 are split
+    for y in 0..12 {
+        for x in 0..12 {
+            let half = if x < 6 { 0 } else { 169 };
+            let corners = [(x, y), (x + 1, y), (x + 1, y + 1), (x, y + 1)].map(|(x, y)| {
+                let v = y * 13 + x + 1;
+                format!("{}/{}", v, v + half)
+            });
+            obj += &format!("f {} {} {}\nf {} {} {}\n", corners[0], corners[1], corners[2], corners[0], corners[2], corners[3]);
+        }
+    }
+    obj
+}
+
+#[test]
+fn imported_meshes_keep_seams_and_borders() {
+    let mesh = ObjModel::parse(&terrain()).unwrap().to_mesh(MeshId::FIRST_CUSTOM).unwrap();
+    assert_eq!((mesh.vertices.len(), mesh.indices.len() / 3), (13 * 14, 288));
+
+    let simplified = mesh.simplify(0.25).unwrap();
+    assert!(simplified.indices.len() / 3 <= 72, "{} triangles left", simplified.indices.len() / 3);
+    assert!(simplified.error > 0.0 && simplified.error < 0.2, "error {}", simplified.error);
+    assert_not_flipped(&mesh.vertices, &simplified.indices);
+
+    // Only the sides of the terrain are borders, the seam didn't open
+    for ((a, b), count) in edges(&mesh.vertices, &simplified.indices) {
+        let [a, b] = [a, b].map(|p| p.map(|v| v as f32 / 1e5));
+        let side = |axis: usize| a[axis] == b[axis] && (a[axis] == 0.0 || a[axis] == 12.0);
+        assert_eq!(count, if side(0) || side(1) { 1 } else { 2 }, "{:?} {:?}", a, b);
+    }
+
+    // Every triangle stays on its side of the seam with the UVs of that side
+    for t in simplified.indices.chunks_exact(3) {
+        let corners = t.iter().map(|&i| (mesh.vertices[i as usize].position()[0], mesh.vertices[i as usize].tex_coord()[0]));
+        let left = corners.clone().all(|(x, u)| x <= 6.0 && u <= 1.0);
+        let right = corners.clone().all(|(x, u)| x >= 6.0 && u >= 1.0);
+        assert!(left || right, "{:?}", corners.collect::<Vec<_>>());
+    }
+
+    // Imported meshes come with levels of detail, each with about half the triangles of the previous one
+    assert!(mesh.lod_count() > 2);
+    for lod in 1..mesh.lod_count() {
+        assert!(mesh.lod_indices(lod).len() * 4 <= mesh.lod_indices(lod - 1).len() * 3);
+    }
+}
+
+#[test]
+fn invalid_input_is_reported() {
+    let mesh = ObjModel::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap().to_mesh(MeshId::FIRST_CUSTOM).unwrap();
+    for ratio in [0.0, -0.5, 1.5, f32::NAN] {
+        assert!(mesh.simplify(ratio).is_err(), "{}", ratio);
+    }
+
+    let mut broken = Mesh { lods: Vec::new(), ..mesh };
+    broken.indices[2] = 3;
+    assert_eq!(broken.simplify(0.5).unwrap_err(), "Index 3 is out of range of 3 vertices");
+    broken.indices.pop();
+    assert!(broken.simplify(0.5).unwrap_err().contains("multiple of 3"));
+
+    // A single triangle can't be simplified, its corners are on the border
+    let triangle = ObjModel::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap().to_mesh(MeshId::FIRST_CUSTOM).unwrap();
+    assert_eq!(triangle.simplify(0.5).unwrap().indices, triangle.indices);
+}
@@ -0,0 +1,90 @@
+use paralelizace_3d_renderovani::shapes::{Mesh, MeshId, ObjModel, Vertex};
+use glam::{Vec3, Vec4Swizzles};
+
+fn mesh(m_type: MeshId) -> Mesh {
+    let mut mesh = Mesh { m_type, vertices: Vec::new(), indices: Vec::new(), lods: Vec::new() };
+    mesh.generate_vertices();
+    mesh
+}
+
+// Quad in the XY plane facing +Z, tex_coord maps the corners to UVs
+fn quad(tex_coord: impl Fn(f32, f32) -> [f32; 2]) -> Mesh {
+    let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+    Mesh {
+        m_type: MeshId::FIRST_CUSTOM,
+        vertices: corners.iter().map(|&(x, y)| Vertex::new([x, y, 0.0], tex_coord(x, y))).collect(),
+        indices: vec![0, 1, 2, 0, 2, 3],
+        lods: Vec::new(),
+    }
+}
+
+fn assert_close(a: Vec3, b: Vec3) {
+    assert!(a.distance(b) < 1e-5, "{} != {}", a, b);
+}
+
+#[test]
+fn procedural_meshes_have_tangent_frames() {
+    for m_type in [MeshId::CUBE, MeshId::CYLINDER, MeshId::SPHERE] {
+        let mesh = mesh(m_type);
+        for vertex in &mesh.vertices {
+            let normal = Vec3::from_array(vertex.normal());
+            let tangent = glam::Vec4::from_array(vertex.tangent());
+            assert!((normal.length() - 1.0).abs() < 1e-5, "{:?} {:?}", m_type, vertex);
+            assert!((tangent.xyz().length() - 1.0).abs() < 1e-5, "{:?} {:?}", m_type, vertex);
+            assert!(normal.dot(tangent.xyz()).abs() < 1e-5, "{:?} {:?}", m_type, vertex);
+            assert_eq!(tangent.w.abs(), 1.0);
+
+            let [u, v] = vertex.tex_coord();
+            assert!((0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v));
+        }
+    }
+
+    // Normals of the sphere point away from its center, U runs around the Z axis
+    let sphere = mesh(MeshId::SPHERE);
+    for vertex in &sphere.vertices {
+        assert_close(Vec3::from_array(vertex.normal()), Vec3::from_array(vertex.position()));
+    }
+    let equator = sphere.vertices.iter().find(|v| v.position()[2].abs() < 1e-5 && v.position()[0] > 0.999).unwrap();
+    assert_close(glam::Vec4::from_array(equator.tangent()).xyz(), Vec3::Y);
+}
+
+#[test]
+fn tangents_follow_uvs() {
+    // U along X and V (top-left origin) along -Y, the bitangent is cross(normal, tangent)
+    let mut mesh = quad(|x, y| [x, 1.0 - y]);
+    mesh.generate_normals();
+    mesh.generate_tangents();
+    for vertex in &mesh.vertices {
+        assert_eq!(vertex.normal(), [0.0, 0.0, 1.0]);
+        assert_eq!(vertex.tangent(), [1.0, 0.0, 0.0, -1.0]);
+    }
+
+    // Mirrored texture
+    let mut mesh = quad(|x, y| [1.0 - x, 1.0 - y]);
+    mesh.generate_normals();
+    mesh.generate_tangents();
+    assert_eq!(mesh.vertices[0].tangent(), [-1.0, 0.0, 0.0, 1.0]);
+
+    // Without UVs any tangent perpendicular to the normal
+    let mut mesh = quad(|_, _| [0.0, 0.0]);
+    mesh.generate_normals();
+    mesh.generate_tangents();
+    let tangent = glam::Vec4::from_array(mesh.vertices[0].tangent());
+    assert!((tangent.xyz().length() - 1.0).abs() < 1e-5 && tangent.z == 0.0);
+}
+
+#[test]
+fn only_missing_normals_are_generated() {
+    let mut mesh = quad(|x, y| [x, y]);
+    mesh.vertices[0] = Vertex::with_normal([0.0; 3], [0.0; 2], [0.0, 1.0, 0.0]);
+    mesh.generate_normals();
+    assert_eq!(mesh.vertices[0].normal(), [0.0, 1.0, 0.0]);
+    assert_eq!(mesh.vertices[1].normal(), [0.0, 0.0, 1.0]);
+
+    // Faces of an OBJ without vn
+    let model = ObjModel::parse("v 0 0 0\nv 0 1 0\nv 0 0 1\nf 1 2 3\n").unwrap();
+    for vertex in &model.groups[0].vertices {
+        assert_eq!(vertex.normal(), [1.0, 0.0, 0.0]);
+        assert_eq!(Vec3::from_array(vertex.normal()).dot(glam::Vec4::from_array(vertex.tangent()).xyz()), 0.0);
+    }
+}